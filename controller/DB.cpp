@@ -32,6 +32,8 @@ void DB::initNetwork(nlohmann::json &network)
 	if (!network.count("v4AssignMode")) network["v4AssignMode"] = {{"zt",false}};
 	if (!network.count("v6AssignMode")) network["v6AssignMode"] = {{"rfc4193",false},{"zt",false},{"6plane",false}};
 	if (!network.count("authTokens")) network["authTokens"] = {{}};
+	if (!network.count("tokenAuthorizationTime")) network["tokenAuthorizationTime"] = 0ULL;
+	if (!network.count("autoAuthorize")) network["autoAuthorize"] = nlohmann::json::array();
 	if (!network.count("capabilities")) network["capabilities"] = nlohmann::json::array();
 	if (!network.count("tags")) network["tags"] = nlohmann::json::array();
 	if (!network.count("routes")) network["routes"] = nlohmann::json::array();
@@ -65,6 +67,7 @@ void DB::initMember(nlohmann::json &member)
 	if (!member.count("revision")) member["revision"] = 0ULL;
 	if (!member.count("lastDeauthorizedTime")) member["lastDeauthorizedTime"] = 0ULL;
 	if (!member.count("lastAuthorizedTime")) member["lastAuthorizedTime"] = 0ULL;
+	if (!member.count("authorizationExpiry")) member["authorizationExpiry"] = 0ULL;
 	if (!member.count("lastAuthorizedCredentialType")) member["lastAuthorizedCredentialType"] = nlohmann::json();
 	if (!member.count("lastAuthorizedCredential")) member["lastAuthorizedCredential"] = nlohmann::json();
 	if (!member.count("vMajor")) member["vMajor"] = -1;
@@ -25,6 +25,7 @@
 #include <algorithm>
 #include <utility>
 #include <stdexcept>
+#include <chrono>
 #include <map>
 #include <thread>
 #include <memory>
@@ -56,6 +57,26 @@ using json = nlohmann::json;
 // Global maximum size of arrays in JSON objects
 #define ZT_CONTROLLER_MAX_ARRAY_SIZE 16384
 
+// Default size at which a network's history log is rotated
+#define ZT_CONTROLLER_HISTORY_DEFAULT_ROTATE_SIZE 1048576
+
+// Default and maximum number of rotated history logs kept per network
+#define ZT_CONTROLLER_HISTORY_DEFAULT_KEEP 1
+#define ZT_CONTROLLER_HISTORY_MAX_KEEP 100
+
+// Default time after its last entry that the history of a deleted network is removed
+#define ZT_CONTROLLER_HISTORY_DEFAULT_DELETED_RETENTION 2592000000LL
+
+// How often history retention is applied
+#define ZT_CONTROLLER_HISTORY_EXPIRY_CHECK_PERIOD 3600000
+
+// Default and maximum number of history entries returned per GET
+#define ZT_CONTROLLER_HISTORY_DEFAULT_LIMIT 100
+#define ZT_CONTROLLER_HISTORY_MAX_LIMIT 1000
+
+// Longest the expiry thread sleeps between deadlines, so a wall clock jump delays expiry by at most this
+#define ZT_CONTROLLER_AUTHORIZATION_EXPIRY_MAX_WAIT 60000
+
 namespace ZeroTier {
 
 namespace {
@@ -454,6 +475,90 @@ static bool _parseRule(json &r,ZT_VirtualNetworkRule &rule)
 	return false;
 }
 
+// Who to record as making a change through the API; callers sharing the
+// auth token can say who they are with an X-ZT1-Actor header.
+static std::string _historyActor(const std::map<std::string,std::string> &headers)
+{
+	std::string by("api");
+	const auto a = headers.find("x-zt1-actor");
+	if ((a != headers.end())&&(a->second.length() > 0))
+		by.append(":").append(a->second.substr(0,64));
+	return by;
+}
+
+// Join tokens are secrets, so history only records enough of each to tell
+// them apart.
+static json _historyRedact(const std::string &key,const json &v,const json &record)
+{
+	if ((key == "authTokens")&&(v.is_object())) {
+		json r = json::object();
+		for(auto t=v.begin();t!=v.end();++t)
+			r[t.key().substr(0,6) + "..."] = t.value();
+		return r;
+	}
+	if ((key == "lastAuthorizedCredential")&&(v.is_string())&&(record.is_object())&&(record.value("lastAuthorizedCredentialType",std::string()) == "token"))
+		return json(v.get<std::string>().substr(0,6) + "...");
+	return v;
+}
+
+// Calls fn with each line in the first 'end' bytes of f, last line first,
+// until fn returns false. Returns false if fn stopped it.
+template<typename F>
+static bool _historyReverseLines(FILE *f,const long end,F fn)
+{
+	std::vector<char> chunk(65536);
+	long pos = end;
+	std::string carry;
+	while (pos > 0) {
+		const long n = std::min(pos,(long)chunk.size());
+		pos -= n;
+		fseek(f,pos,SEEK_SET);
+		if (fread(chunk.data(),1,(size_t)n,f) != (size_t)n)
+			break;
+		std::string buf(chunk.data(),(size_t)n);
+		buf.append(carry);
+		std::size_t e = buf.length();
+		while (e > 0) {
+			const std::size_t nl = buf.rfind('\n',e - 1);
+			if ((nl == std::string::npos)&&(pos > 0))
+				break; // partial line, completed by the next chunk
+			const std::size_t b = (nl == std::string::npos) ? 0 : (nl + 1);
+			if ((e > b)&&(!fn(buf.substr(b,e - b))))
+				return false;
+			e = (nl == std::string::npos) ? 0 : nl;
+		}
+		carry = buf.substr(0,e);
+	}
+	return true;
+}
+
+// ZeroTier Central's API wraps the controller's own network and member
+// objects in "config" and names members by "nodeId". Unwrap these so they
+// can be imported like our own export.
+static void _unwrapCentralObject(json &obj)
+{
+	if ((obj.count("config"))&&(obj["config"].is_object())) {
+		json cfg(obj["config"]);
+		if ((!cfg.count("id"))&&(obj.count("nodeId")))
+			cfg["id"] = obj["nodeId"];
+		obj = cfg;
+	}
+}
+
+// Deauthorizes a member whose time limited (e.g. guest) authorization has
+// passed and releases its IPs. Returns true if the member was changed.
+static bool _expireAuthorization(json &member,const int64_t now)
+{
+	const int64_t authExpiry = (int64_t)OSUtils::jsonInt(member["authorizationExpiry"],0ULL);
+	if ((authExpiry <= 0)||(authExpiry > now)||(!OSUtils::jsonBool(member["authorized"],false)))
+		return false;
+	member["authorized"] = false;
+	member["lastDeauthorizedTime"] = now;
+	member["authorizationExpiry"] = 0ULL;
+	member["ipAssignments"] = json::array();
+	return true;
+}
+
 } // anonymous namespace
 
 EmbeddedNetworkController::EmbeddedNetworkController(Node *node,const char *ztPath,const char *dbPath, int listenPort, RedisConfig *rc) :
@@ -462,14 +567,27 @@ EmbeddedNetworkController::EmbeddedNetworkController(Node *node,const char *ztPa
 	_node(node),
 	_ztPath(ztPath),
 	_path(dbPath),
+	_historyRotateSize(ZT_CONTROLLER_HISTORY_DEFAULT_ROTATE_SIZE),
+	_historyKeep(ZT_CONTROLLER_HISTORY_DEFAULT_KEEP),
+	_historyMaxAge(0),
+	_historyDeletedRetention(ZT_CONTROLLER_HISTORY_DEFAULT_DELETED_RETENTION),
 	_sender((NetworkController::Sender *)0),
 	_db(this),
+	_authorizationExpiryRun(false),
 	_rc(rc)
 {
 }
 
 EmbeddedNetworkController::~EmbeddedNetworkController()
 {
+	{
+		std::lock_guard<std::mutex> l(_authorizationExpiry_l);
+		_authorizationExpiryRun = false;
+	}
+	_authorizationExpiry_c.notify_all();
+	if (_authorizationExpiryThread.joinable())
+		_authorizationExpiryThread.join();
+
 	std::lock_guard<std::mutex> l(_threads_l);
 	_queue.stop();
 	for(auto t=_threads.begin();t!=_threads.end();++t)
@@ -483,9 +601,14 @@ void EmbeddedNetworkController::init(const Identity &signingId,Sender *sender)
 	_sender = sender;
 	_signingIdAddressString = signingId.address().toString(tmp);
 
+	// History is only kept next to a FileDB. A local log of a database that
+	// other controllers also write would miss their changes, and its sequence
+	// numbers would not be shared with them.
+	bool historyEnabled = true;
 #ifdef ZT_CONTROLLER_USE_LIBPQ
 	if ((_path.length() > 9)&&(_path.substr(0,9) == "postgres:")) {
 		_db.addDB(std::shared_ptr<DB>(new PostgreSQL(_signingId,_path.substr(9).c_str(), _listenPort, _rc)));
+		historyEnabled = false;
 	} else {
 #endif
 		_db.addDB(std::shared_ptr<DB>(new FileDB(_path.c_str())));
@@ -499,6 +622,16 @@ void EmbeddedNetworkController::init(const Identity &signingId,Sender *sender)
 		nlohmann::json lfConfig(OSUtils::jsonParse(lfJSON));
 		nlohmann::json &settings = lfConfig["settings"];
 		if (settings.is_object()) {
+			nlohmann::json &history = settings["controllerHistory"];
+			if (history.is_object()) {
+				if (!OSUtils::jsonBool(history["enabled"],true))
+					historyEnabled = false;
+				_historyRotateSize = std::max(OSUtils::jsonInt(history["rotateSize"],(uint64_t)ZT_CONTROLLER_HISTORY_DEFAULT_ROTATE_SIZE),(uint64_t)4096);
+				_historyKeep = (unsigned int)std::min(OSUtils::jsonInt(history["keep"],(uint64_t)ZT_CONTROLLER_HISTORY_DEFAULT_KEEP),(uint64_t)ZT_CONTROLLER_HISTORY_MAX_KEEP);
+				_historyMaxAge = std::max(OSUtils::jsonDuration(history["maxAge"],0),(int64_t)0);
+				_historyDeletedRetention = std::max(OSUtils::jsonDuration(history["deletedRetention"],ZT_CONTROLLER_HISTORY_DEFAULT_DELETED_RETENTION),(int64_t)0);
+			}
+
 			nlohmann::json &controllerDb = settings["controllerDb"];
 			if (controllerDb.is_object()) {
 				std::string type = controllerDb["type"];
@@ -523,7 +656,29 @@ void EmbeddedNetworkController::init(const Identity &signingId,Sender *sender)
 		}
 	}
 
+	if (historyEnabled) {
+		_historyPath = _path + ZT_PATH_SEPARATOR_S "history";
+		OSUtils::mkdir(_historyPath.c_str());
+	} else if (_path.substr(0,9) == "postgres:") {
+		fprintf(stderr,"NOTICE: controller change history is not kept with a PostgreSQL database" ZT_EOL_S);
+	}
+
 	_db.waitForReady();
+
+	// Listeners are not notified as the database loads, so find the time
+	// limited authorizations already on record before the sweep starts.
+	std::set<uint64_t> networkIds;
+	_db.networks(networkIds);
+	for(auto nwid=networkIds.begin();nwid!=networkIds.end();++nwid) {
+		json network;
+		std::vector<json> members;
+		if (_db.get(*nwid,network,members)) {
+			for(auto m=members.begin();m!=members.end();++m)
+				_watchAuthorizationExpiry(*nwid,OSUtils::jsonIntHex((*m)["id"],0ULL),*m);
+		}
+	}
+	_authorizationExpiryRun = true;
+	_authorizationExpiryThread = std::thread([this]() { _authorizationExpiryThreadMain(); });
 }
 
 void EmbeddedNetworkController::request(
@@ -559,7 +714,7 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpGET(
 		if ((path.size() >= 2)&&(path[1].length() == 16)) {
 			const uint64_t nwid = Utils::hexStrToU64(path[1].c_str());
 			json network;
-			if (!_db.get(nwid,network))
+			if ((!_db.get(nwid,network))&&(!((path.size() == 3)&&(path[2] == "history")&&(_historyPath.length() > 0)))) // history outlives the network
 				return 404;
 
 			if (path.size() >= 3) {
@@ -597,6 +752,50 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpGET(
 					}
 					return 200;
 
+				} else if ((path.size() == 3)&&(path[2] == "export")) {
+					// Export network and all members (backup, migration)
+
+					std::vector<json> members;
+					_db.get(nwid,network,members);
+					json ex;
+					ex["network"] = network;
+					json exm = json::array();
+					for(auto member=members.begin();member!=members.end();++member)
+						exm.push_back(*member);
+					ex["members"] = exm;
+					responseBody = OSUtils::jsonDump(ex);
+					responseContentType = "application/json";
+					return 200;
+
+				} else if ((path.size() == 3)&&(path[2] == "history")) {
+					// Change history, newest last, optionally filtered by member and time
+
+					if (_historyPath.empty())
+						return 404;
+
+					auto a = urlArgs.find("member");
+					const uint64_t memberId = (a != urlArgs.end()) ? Utils::hexStrToU64(a->second.c_str()) : 0;
+					a = urlArgs.find("since");
+					const int64_t since = (a != urlArgs.end()) ? (int64_t)Utils::strToU64(a->second.c_str()) : 0;
+					a = urlArgs.find("before");
+					const int64_t before = (a != urlArgs.end()) ? (int64_t)Utils::strToU64(a->second.c_str()) : 0;
+					a = urlArgs.find("beforeSeq");
+					const uint64_t beforeSeq = (a != urlArgs.end()) ? Utils::strToU64(a->second.c_str()) : 0;
+					a = urlArgs.find("limit");
+					unsigned long limit = (a != urlArgs.end()) ? (unsigned long)Utils::strToU64(a->second.c_str()) : 0;
+					if ((limit == 0)||(limit > ZT_CONTROLLER_HISTORY_MAX_LIMIT))
+						limit = (limit == 0) ? ZT_CONTROLLER_HISTORY_DEFAULT_LIMIT : ZT_CONTROLLER_HISTORY_MAX_LIMIT;
+
+					std::vector<json> hist;
+					_readHistory(nwid,memberId,since,before,beforeSeq,limit,hist);
+
+					json res = json::array();
+					for(auto h=hist.rbegin();h!=hist.rend();++h)
+						res.push_back(*h);
+					responseBody = OSUtils::jsonDump(res);
+					responseContentType = "application/json";
+					return 200;
+
 				} // else 404
 
 			} else {
@@ -678,6 +877,113 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpPOST(
 
 			if (path.size() >= 3) {
 
+				if ((path.size() == 3)&&(path[2] == "token")) {
+					// Mint a new random auth token, optionally expiring at "expires" (ms since epoch)
+
+					json network;
+					if (!_db.get(nwid,network))
+						return 404;
+
+					uint8_t rtok[16];
+					char token[48];
+					Utils::getSecureRandom(rtok,sizeof(rtok));
+					Utils::hex(rtok,sizeof(rtok),token);
+					const uint64_t expires = OSUtils::jsonInt(b["expires"],0ULL);
+
+					json &authTokens = network["authTokens"];
+					if (!authTokens.is_object())
+						authTokens = json::object();
+					authTokens[token] = expires;
+					_save(network,_historyActor(headers));
+
+					json res;
+					res["token"] = token;
+					res["expires"] = expires;
+					responseBody = OSUtils::jsonDump(res);
+					responseContentType = "application/json";
+					return 200;
+				}
+
+				if ((path.size() == 3)&&(path[2] == "clone")) {
+					// Create a new network with the settings of an existing one ("from"), overridden by anything else in the body
+
+					if (_db.hasNetwork(nwid))
+						return 409;
+					json src;
+					const std::string srcs(OSUtils::jsonString(b["from"],""));
+					if ((srcs.length() != 16)||(!_db.get(Utils::hexStrToU64(srcs.c_str()),src)))
+						return 404;
+					// Join credentials and identity stay with the source network
+					src.erase("authTokens");
+					src.erase("tokenAuthorizationTime");
+					src.erase("autoAuthorize");
+					src.erase("id");
+					src.erase("nwid");
+					src.erase("revision");
+					src.erase("creationTime");
+					b.erase("from");
+					for(json::iterator i(b.begin());i!=b.end();++i)
+						src[i.key()] = i.value();
+
+					std::vector<std::string> subPath(path.begin(),path.begin() + 2);
+					return handleControlPlaneHttpPOST(subPath,urlArgs,headers,OSUtils::jsonDump(src,-1),responseBody,responseContentType);
+				}
+
+				if ((path.size() == 3)&&(path[2] == "import")) {
+					// Bulk import of network settings and members (e.g. output of .../export)
+
+					std::vector<std::string> subPath(path.begin(),path.begin() + 2);
+					std::string subResponseBody,subResponseContentType;
+
+					json &inet = b["network"];
+					if (inet.is_object()) {
+						_unwrapCentralObject(inet);
+						const unsigned int sc = handleControlPlaneHttpPOST(subPath,urlArgs,headers,OSUtils::jsonDump(inet,-1),subResponseBody,subResponseContentType);
+						if (sc != 200) {
+							responseBody = subResponseBody;
+							responseContentType = subResponseContentType;
+							return sc;
+						}
+						// Import members into the network actually created, since an ID ending in ______ picks a random one
+						try {
+							const std::string createdId(OSUtils::jsonString(OSUtils::jsonParse(subResponseBody)["id"],""));
+							if (createdId.length() == 16) {
+								subPath[1] = createdId;
+								nwid = Utils::hexStrToU64(createdId.c_str());
+								OSUtils::ztsnprintf(nwids,sizeof(nwids),"%.16llx",(unsigned long long)nwid);
+							}
+						} catch ( ... ) {}
+					}
+					if (!_db.hasNetwork(nwid))
+						return 404;
+
+					unsigned long imported = 0;
+					json failed = json::array();
+					json &imembers = b["members"];
+					if (imembers.is_array()) {
+						subPath.push_back("member");
+						subPath.push_back(std::string());
+						for(unsigned long i=0;i<imembers.size();++i) {
+							json &im = imembers[i];
+							if (!im.is_object())
+								continue;
+							_unwrapCentralObject(im);
+							subPath[3] = OSUtils::jsonString(im["id"],"");
+							if ((subPath[3].length() == 10)&&(handleControlPlaneHttpPOST(subPath,urlArgs,headers,OSUtils::jsonDump(im,-1),subResponseBody,subResponseContentType) == 200))
+								++imported;
+							else failed.push_back(subPath[3]);
+						}
+					}
+
+					json res;
+					res["id"] = nwids;
+					res["imported"] = imported;
+					res["failed"] = failed;
+					responseBody = OSUtils::jsonDump(res);
+					responseContentType = "application/json";
+					return 200;
+				}
+
 				if ((path.size() == 4)&&(path[2] == "member")&&(path[3].length() == 10)) {
 					uint64_t address = Utils::hexStrToU64(path[3].c_str());
 					char addrs[24];
@@ -701,6 +1007,8 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpPOST(
 						}
 						if (b.count("remoteTraceLevel")) member["remoteTraceLevel"] = OSUtils::jsonInt(b["remoteTraceLevel"],0ULL);
 
+						if (b.count("authorizationExpiry")) member["authorizationExpiry"] = OSUtils::jsonInt(b["authorizationExpiry"],0ULL);
+
 						if (b.count("authorized")) {
 							const bool newAuth = OSUtils::jsonBool(b["authorized"],false);
 							if (newAuth != OSUtils::jsonBool(member["authorized"],false)) {
@@ -778,7 +1086,7 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpPOST(
 					member["nwid"] = nwids;
 
 					DB::cleanMember(member);
-					_db.save(member,true);
+					_save(member,_historyActor(headers));
 					responseBody = OSUtils::jsonDump(member);
 					responseContentType = "application/json";
 
@@ -958,6 +1266,31 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpPOST(
 						}
 					}
 
+					if (b.count("tokenAuthorizationTime")) network["tokenAuthorizationTime"] = OSUtils::jsonInt(b["tokenAuthorizationTime"],0ULL);
+
+					if (b.count("autoAuthorize")) {
+						json &autoAuthorize = b["autoAuthorize"];
+						json naa = json::array();
+						if (autoAuthorize.is_array()) {
+							for(unsigned long i=0;i<autoAuthorize.size();++i) {
+								const std::string e(OSUtils::jsonString(autoAuthorize[i],""));
+								if (e.length() == 10) {
+									char tmp[16];
+									const uint64_t a = Utils::hexStrToU64(e.c_str());
+									if (a)
+										naa.push_back(Utils::hex10(a,tmp));
+								} else {
+									Identity id;
+									if ((id.fromString(e.c_str()))&&(id.locallyValidate())) {
+										char tmp[ZT_IDENTITY_STRING_BUFFER_LENGTH];
+										naa.push_back(id.toString(false,tmp));
+									}
+								}
+							}
+						}
+						network["autoAuthorize"] = naa;
+					}
+
 					if (b.count("capabilities")) {
 						json &capabilities = b["capabilities"];
 						if (capabilities.is_array()) {
@@ -1059,7 +1392,7 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpPOST(
 				network["nwid"] = nwids; // legacy
 
 				DB::cleanNetwork(network);
-				_db.save(network,true);
+				_save(network,_historyActor(headers));
 
 				responseBody = OSUtils::jsonDump(network);
 				responseContentType = "application/json";
@@ -1094,6 +1427,8 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpDELETE(
 					json network,member;
 					_db.get(nwid,network,address,member);
 					_db.eraseMember(nwid, address);
+					if (member.size())
+						_recordHistory(_historyActor(headers),member,json());
 
 					{
 						std::lock_guard<std::mutex> l(_memberStatus_l);
@@ -1105,11 +1440,26 @@ unsigned int EmbeddedNetworkController::handleControlPlaneHttpDELETE(
 					responseBody = OSUtils::jsonDump(member);
 					responseContentType = "application/json";
 					return 200;
+				} else if ((path.size() == 4)&&(path[2] == "token")) {
+					// Revoke an auth token (members it already authorized are not affected)
+
+					json network;
+					if (!_db.get(nwid,network))
+						return 404;
+					json &authTokens = network["authTokens"];
+					if ((!authTokens.is_object())||(!authTokens.erase(path[3])))
+						return 404;
+					_save(network,_historyActor(headers));
+					responseBody = "{}";
+					responseContentType = "application/json";
+					return 200;
 				}
 			} else {
 				json network;
 				_db.get(nwid,network);
 				_db.eraseNetwork(nwid);
+				if (network.size())
+					_recordHistory(_historyActor(headers),network,json());
 
 				{
 					std::lock_guard<std::mutex> l(_memberStatus_l);
@@ -1196,6 +1546,8 @@ void EmbeddedNetworkController::onNetworkUpdate(const void *db,uint64_t networkI
 
 void EmbeddedNetworkController::onNetworkMemberUpdate(const void *db,uint64_t networkId,uint64_t memberId,const nlohmann::json &member)
 {
+	_watchAuthorizationExpiry(networkId,memberId,member);
+
 	// Push update to member if online
 	try {
 		std::lock_guard<std::mutex> l(_memberStatus_l);
@@ -1207,14 +1559,19 @@ void EmbeddedNetworkController::onNetworkMemberUpdate(const void *db,uint64_t ne
 
 void EmbeddedNetworkController::onNetworkMemberDeauthorize(const void *db,uint64_t networkId,uint64_t memberId)
 {
+	if (!_sender)
+		return;
 	const int64_t now = OSUtils::now();
-	Revocation rev((uint32_t)_node->prng(),networkId,0,now,ZT_REVOCATION_FLAG_FAST_PROPAGATE,Address(memberId),Revocation::CREDENTIAL_TYPE_COM);
+	uint32_t revId;
+	Utils::getSecureRandom(&revId,sizeof(revId));
+	Revocation rev(revId,networkId,0,now,ZT_REVOCATION_FLAG_FAST_PROPAGATE,Address(memberId),Revocation::CREDENTIAL_TYPE_COM);
 	rev.sign(_signingId);
 	{
 		std::lock_guard<std::mutex> l(_memberStatus_l);
 		for(auto i=_memberStatus.begin();i!=_memberStatus.end();++i) {
+			// Through the Sender like configs and errors: it is the Node in the service, and a test's own sender in the selftest
 			if ((i->first.networkId == networkId)&&(i->second.online(now)))
-				_node->ncSendRevocation(Address(i->first.nodeId),rev);
+				_sender->ncSendRevocation(Address(i->first.nodeId),rev);
 		}
 	}
 }
@@ -1285,6 +1642,13 @@ void EmbeddedNetworkController::_request(
 		member["nwid"] = nwids;
 	}
 
+	// Authorization changes made here are recorded in history; routine saves are not
+	bool authChanged = false;
+
+	// Time limited (e.g. guest) authorization that has expired but not been swept yet
+	if (_expireAuthorization(member,now))
+		authChanged = true;
+
 	// Determine whether and how member is authorized
 	bool authorized = false;
 	bool autoAuthorized = false;
@@ -1296,14 +1660,41 @@ void EmbeddedNetworkController::_request(
 		autoAuthorized = true;
 		autoAuthCredentialType = "public";
 	} else {
+		// Members listed by address or full identity are authorized on first contact. One that has
+		// ever been deauthorized (through the API or by an expired time limit) stays deauthorized.
+		json &autoAuthorize = network["autoAuthorize"];
+		if ((autoAuthorize.is_array())&&(OSUtils::jsonInt(member["lastDeauthorizedTime"],0ULL) == 0)) {
+			for(unsigned long i=0;i<autoAuthorize.size();++i) {
+				const std::string e(OSUtils::jsonString(autoAuthorize[i],""));
+				bool match;
+				if (e.length() == 10) {
+					match = (Utils::hexStrToU64(e.c_str()) == identity.address().toInt());
+				} else {
+					Identity id;
+					match = ((id.fromString(e.c_str()))&&(id == identity));
+				}
+				if (match) {
+					authorized = true;
+					autoAuthorized = true;
+					autoAuthCredentialType = "autoAuthorize";
+					autoAuthCredential = e;
+					break;
+				}
+			}
+		}
+
 		char presentedAuth[512];
-		if (metaData.get(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_AUTH,presentedAuth,sizeof(presentedAuth)) > 0) {
+		if ((!authorized)&&(metaData.get(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_AUTH,presentedAuth,sizeof(presentedAuth)) > 0)) {
 			presentedAuth[511] = (char)0; // sanity check
-			if ((strlen(presentedAuth) > 6)&&(!strncmp(presentedAuth,"token:",6))) {
+			json authTokens(network["authTokens"]);
+			if ((strlen(presentedAuth) > 6)&&(!strncmp(presentedAuth,"token:",6))&&(authTokens.is_object())) { // networks with no tokens have an empty array here
 				const char *const presentedToken = presentedAuth + 6;
-				json authTokens(network["authTokens"]);
 				json &tokenExpires = authTokens[presentedToken];
-				if (tokenExpires.is_number()) {
+				// A token can't renew a time box it granted once that has expired or been revoked
+				const bool tokenUsedUp = ((OSUtils::jsonString(member["lastAuthorizedCredentialType"],"") == "token")&&
+				                          (OSUtils::jsonString(member["lastAuthorizedCredential"],"") == presentedToken)&&
+				                          ((int64_t)OSUtils::jsonInt(member["lastDeauthorizedTime"],0ULL) >= (int64_t)OSUtils::jsonInt(member["lastAuthorizedTime"],0ULL)));
+				if ((tokenExpires.is_number())&&(!tokenUsedUp)) {
 					if ((tokenExpires == 0)||(tokenExpires > now)) {
 						authorized = true;
 						autoAuthorized = true;
@@ -1317,10 +1708,15 @@ void EmbeddedNetworkController::_request(
 
 	// If we auto-authorized, update member record
 	if ((autoAuthorized)&&(authorized)) {
+		authChanged = true;
 		member["authorized"] = true;
 		member["lastAuthorizedTime"] = now;
 		member["lastAuthorizedCredentialType"] = autoAuthCredentialType;
 		member["lastAuthorizedCredential"] = autoAuthCredential;
+		if (autoAuthCredentialType == "token") {
+			const int64_t tat = (int64_t)OSUtils::jsonInt(network["tokenAuthorizationTime"],0ULL);
+			member["authorizationExpiry"] = (tat > 0) ? (now + tat) : 0;
+		}
 	}
 
 	if (authorized) {
@@ -1351,7 +1747,9 @@ void EmbeddedNetworkController::_request(
 	} else {
 		// If they are not authorized, STOP!
 		DB::cleanMember(member);
-		_db.save(member,true);
+		if (authChanged)
+			_save(member,"controller");
+		else _db.save(member,true);
 		_sender->ncSendError(nwid,requestPacketId,identity.address(),NetworkController::NC_ERROR_ACCESS_DENIED);
 		return;
 	}
@@ -1739,10 +2137,289 @@ void EmbeddedNetworkController::_request(
 	}
 
 	DB::cleanMember(member);
-	_db.save(member,true);
+	if (authChanged)
+		_save(member,"controller");
+	else _db.save(member,true);
 	_sender->ncSendConfig(nwid,requestPacketId,identity.address(),*(nc.get()),metaData.getUI(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_VERSION,0) < 6);
 }
 
+bool EmbeddedNetworkController::_save(json &record,const std::string &by)
+{
+	// Saves of the same record at the same time may both be diffed against
+	// the record as it was before either, but each entry still shows exactly
+	// what its own save changed.
+	json network,old;
+	const uint64_t id = OSUtils::jsonIntHex(record["id"],0ULL);
+	if (OSUtils::jsonString(record["objtype"],"") == "member")
+		_db.get(OSUtils::jsonIntHex(record["nwid"],0ULL),network,id,old);
+	else _db.get(id,old);
+	if (!_db.save(record,true))
+		return false;
+	_recordHistory(by,old,record);
+	return true;
+}
+
+std::string EmbeddedNetworkController::_historyLogPath(const uint64_t nwid,const unsigned int n) const
+{
+	char p[4096];
+	if (n)
+		OSUtils::ztsnprintf(p,sizeof(p),"%s" ZT_PATH_SEPARATOR_S "%.16llx.log.%u",_historyPath.c_str(),(unsigned long long)nwid,n);
+	else OSUtils::ztsnprintf(p,sizeof(p),"%s" ZT_PATH_SEPARATOR_S "%.16llx.log",_historyPath.c_str(),(unsigned long long)nwid);
+	return std::string(p);
+}
+
+void EmbeddedNetworkController::_recordHistory(const std::string &by,const json &old,const json &record)
+{
+	if (_historyPath.empty())
+		return;
+	try {
+		const json &r = record.is_object() ? record : old;
+		if (!r.is_object())
+			return;
+
+		// Every changed field is recorded as [ old, new ] so a change can be reverted by hand
+		std::set<std::string> keys;
+		if (old.is_object()) {
+			for(auto i=old.begin();i!=old.end();++i)
+				keys.insert(i.key());
+		}
+		if (record.is_object()) {
+			for(auto i=record.begin();i!=record.end();++i)
+				keys.insert(i.key());
+		}
+		json changes = json::object();
+		for(auto k=keys.begin();k!=keys.end();++k) {
+			if (*k == "revision")
+				continue;
+			const json o(old.is_object() ? old.value(*k,json()) : json());
+			const json n(record.is_object() ? record.value(*k,json()) : json());
+			if (o != n)
+				changes[*k] = json::array({ _historyRedact(*k,o,old),_historyRedact(*k,n,record) });
+		}
+		if (changes.empty())
+			return;
+
+		const std::string objtype(r.value("objtype",std::string()));
+		const uint64_t nwid = Utils::hexStrToU64(r.value((objtype == "member") ? "nwid" : "id",std::string()).c_str());
+		json h;
+		h["ts"] = OSUtils::now();
+		h["by"] = by;
+		h["op"] = (!old.is_object()) ? "create" : ((!record.is_object()) ? "delete" : "update");
+		h["objtype"] = objtype;
+		h["id"] = r.value("id",std::string());
+		h["revision"] = r.value("revision",0ULL);
+		h["changes"] = changes;
+
+		const std::string p(_historyLogPath(nwid,0));
+
+		// Each entry gets a sequence number, unique within the network, that
+		// GET .../history pages by. After a restart it continues from the
+		// last entry logged, which is looked up before taking the lock.
+		bool seeded;
+		{
+			std::lock_guard<std::mutex> l(_history_l);
+			seeded = (_historySeq.find(nwid) != _historySeq.end());
+		}
+		uint64_t last = 0;
+		for(unsigned int li=0;((!seeded)&&(li<=_historyKeep)&&(!last));++li) {
+			FILE *lf = fopen(_historyLogPath(nwid,li).c_str(),"rb");
+			if (lf) {
+				fseek(lf,0,SEEK_END);
+				_historyReverseLines(lf,ftell(lf),[&last](const std::string &entry) -> bool {
+					try {
+						last = OSUtils::jsonInt(OSUtils::jsonParse(entry)["seq"],0ULL);
+					} catch ( ... ) {}
+					return (last == 0);
+				});
+				fclose(lf);
+			}
+		}
+
+		std::lock_guard<std::mutex> l(_history_l);
+		h["seq"] = ++(_historySeq.insert(std::pair<uint64_t,uint64_t>(nwid,last)).first->second);
+		const std::string line(OSUtils::jsonDump(h,-1) + "\n");
+		FILE *f = fopen(p.c_str(),"a");
+		if (!f) {
+			fprintf(stderr,"WARNING: controller unable to write history to path: %s" ZT_EOL_S,p.c_str());
+			return;
+		}
+		const bool written = (fwrite(line.data(),1,line.length(),f) == line.length());
+		const long size = ftell(f);
+		if ((fclose(f) != 0)||(!written)) {
+			fprintf(stderr,"WARNING: controller unable to write history to path: %s" ZT_EOL_S,p.c_str());
+			return;
+		}
+		if ((size > 0)&&((uint64_t)size > _historyRotateSize)) {
+			// <id>.log.1 is the newest rotated log and <id>.log.<keep> the oldest
+			if (_historyKeep) {
+				OSUtils::rm(_historyLogPath(nwid,_historyKeep).c_str());
+				for(unsigned int li=_historyKeep;li>0;--li) {
+					const std::string from(_historyLogPath(nwid,li - 1));
+					if ((OSUtils::fileExists(from.c_str()))&&(!OSUtils::rename(from.c_str(),_historyLogPath(nwid,li).c_str())))
+						fprintf(stderr,"WARNING: controller unable to rotate history log: %s" ZT_EOL_S,from.c_str());
+				}
+			} else {
+				OSUtils::rm(p.c_str());
+			}
+		}
+	} catch (std::exception &e) {
+		fprintf(stderr,"WARNING: controller unable to record history: %s" ZT_EOL_S,e.what());
+	} catch ( ... ) {
+		fprintf(stderr,"WARNING: controller unable to record history: unknown exception" ZT_EOL_S);
+	}
+}
+
+// Applies history retention: rotated logs past the number kept or older than
+// maxAge are removed, and so are all logs of networks that no longer exist
+// once they have not been written for the deleted network retention time.
+void EmbeddedNetworkController::_expireHistory(const int64_t now)
+{
+	if (_historyPath.empty())
+		return;
+
+	std::map< uint64_t,std::vector< std::pair<unsigned int,std::string> > > logs; // network ID -> [ (rotation, name) ]
+	std::vector<std::string> files(OSUtils::listDirectory(_historyPath.c_str()));
+	for(std::vector<std::string>::const_iterator lf(files.begin());lf!=files.end();++lf) {
+		if ((lf->length() < 20)||(lf->substr(16,4) != ".log")||(lf->substr(0,16).find_first_not_of("0123456789abcdef") != std::string::npos))
+			continue;
+		unsigned int n = 0;
+		if (lf->length() > 20) {
+			if ((lf->length() < 22)||((*lf)[20] != '.')||(lf->substr(21).find_first_not_of("0123456789") != std::string::npos))
+				continue;
+			n = (unsigned int)Utils::strToUInt(lf->substr(21).c_str());
+			if (!n)
+				continue;
+		}
+		logs[Utils::hexStrToU64(lf->substr(0,16).c_str())].push_back(std::pair<unsigned int,std::string>(n,_historyPath + ZT_PATH_SEPARATOR_S + *lf));
+	}
+
+	for(auto nl=logs.begin();nl!=logs.end();++nl) {
+		json network;
+		const bool deleted = ((nl->first)&&(!_db.get(nl->first,network)));
+		int64_t lastWritten = 0;
+		for(auto lf=nl->second.begin();lf!=nl->second.end();++lf)
+			lastWritten = std::max(lastWritten,(int64_t)OSUtils::getLastModified(lf->second.c_str()));
+
+		std::lock_guard<std::mutex> l(_history_l);
+		if ((deleted)&&(lastWritten < (now - _historyDeletedRetention))&&((int64_t)OSUtils::getLastModified(_historyLogPath(nl->first,0).c_str()) <= lastWritten)) {
+			for(auto lf=nl->second.begin();lf!=nl->second.end();++lf)
+				OSUtils::rm(lf->second.c_str());
+			_historySeq.erase(nl->first);
+			continue;
+		}
+		for(auto lf=nl->second.begin();lf!=nl->second.end();++lf) {
+			if ((lf->first > _historyKeep)||((lf->first)&&(_historyMaxAge > 0)&&((int64_t)OSUtils::getLastModified(lf->second.c_str()) < (now - _historyMaxAge))))
+				OSUtils::rm(lf->second.c_str());
+		}
+	}
+}
+
+void EmbeddedNetworkController::_readHistory(const uint64_t nwid,const uint64_t memberId,const int64_t since,const int64_t before,const uint64_t beforeSeq,const unsigned long limit,std::vector<json> &hist)
+{
+	// All logs are opened under the lock so that a rotation can't cause an
+	// entry to be read twice or missed, but are read without it so saves are
+	// not held up. Entries are read newest first, from the end of the current
+	// and then each rotated log, stopping once enough have been found.
+	std::vector< std::pair<FILE *,long> > f;
+	{
+		std::lock_guard<std::mutex> l(_history_l);
+		for(unsigned int li=0;li<=_historyKeep;++li) {
+			FILE *lf = fopen(_historyLogPath(nwid,li).c_str(),"rb");
+			if (!lf)
+				break;
+			fseek(lf,0,SEEK_END);
+			f.push_back(std::pair<FILE *,long>(lf,ftell(lf)));
+		}
+	}
+
+	bool more = true;
+	for(auto lf=f.begin();lf!=f.end();++lf) {
+		if (more) {
+			more = _historyReverseLines(lf->first,lf->second,[&](const std::string &entry) -> bool {
+				try {
+					json h(OSUtils::jsonParse(entry));
+					const int64_t ts = (int64_t)OSUtils::jsonInt(h["ts"],0ULL);
+					if (ts < since)
+						return false; // entries are in time order
+					if (((before <= 0)||(ts < before))&&((!beforeSeq)||(OSUtils::jsonInt(h["seq"],0ULL) < beforeSeq))&&((!memberId)||((OSUtils::jsonString(h["objtype"],"") == "member")&&(OSUtils::jsonIntHex(h["id"],0ULL) == memberId)))) {
+						hist.push_back(h);
+						if (hist.size() >= limit)
+							return false;
+					}
+				} catch ( ... ) {} // skip truncated lines
+				return true;
+			});
+		}
+		fclose(lf->first);
+	}
+}
+
+void EmbeddedNetworkController::_watchAuthorizationExpiry(const uint64_t networkId,const uint64_t memberId,const json &member)
+{
+	if ((!networkId)||(!memberId)||(!member.is_object()))
+		return;
+	const int64_t authExpiry = (int64_t)OSUtils::jsonInt(member.value("authorizationExpiry",json()),0ULL);
+	if ((authExpiry > 0)&&(OSUtils::jsonBool(member.value("authorized",json()),false))) {
+		bool earliest;
+		{
+			std::lock_guard<std::mutex> l(_authorizationExpiry_l);
+			const auto e(_authorizationExpiry.insert(std::pair< int64_t,std::pair<uint64_t,uint64_t> >(authExpiry,std::pair<uint64_t,uint64_t>(networkId,memberId))).first);
+			earliest = (e == _authorizationExpiry.begin());
+		}
+		// The thread sleeps until the earliest expiry, so wake it to sleep until this one instead
+		if (earliest)
+			_authorizationExpiry_c.notify_all();
+	}
+}
+
+// Expired authorizations must not wait for the member's next config request:
+// a member that stops asking keeps using its COM until credentialTimeMaxDelta
+// runs out unless revocations are sent. Saving the deauthorized member records
+// it in history and calls onNetworkMemberDeauthorize(), which sends them.
+void EmbeddedNetworkController::_authorizationExpiryThreadMain()
+{
+	int64_t lastHistoryExpiry = 0;
+	std::unique_lock<std::mutex> l(_authorizationExpiry_l);
+	while (_authorizationExpiryRun) {
+		int64_t now = OSUtils::now();
+		int64_t next = lastHistoryExpiry + ZT_CONTROLLER_HISTORY_EXPIRY_CHECK_PERIOD;
+		if (!_authorizationExpiry.empty())
+			next = std::min(next,_authorizationExpiry.begin()->first);
+		if (next > now)
+			_authorizationExpiry_c.wait_for(l,std::chrono::milliseconds(std::min(next - now,(int64_t)ZT_CONTROLLER_AUTHORIZATION_EXPIRY_MAX_WAIT)));
+		if (!_authorizationExpiryRun)
+			break;
+
+		now = OSUtils::now();
+		if ((now - lastHistoryExpiry) >= ZT_CONTROLLER_HISTORY_EXPIRY_CHECK_PERIOD) {
+			lastHistoryExpiry = now;
+			l.unlock();
+			_expireHistory(now);
+			l.lock();
+		}
+
+		std::vector< std::pair<uint64_t,uint64_t> > due;
+		while ((!_authorizationExpiry.empty())&&(_authorizationExpiry.begin()->first <= now)) {
+			due.push_back(_authorizationExpiry.begin()->second);
+			_authorizationExpiry.erase(_authorizationExpiry.begin());
+		}
+		if (due.empty())
+			continue;
+
+		l.unlock();
+		for(auto d=due.begin();d!=due.end();++d) {
+			try {
+				json network,member;
+				if ((_db.get(d->first,network,d->second,member))&&(_expireAuthorization(member,now))) {
+					DB::cleanMember(member);
+					_save(member,"controller");
+				}
+			} catch ( ... ) {}
+		}
+		l.lock();
+	}
+}
+
 void EmbeddedNetworkController::_startThreads()
 {
 	std::lock_guard<std::mutex> l(_threads_l);
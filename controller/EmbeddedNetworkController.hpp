@@ -24,6 +24,8 @@
 #include <thread>
 #include <unordered_map>
 #include <atomic>
+#include <mutex>
+#include <condition_variable>
 
 #include "../node/Constants.hpp"
 #include "../node/NetworkController.hpp"
@@ -95,6 +97,13 @@ public:
 private:
 	void _request(uint64_t nwid,const InetAddress &fromAddr,uint64_t requestPacketId,const Identity &identity,const Dictionary<ZT_NETWORKCONFIG_METADATA_DICT_CAPACITY> &metaData);
 	void _startThreads();
+	bool _save(nlohmann::json &record,const std::string &by);
+	std::string _historyLogPath(const uint64_t nwid,const unsigned int n) const;
+	void _recordHistory(const std::string &by,const nlohmann::json &old,const nlohmann::json &record);
+	void _expireHistory(const int64_t now);
+	void _readHistory(const uint64_t nwid,const uint64_t memberId,const int64_t since,const int64_t before,const uint64_t beforeSeq,const unsigned long limit,std::vector<nlohmann::json> &hist);
+	void _watchAuthorizationExpiry(const uint64_t networkId,const uint64_t memberId,const nlohmann::json &member);
+	void _authorizationExpiryThreadMain();
 
 	struct _RQEntry
 	{
@@ -137,6 +146,13 @@ private:
 	Node *const _node;
 	std::string _ztPath;
 	std::string _path;
+	std::string _historyPath; // empty if history is not kept
+	uint64_t _historyRotateSize;
+	unsigned int _historyKeep; // number of rotated logs kept per network
+	int64_t _historyMaxAge; // rotated logs not written for this long are removed, 0 for no limit
+	int64_t _historyDeletedRetention;
+	std::map<uint64_t,uint64_t> _historySeq; // last history entry sequence number by network
+	std::mutex _history_l; // held while appending to, rotating, or removing logs
 	Identity _signingId;
 	std::string _signingIdAddressString;
 	NetworkController::Sender *_sender;
@@ -150,6 +166,13 @@ private:
 	std::unordered_map< _MemberStatusKey,_MemberStatus,_MemberStatusHash > _memberStatus;
 	std::mutex _memberStatus_l;
 
+	// Time limited authorizations by expiry time, entries may be stale and are checked against the member when due
+	std::set< std::pair< int64_t,std::pair<uint64_t,uint64_t> > > _authorizationExpiry;
+	std::mutex _authorizationExpiry_l;
+	std::condition_variable _authorizationExpiry_c;
+	bool _authorizationExpiryRun;
+	std::thread _authorizationExpiryThread;
+
 	RedisConfig *_rc;
 };
 
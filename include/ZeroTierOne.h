@@ -66,6 +66,22 @@ extern "C" {
 /* Core constants                                                           */
 /****************************************************************************/
 
+/**
+ * Version of the binary layout of the structures in this header
+ *
+ * This is increased whenever a structure passed across this API changes
+ * size or field offsets. Code built against an older value must be rebuilt.
+ * ZT_apiVersion() returns the value the core library was built with.
+ *
+ * 1 - 1.6.2 and earlier
+ * 2 - ZT_PeerPhysicalPath gained MTU probe results (mtuProbeMaxSize through
+ *     lastMtuProbe) and keepalive, and ZT_Peer gained macFailures, relayed and aesGmacSiv
+ *     after paths[]. Both structures changed size, so every paths[i] after
+ *     the first, everything after paths[], and the stride of
+ *     ZT_PeerList::peers moved.
+ */
+#define ZT_API_VERSION 2
+
 /**
  * Default UDP port for devices running a ZeroTier endpoint
  */
@@ -120,6 +136,11 @@ extern "C" {
  */
 #define ZT_MAX_NETWORK_SHORT_NAME_LENGTH 127
 
+/**
+ * Maximum length of a network join token
+ */
+#define ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH 127
+
 /**
  * Maximum number of pushed routes on a network
  */
@@ -1120,6 +1141,46 @@ typedef struct
 	struct sockaddr_storage server_addr[ZT_MAX_DNS_SERVERS];
 } ZT_VirtualNetworkDNS;
 
+/**
+ * Traffic counters for a virtual network since it was joined
+ */
+typedef struct
+{
+	/**
+	 * Frames and bytes delivered to the virtual network port
+	 */
+	uint64_t rxPackets;
+	uint64_t rxBytes;
+
+	/**
+	 * Frames received from peers and not delivered (access denied, rules, bridging, etc.)
+	 */
+	uint64_t rxDropped;
+
+	/**
+	 * Frames and bytes read from the virtual network port, including those later dropped
+	 */
+	uint64_t txPackets;
+	uint64_t txBytes;
+
+	/**
+	 * Frames read from the virtual network port and not sent (rules, bridging, etc.)
+	 */
+	uint64_t txDropped;
+
+	/**
+	 * Multicast and broadcast frames included in rxPackets and txPackets
+	 */
+	uint64_t rxMulticast;
+	uint64_t txMulticast;
+
+	/**
+	 * Frames included in rxPackets and txPackets whose ethertype is not IPv4, ARP, or IPv6
+	 */
+	uint64_t rxUnknownEtherType;
+	uint64_t txUnknownEtherType;
+} ZT_VirtualNetworkStats;
+
 /**
  * An Ethernet multicast group
  */
@@ -1456,6 +1517,35 @@ typedef struct
 	 * Is path preferred?
 	 */
 	int preferred;
+
+	/**
+	 * Largest MTU probe in bytes that was answered, or 0 if none (see ZT_Node_probePathMtu)
+	 */
+	unsigned int mtuProbeMaxSize;
+
+	/**
+	 * Non-zero if every try at the largest probe size was lost while smaller ones were answered
+	 */
+	int mtuProbeBlackhole;
+
+	/**
+	 * Non-zero once all probes have been sent and have had time to be answered
+	 */
+	int mtuProbeDone;
+
+	/**
+	 * Time the last MTU probe was started or 0 if never probed
+	 */
+	int64_t lastMtuProbe;
+
+	/**
+	 * Interval in milliseconds at which this path is sent keepalives when otherwise idle
+	 *
+	 * This is the peer's keepalive (see ZT_Node_setPeerKeepalive and
+	 * ZT_Node_setNetworkKeepalive) or the default heartbeat period, which
+	 * is only kept up while the peer is active.
+	 */
+	unsigned int keepalive;
 } ZT_PeerPhysicalPath;
 
 /**
@@ -1532,6 +1622,27 @@ typedef struct
 	 * Known network paths to peer
 	 */
 	ZT_PeerPhysicalPath paths[ZT_MAX_PEER_NETWORK_PATHS];
+
+	/*
+	 * Fields below were added in ZT_API_VERSION 2. This layout is not
+	 * compatible with version 1: ZT_PeerPhysicalPath also grew, so paths[]
+	 * past the first entry and these fields are at new offsets.
+	 */
+
+	/**
+	 * Number of packets from this peer dropped due to MAC (authentication) failure
+	 */
+	unsigned int macFailures;
+
+	/**
+	 * Nonzero if this peer was recently heard from but has no direct path
+	 */
+	int relayed;
+
+	/**
+	 * Nonzero if payloads to this peer are encrypted with AES-GMAC-SIV, zero for Salsa20/12-Poly1305
+	 */
+	int aesGmacSiv;
 } ZT_Peer;
 
 /**
@@ -1981,6 +2092,20 @@ ZT_SDK_API enum ZT_ResultCode ZT_Node_join(ZT_Node *node,uint64_t nwid,void *upt
  */
 ZT_SDK_API enum ZT_ResultCode ZT_Node_leave(ZT_Node *node,uint64_t nwid,void **uptr,void *tptr);
 
+/**
+ * Set a token to present to a network's controller when requesting config
+ *
+ * Controllers can authorize members automatically if they present one of
+ * the network's auth tokens. The token is sent with every config request
+ * until it is changed. A NULL or empty token sends none.
+ *
+ * @param node Node instance
+ * @param nwid 64-bit network ID (must already be joined)
+ * @param token Token of at most ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH characters
+ * @return OK, ZT_RESULT_ERROR_NETWORK_NOT_FOUND, or ZT_RESULT_ERROR_BAD_PARAMETER if token is too long
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setNetworkJoinToken(ZT_Node *node,uint64_t nwid,const char *token);
+
 /**
  * Subscribe to an Ethernet multicast group
  *
@@ -2051,6 +2176,174 @@ ZT_SDK_API enum ZT_ResultCode ZT_Node_orbit(ZT_Node *node,void *tptr,uint64_t mo
  */
 ZT_SDK_API enum ZT_ResultCode ZT_Node_deorbit(ZT_Node *node,void *tptr,uint64_t moonWorldId);
 
+/**
+ * Forget a peer, its paths, and any cached state for it
+ *
+ * Upstream peers (roots and moons) can't be forgotten. Other peers are
+ * learned again normally if traffic is exchanged with them.
+ *
+ * @param node Node instance
+ * @param tptr Thread pointer to pass to functions/callbacks resulting from this call
+ * @param address ZeroTier address of peer
+ * @return OK (even if peer was not known) or ZT_RESULT_ERROR_UNSUPPORTED_OPERATION for upstreams
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_forgetPeer(ZT_Node *node,void *tptr,uint64_t address);
+
+/**
+ * Drop all physical paths to a peer but keep the peer
+ *
+ * Traffic with the peer is relayed until a direct path is learned again,
+ * which is how stale paths can be flushed without losing the peer's
+ * identity or keys.
+ *
+ * @param node Node instance
+ * @param address ZeroTier address of peer
+ * @return OK or ZT_RESULT_ERROR_BAD_PARAMETER if the peer is not in memory
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_clearPaths(ZT_Node *node,uint64_t address);
+
+/**
+ * Probe the MTU of each live direct path to a peer
+ *
+ * Padded ECHO packets of each size in ZT_PATH_MTU_PROBE_SIZES are sent over
+ * each path, one per ZT_PATH_MTU_PROBE_INTERVAL to the peer, and sizes that
+ * go unanswered are tried up to ZT_PATH_MTU_PROBE_TRIES times. Results are
+ * reported in each ZT_PeerPhysicalPath of the peer.
+ *
+ * ZeroTier does not split probes, but like all ZeroTier traffic they are
+ * sent without the IP don't-fragment bit, so the OS may fragment them. What
+ * is measured is the largest packet that gets through the path the way
+ * ZeroTier's own traffic does, not the path MTU itself. A black hole usually
+ * means IP fragments are being dropped somewhere on the path.
+ *
+ * @param node Node instance
+ * @param tptr Thread pointer to pass to functions/callbacks resulting from this call
+ * @param address ZeroTier address of peer
+ * @return OK, ZT_RESULT_ERROR_BAD_PARAMETER if the peer is unknown or has no live direct paths, or ZT_RESULT_ERROR_UNSUPPORTED_OPERATION in low-power mode
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_probePathMtu(ZT_Node *node,void *tptr,uint64_t address);
+
+/**
+ * Set how often to send keepalives to a peer over its direct paths
+ *
+ * Paths to peers with a keepalive set are kept open even when no traffic
+ * is being exchanged, which helps with NATs that drop idle mappings
+ * quickly. The peer need not be known yet.
+ *
+ * @param node Node instance
+ * @param address ZeroTier address of peer
+ * @param interval Interval in milliseconds or 0 to restore the default
+ * @return OK or ZT_RESULT_ERROR_BAD_PARAMETER if interval is not between 1000 and 14000 (the default)
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setPeerKeepalive(ZT_Node *node,uint64_t address,unsigned int interval);
+
+/**
+ * Set how often to send keepalives to the peers of a network
+ *
+ * This applies to the network's controller and to members we have
+ * exchanged credentials or traffic with. A peer's own keepalive set with
+ * ZT_Node_setPeerKeepalive takes precedence, and a peer in several networks
+ * gets the shortest of their intervals. The setting is dropped on leave.
+ *
+ * @param node Node instance
+ * @param nwid 64-bit network ID
+ * @param interval Interval in milliseconds or 0 to restore the default
+ * @return OK, ZT_RESULT_ERROR_NETWORK_NOT_FOUND if not joined, or ZT_RESULT_ERROR_BAD_PARAMETER if interval is not between 1000 and 14000
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setNetworkKeepalive(ZT_Node *node,uint64_t nwid,unsigned int interval);
+
+/**
+ * Allow or forbid relaying of network traffic to and from a peer
+ *
+ * With relaying forbidden, frames for a peer with no direct path are held
+ * and then dropped instead of going through an upstream, and relayed frames
+ * from it are ignored. Control traffic is still relayed so that upstreams
+ * can introduce the two nodes and a direct path can form.
+ *
+ * @param node Node instance
+ * @param address ZeroTier address of peer, or 0 to set the default for all peers
+ * @param forbid Nonzero to forbid relaying
+ * @return OK (even if peer is not known)
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setPeerNoRelay(ZT_Node *node,uint64_t address,int forbid);
+
+/**
+ * Prefer IPv4 or IPv6 direct paths to a peer
+ *
+ * By default the lowest latency path is used regardless of family. With a
+ * preference set, the best path of that family is used whenever one is up
+ * and the other family is only a fallback. This has no effect on bonded
+ * peers, which follow their bonding policy.
+ *
+ * @param node Node instance
+ * @param address ZeroTier address of peer, or 0 to set the default for all peers
+ * @param family AF_INET, AF_INET6, or 0 for no preference
+ * @return OK (even if peer is not known) or ZT_RESULT_ERROR_BAD_PARAMETER if family is not valid
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setPeerPreferredFamily(ZT_Node *node,uint64_t address,int family);
+
+/**
+ * Set the maximum number of packets queued for sending on each network
+ *
+ * This only applies when QoS/AQM is in use. When the limit is exceeded,
+ * packets are dropped from the longest queue. Small devices can lower it
+ * to save memory, busy servers can raise it to absorb bursts.
+ *
+ * @param node Node instance
+ * @param max Maximum number of packets or 0 to restore the default (1024)
+ * @return OK or ZT_RESULT_ERROR_BAD_PARAMETER if max is not between 16 and 1048576
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setAqmMaxEnqueuedPackets(ZT_Node *node,unsigned int max);
+
+/**
+ * Set the sizes of the RX and TX queues
+ *
+ * The RX queue holds packets waiting for fragments or for WHOIS replies.
+ * The TX queue holds packets to peers that are not known yet, and its size
+ * also caps each multicast group's send queue. Small devices can lower
+ * these to save memory, busy roots and controllers can raise them.
+ *
+ * The TX queue size can be changed at any time. The RX queue can only be
+ * resized before the node has received its first packet, so call this
+ * right after ZT_Node_new() to change it.
+ *
+ * @param node Node instance
+ * @param rx RX queue size or 0 for the default (32)
+ * @param tx TX queue size or 0 for the default (32)
+ * @return OK, ZT_RESULT_ERROR_BAD_PARAMETER if a size is not between 8 and 65536, or ZT_RESULT_ERROR_UNSUPPORTED_OPERATION if the RX queue could not be resized (the TX size is still applied)
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setQueueSizes(ZT_Node *node,unsigned int rx,unsigned int tx);
+
+/**
+ * Set how long a peer that has not been heard from is kept in memory
+ *
+ * Idle peers are written to the peer state object and dropped after this
+ * long. Nodes that talk to many short-lived peers can lower it, and nodes
+ * that would rather not look peers up again after a quiet spell can raise
+ * it. Upstreams are always kept.
+ *
+ * @param node Node instance
+ * @param timeout Timeout in milliseconds or 0 to restore the default (500000)
+ * @return OK or ZT_RESULT_ERROR_BAD_PARAMETER if timeout is not between 30000 and 604800000
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_setPeerIdleTimeout(ZT_Node *node,uint64_t timeout);
+
+/**
+ * Turn low-power mode on or off
+ *
+ * For laptops on battery and devices on metered connections. While it is
+ * on, keepalives set with ZT_Node_setPeerKeepalive and
+ * ZT_Node_setNetworkKeepalive are not sent, upstream endpoints without a
+ * live path and bonded paths are not probed, MTU probes are stopped and
+ * refused, and direct paths are advertised to relayed peers only as often
+ * as to peers we already have a path to. The ping and heartbeat periods
+ * themselves are unchanged, since peers and roots expire paths based on them.
+ *
+ * @param node Node instance
+ * @param enabled Non-zero to turn low-power mode on
+ */
+ZT_SDK_API void ZT_Node_setLowPower(ZT_Node *node,int enabled);
+
 /**
  * Get this node's 40-bit ZeroTier address
  *
@@ -2078,6 +2371,25 @@ ZT_SDK_API void ZT_Node_status(ZT_Node *node,ZT_NodeStatus *status);
  */
 ZT_SDK_API ZT_PeerList *ZT_Node_peers(ZT_Node *node);
 
+/**
+ * Get a filtered page of known peer nodes
+ *
+ * Peers are in address order, as with ZT_Node_peers(). Filters are applied
+ * before the offset and limit, and only the peers returned are copied, so
+ * nodes with many peers can be listed a page at a time.
+ *
+ * The pointer returned here must be freed with freeQueryResult()
+ * when you are done with it.
+ *
+ * @param node Node instance
+ * @param role Only return peers with this role (a ZT_PeerRole), or -1 for any (other values match nothing)
+ * @param activeSince Only return peers with a path we received on at or after this time in ms, or 0 for any
+ * @param offset Number of matching peers to skip
+ * @param limit Maximum number of peers to return, or 0 for no limit
+ * @return List of peers or NULL on failure
+ */
+ZT_SDK_API ZT_PeerList *ZT_Node_peersPage(ZT_Node *node,int role,int64_t activeSince,unsigned long offset,unsigned long limit);
+
 /**
  * Get the status of a virtual network
  *
@@ -2090,6 +2402,19 @@ ZT_SDK_API ZT_PeerList *ZT_Node_peers(ZT_Node *node);
  */
 ZT_SDK_API ZT_VirtualNetworkConfig *ZT_Node_networkConfig(ZT_Node *node,uint64_t nwid);
 
+/**
+ * Get the traffic counters of a virtual network
+ *
+ * These are not part of ZT_VirtualNetworkConfig so that its size, and the
+ * layout of ZT_VirtualNetworkList, stay the same as in earlier versions.
+ *
+ * @param node Node instance
+ * @param nwid 64-bit network ID
+ * @param stats Buffer to fill with counters
+ * @return OK or ZT_RESULT_ERROR_NETWORK_NOT_FOUND if we are not a member of this network
+ */
+ZT_SDK_API enum ZT_ResultCode ZT_Node_networkStats(ZT_Node *node,uint64_t nwid,ZT_VirtualNetworkStats *stats);
+
 /**
  * Enumerate and get status of all networks
  *
@@ -2189,6 +2514,16 @@ ZT_SDK_API enum ZT_ResultCode ZT_Node_setPhysicalPathConfiguration(ZT_Node *node
  */
 ZT_SDK_API void ZT_version(int *major,int *minor,int *revision);
 
+/**
+ * Get the ZT_API_VERSION the core was built with
+ *
+ * Callers should refuse to run if this differs from the ZT_API_VERSION in
+ * the header they were compiled against.
+ *
+ * @return Structure layout version
+ */
+ZT_SDK_API int ZT_apiVersion(void);
+
 #ifdef __cplusplus
 }
 #endif
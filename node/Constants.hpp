@@ -212,14 +212,35 @@
 #define ZT_MAX_PACKET_FRAGMENTS 7
 
 /**
- * Size of RX queue
+ * Default size of RX queue
+ *
+ * This can be changed with ZT_Node_setQueueSizes(), e.g. smaller for
+ * constrained devices or larger for busy roots and controllers. The
+ * default itself may be overridden at build time.
  */
+#ifndef ZT_RX_QUEUE_SIZE
 #define ZT_RX_QUEUE_SIZE 32
+#endif
 
 /**
- * Size of TX queue
+ * Default size of TX queue (may be overridden at build time)
  */
+#ifndef ZT_TX_QUEUE_SIZE
 #define ZT_TX_QUEUE_SIZE 32
+#endif
+
+/**
+ * Bounds for RX and TX queue sizes
+ */
+#define ZT_QUEUE_SIZE_MIN 8
+#define ZT_QUEUE_SIZE_MAX 65536
+
+#if (ZT_RX_QUEUE_SIZE < ZT_QUEUE_SIZE_MIN) || (ZT_RX_QUEUE_SIZE > ZT_QUEUE_SIZE_MAX)
+#error ZT_RX_QUEUE_SIZE must be between 8 and 65536
+#endif
+#if (ZT_TX_QUEUE_SIZE < ZT_QUEUE_SIZE_MIN) || (ZT_TX_QUEUE_SIZE > ZT_QUEUE_SIZE_MAX)
+#error ZT_TX_QUEUE_SIZE must be between 8 and 65536
+#endif
 
 /**
  * Minimum delay between timer task checks to prevent thrashing
@@ -290,6 +311,42 @@
  */
 #define ZT_PATH_HEARTBEAT_PERIOD 14000
 
+/**
+ * Number of MTU probe sizes
+ */
+#define ZT_PATH_MTU_PROBE_COUNT 5
+
+/**
+ * Sizes of MTU probe packets in bytes (UDP payload), smallest first
+ *
+ * The largest is the default physical MTU, since ZeroTier never sends
+ * larger packets unless a path's MTU is raised in local.conf.
+ */
+#define ZT_PATH_MTU_PROBE_SIZES { 576,1024,1280,ZT_MIN_PHYSMTU,ZT_DEFAULT_PHYSMTU }
+
+/**
+ * Number of times an MTU probe size is sent before it is considered lost
+ */
+#define ZT_PATH_MTU_PROBE_TRIES 3
+
+/**
+ * Interval between MTU probe packets to a peer
+ *
+ * This is above ZT_PEER_GENERAL_RATE_LIMIT so that peers that rate limit
+ * ECHO, as some older versions do, answer every probe.
+ */
+#define ZT_PATH_MTU_PROBE_INTERVAL 1250
+
+/**
+ * Time after which an unanswered MTU probe packet is considered lost
+ */
+#define ZT_PATH_MTU_PROBE_TIMEOUT 5000
+
+/**
+ * Shortest keepalive interval that can be configured for a peer
+ */
+#define ZT_PEER_KEEPALIVE_MIN_INTERVAL 1000
+
 /**
  * Do not accept HELLOs over a given path more often than this
  */
@@ -300,6 +357,19 @@
  */
 #define ZT_PEER_PING_PERIOD 60000
 
+/**
+ * Delay between HELLOs to upstream stable endpoints that are not answering
+ */
+#define ZT_UPSTREAM_ENDPOINT_PROBE_PERIOD 60000
+
+/**
+ * Hysteresis for switching a peer's best path, e.g. between IPv4 and IPv6
+ *
+ * The currently selected path's quality metric is improved by 1/N when
+ * comparing, so another path must be meaningfully better to replace it.
+ */
+#define ZT_PEER_PATH_SELECTION_HYSTERESIS 8
+
 /**
  * Paths are considered expired if they have not sent us a real packet in this long
  */
@@ -391,11 +461,17 @@
 #define ZT_AQM_QUANTUM ZT_DEFAULT_MTU
 
 /**
- * The maximum total number of packets that can be queued among all
- * active/inactive, old/new queues.
+ * Default maximum total number of packets that can be queued among all
+ * active/inactive, old/new queues (see ZT_Node_setAqmMaxEnqueuedPackets)
  */
 #define ZT_AQM_MAX_ENQUEUED_PACKETS 1024
 
+/**
+ * Range allowed for the maximum number of AQM enqueued packets
+ */
+#define ZT_AQM_MAX_ENQUEUED_PACKETS_MIN 16
+#define ZT_AQM_MAX_ENQUEUED_PACKETS_MAX 1048576
+
 /**
  * Number of QoS queues (buckets)
  */
@@ -523,6 +599,12 @@
 #define ZT_PEER_ACTIVITY_TIMEOUT 30000
 #endif
 
+/**
+ * Range allowed for the time an idle peer is kept in memory (see ZT_Node_setPeerIdleTimeout)
+ */
+#define ZT_PEER_IDLE_TIMEOUT_MIN 30000
+#define ZT_PEER_IDLE_TIMEOUT_MAX 604800000
+
 /**
  * General rate limit timeout for multiple packet types (HELLO, etc.)
  */
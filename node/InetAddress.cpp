@@ -281,7 +281,7 @@ bool InetAddress::containsAddress(const InetAddress &addr) const
 				const uint8_t *a = reinterpret_cast<const uint8_t *>(reinterpret_cast<const struct sockaddr_in6 *>(&addr)->sin6_addr.s6_addr);
 				const uint8_t *b = reinterpret_cast<const uint8_t *>(reinterpret_cast<const struct sockaddr_in6 *>(this)->sin6_addr.s6_addr);
 				for(unsigned int i=0;i<16;++i) {
-					if ((a[i] & m[i]) != b[i])
+					if ((a[i] & m[i]) != (b[i] & m[i]))
 						return false;
 				}
 				return true;
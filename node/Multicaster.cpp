@@ -262,7 +262,7 @@ void Multicaster::send(
 				}
 			}
 		} else {
-			while (gs.txQueue.size() >= ZT_TX_QUEUE_SIZE) {
+			while (gs.txQueue.size() >= RR->sw->txQueueSize()) {
 				gs.txQueue.pop_front();
 			}
 
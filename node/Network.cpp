@@ -550,10 +550,22 @@ Network::Network(const RuntimeEnvironment *renv,void *tPtr,uint64_t nwid,void *u
 	_lastConfigUpdate(0),
 	_destroyed(false),
 	_netconfFailure(NETCONF_FAILURE_NONE),
-	_portError(0)
+	_portError(0),
+	_keepalive(0),
+	_rxPackets(0),
+	_rxBytes(0),
+	_rxDropped(0),
+	_rxMulticast(0),
+	_rxUnknownEtherType(0),
+	_txPackets(0),
+	_txBytes(0),
+	_txDropped(0),
+	_txMulticast(0),
+	_txUnknownEtherType(0)
 {
 	for(int i=0;i<ZT_NETWORK_MAX_INCOMING_UPDATES;++i)
 		_incomingConfigChunks[i].ts = 0;
+	_joinToken[0] = (char)0;
 
 	if (nconf) {
 		this->setConfiguration(tPtr,*nconf,false);
@@ -1191,6 +1203,15 @@ void Network::requestConfiguration(void *tPtr)
 	rmd.add(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_MAX_NETWORK_TAGS,(uint64_t)ZT_MAX_NETWORK_TAGS);
 	rmd.add(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_FLAGS,(uint64_t)0);
 	rmd.add(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_RULES_ENGINE_REV,(uint64_t)ZT_RULES_ENGINE_REVISION);
+	{
+		Mutex::Lock _l(_lock);
+		if (_joinToken[0]) {
+			char auth[ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH + 8];
+			memcpy(auth,"token:",6);
+			Utils::scopy(auth + 6,sizeof(auth) - 6,_joinToken);
+			rmd.add(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_AUTH,auth);
+		}
+	}
 
 	RR->t->networkConfigRequestSent(tPtr,*this,ctrl);
 
@@ -1384,6 +1405,20 @@ ZT_VirtualNetworkStatus Network::_status() const
 	}
 }
 
+void Network::externalStats(ZT_VirtualNetworkStats *s) const
+{
+	s->rxPackets = _rxPackets.load(std::memory_order_relaxed);
+	s->rxBytes = _rxBytes.load(std::memory_order_relaxed);
+	s->rxDropped = _rxDropped.load(std::memory_order_relaxed);
+	s->rxMulticast = _rxMulticast.load(std::memory_order_relaxed);
+	s->rxUnknownEtherType = _rxUnknownEtherType.load(std::memory_order_relaxed);
+	s->txPackets = _txPackets.load(std::memory_order_relaxed);
+	s->txBytes = _txBytes.load(std::memory_order_relaxed);
+	s->txDropped = _txDropped.load(std::memory_order_relaxed);
+	s->txMulticast = _txMulticast.load(std::memory_order_relaxed);
+	s->txUnknownEtherType = _txUnknownEtherType.load(std::memory_order_relaxed);
+}
+
 void Network::_externalConfig(ZT_VirtualNetworkConfig *ec) const
 {
 	// assumes _lock is locked
@@ -23,6 +23,7 @@
 #include <vector>
 #include <algorithm>
 #include <stdexcept>
+#include <atomic>
 
 #include "Constants.hpp"
 #include "Hashtable.hpp"
@@ -253,6 +254,46 @@ public:
 	 */
 	void clean();
 
+	/**
+	 * Count a frame delivered to or read from this network's virtual port
+	 *
+	 * @param inbound True if delivered to the port, false if read from it
+	 * @param len Frame length in bytes
+	 * @param multicast True if destination is a multicast or broadcast MAC
+	 * @param etherType Ethernet frame type
+	 */
+	inline void countFrame(const bool inbound,const unsigned int len,const bool multicast,const unsigned int etherType)
+	{
+		const bool unknownEtherType = ((etherType != 0x0800)&&(etherType != 0x0806)&&(etherType != 0x86dd)); // not IPv4, ARP, or IPv6
+		if (inbound) {
+			_rxPackets.fetch_add(1,std::memory_order_relaxed);
+			_rxBytes.fetch_add(len,std::memory_order_relaxed);
+			if (multicast)
+				_rxMulticast.fetch_add(1,std::memory_order_relaxed);
+			if (unknownEtherType)
+				_rxUnknownEtherType.fetch_add(1,std::memory_order_relaxed);
+		} else {
+			_txPackets.fetch_add(1,std::memory_order_relaxed);
+			_txBytes.fetch_add(len,std::memory_order_relaxed);
+			if (multicast)
+				_txMulticast.fetch_add(1,std::memory_order_relaxed);
+			if (unknownEtherType)
+				_txUnknownEtherType.fetch_add(1,std::memory_order_relaxed);
+		}
+	}
+
+	/**
+	 * Count a frame dropped on its way to or from this network's virtual port
+	 *
+	 * @param inbound True if received from a peer, false if read from the port
+	 */
+	inline void countDroppedFrame(const bool inbound)
+	{
+		if (inbound)
+			_rxDropped.fetch_add(1,std::memory_order_relaxed);
+		else _txDropped.fetch_add(1,std::memory_order_relaxed);
+	}
+
 	/**
 	 * Push state to members such as multicast group memberships and latest COM (if needed)
 	 *
@@ -389,11 +430,58 @@ public:
 		_externalConfig(ec);
 	}
 
+	/**
+	 * Get this network's traffic counters for export via the ZT core API
+	 *
+	 * @param s Buffer to fill with counters
+	 */
+	void externalStats(ZT_VirtualNetworkStats *s) const;
+
 	/**
 	 * @return Externally usable pointer-to-pointer exported via the core API
 	 */
 	inline void **userPtr() { return &_uPtr; }
 
+	/**
+	 * Set the token presented to the controller with config requests
+	 *
+	 * @param token Join token or NULL/empty for none
+	 */
+	inline void setJoinToken(const char *token)
+	{
+		Mutex::Lock _l(_lock);
+		Utils::scopy(_joinToken,sizeof(_joinToken),(token) ? token : "");
+	}
+
+	/**
+	 * Set how often to send keepalives to this network's controller and known members
+	 *
+	 * @param interval Interval in milliseconds or 0 for the default
+	 */
+	inline void setKeepalive(unsigned int interval)
+	{
+		Mutex::Lock _l(_lock);
+		_keepalive = interval;
+	}
+
+	/**
+	 * @return Keepalive interval in milliseconds or 0 if none is set
+	 */
+	inline unsigned int keepalive() const
+	{
+		Mutex::Lock _l(_lock);
+		return _keepalive;
+	}
+
+	/**
+	 * @return Addresses of members we currently have membership records for
+	 */
+	inline std::vector<Address> memberAddresses() const
+	{
+		Mutex::Lock _l(_lock);
+		return _memberships.keys();
+	}
+
 private:
 	ZT_VirtualNetworkStatus _status() const;
 	void _externalConfig(ZT_VirtualNetworkConfig *ec) const; // assumes _lock is locked
@@ -438,11 +526,17 @@ private:
 		NETCONF_FAILURE_INIT_FAILED
 	} _netconfFailure;
 	int _portError; // return value from port config callback
+	char _joinToken[ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH + 1];
+	unsigned int _keepalive;
 
 	Hashtable<Address,Membership> _memberships;
 
 	Mutex _lock;
 
+	// Traffic counters (see ZT_VirtualNetworkStats), atomic since they are updated for every frame
+	std::atomic<uint64_t> _rxPackets,_rxBytes,_rxDropped,_rxMulticast,_rxUnknownEtherType;
+	std::atomic<uint64_t> _txPackets,_txBytes,_txDropped,_txMulticast,_txUnknownEtherType;
+
 	AtomicCounter __refCount;
 };
 
@@ -46,11 +46,17 @@ Node::Node(void *uptr,void *tptr,const struct ZT_Node_Callbacks *callbacks,int64
 	RR(&_RR),
 	_uPtr(uptr),
 	_networks(8),
+	_minPeerKeepalive(0),
+	_noRelay(false),
+	_preferredFamily(0),
 	_now(now),
 	_lastPingCheck(0),
 	_lastGratuitousPingCheck(0),
+	_lastKeepaliveCheck(0),
+	_lastUpstreamEndpointProbe(0),
 	_lastHousekeepingRun(0),
-	_lastMemoizedTraceSettings(0)
+	_lastMemoizedTraceSettings(0),
+	_lowPower(false)
 {
 	if (callbacks->version != 0)
 		throw ZT_EXCEPTION_INVALID_ARGUMENT;
@@ -180,6 +186,7 @@ ZT_ResultCode Node::processVirtualNetworkFrame(
 	_now = now;
 	SharedPtr<Network> nw(this->network(nwid));
 	if (nw) {
+		nw->countFrame(false,frameLength,MAC(destMac).isMulticast(),etherType);
 		RR->sw->onLocalEthernet(tptr,nw,MAC(sourceMac),MAC(destMac),etherType,vlanId,frameData,frameLength);
 		return ZT_RESULT_OK;
 	} else return ZT_RESULT_ERROR_NETWORK_NOT_FOUND;
@@ -189,11 +196,12 @@ ZT_ResultCode Node::processVirtualNetworkFrame(
 class _PingPeersThatNeedPing
 {
 public:
-	_PingPeersThatNeedPing(const RuntimeEnvironment *renv,void *tPtr,Hashtable< Address,std::vector<InetAddress> > &alwaysContact,int64_t now) :
+	_PingPeersThatNeedPing(const RuntimeEnvironment *renv,void *tPtr,Hashtable< Address,std::vector<InetAddress> > &alwaysContact,int64_t now,bool probeEndpoints = false) :
 		RR(renv),
 		_tPtr(tPtr),
 		_alwaysContact(alwaysContact),
 		_now(now),
+		_probeEndpoints(probeEndpoints),
 		_bestCurrentUpstream(RR->topology->getUpstreamPeer())
 	{
 	}
@@ -233,6 +241,22 @@ public:
 					p->sendHELLO(_tPtr,up->localSocket(),up->address(),_now);
 			}
 
+			if (_probeEndpoints) {
+				// Also try every stable endpoint with no live path so that its health is known
+				const std::vector< SharedPtr<Path> > paths(p->paths(_now));
+				for(std::vector<InetAddress>::const_iterator a(alwaysContactEndpoints->begin());a!=alwaysContactEndpoints->end();++a) {
+					bool alive = false;
+					for(std::vector< SharedPtr<Path> >::const_iterator path(paths.begin());path!=paths.end();++path) {
+						if (((*path)->address() == *a)&&((*path)->alive(_now))) {
+							alive = true;
+							break;
+						}
+					}
+					if (!alive)
+						p->sendHELLO(_tPtr,-1,*a,_now);
+				}
+			}
+
 			_alwaysContact.erase(p->address()); // after this we'll WHOIS all upstreams that remain
 		} else if (p->isActive(_now)) {
 			p->doPingAndKeepalive(_tPtr,_now);
@@ -244,6 +268,7 @@ private:
 	void *_tPtr;
 	Hashtable< Address,std::vector<InetAddress> > &_alwaysContact;
 	const int64_t _now;
+	const bool _probeEndpoints;
 	const SharedPtr<Peer> _bestCurrentUpstream;
 };
 
@@ -257,7 +282,7 @@ ZT_ResultCode Node::processBackgroundTasks(void *tptr,int64_t now,volatile int64
 	if (RR->bc->inUse()) {
 		// Gratuitously ping active peers so that QoS metrics have enough data to work with (if active path monitoring is enabled)
 		bondCheckInterval = std::min(std::max(RR->bc->minReqPathMonitorInterval(), ZT_CORE_TIMER_TASK_GRANULARITY), ZT_PING_CHECK_INVERVAL);
-		if ((now - _lastGratuitousPingCheck) >= bondCheckInterval) {
+		if ((!_lowPower)&&((now - _lastGratuitousPingCheck) >= bondCheckInterval)) {
 			Hashtable< Address,std::vector<InetAddress> > alwaysContact;
 			_PingPeersThatNeedPing pfunc(RR,tptr,alwaysContact,now);
 			RR->topology->eachPeer<_PingPeersThatNeedPing &>(pfunc);
@@ -266,6 +291,54 @@ ZT_ResultCode Node::processBackgroundTasks(void *tptr,int64_t now,volatile int64
 		RR->bc->processBackgroundTasks(tptr, now);
 	}
 
+	// Peers with a configured keepalive, directly or through a network, are kept open even while idle
+	unsigned long keepaliveCheckInterval = ZT_PING_CHECK_INVERVAL;
+	{
+		bool keepaliveCheckDue = false;
+		{
+			Mutex::Lock _l(_peerKeepalives_m);
+			if ((_minPeerKeepalive)&&(!_lowPower)) {
+				keepaliveCheckInterval = std::max(std::min((unsigned long)(_minPeerKeepalive / 2),keepaliveCheckInterval),(unsigned long)ZT_CORE_TIMER_TASK_GRANULARITY);
+				if ((now - _lastKeepaliveCheck) >= (int64_t)keepaliveCheckInterval) {
+					_lastKeepaliveCheck = now;
+					keepaliveCheckDue = true;
+				}
+			}
+		}
+		std::vector<Address> keepalivePeers;
+		if (keepaliveCheckDue) {
+			_updateNetworkKeepalives(); // picks up members learned since the last check
+			Mutex::Lock _l(_peerKeepalives_m);
+			keepalivePeers = _peerKeepalives.keys();
+			Hashtable< Address,unsigned int >::Iterator i(_networkKeepalives);
+			Address *a = (Address *)0;
+			unsigned int *v = (unsigned int *)0;
+			while (i.next(a,v)) {
+				if (!_peerKeepalives.contains(*a))
+					keepalivePeers.push_back(*a);
+			}
+		}
+		for(std::vector<Address>::const_iterator a(keepalivePeers.begin());a!=keepalivePeers.end();++a) {
+			const SharedPtr<Peer> p(RR->topology->getPeerNoCache(*a));
+			if (p)
+				p->doPingAndKeepalive(tptr,now);
+		}
+	}
+
+	// Peers with an MTU probe in progress are sent their next probe packet when it is due
+	unsigned long mtuProbeCheckInterval = ZT_PING_CHECK_INVERVAL;
+	{
+		Mutex::Lock _l(_mtuProbePeers_m);
+		for(std::vector<Address>::iterator a(_mtuProbePeers.begin());a!=_mtuProbePeers.end();) {
+			const SharedPtr<Peer> p(RR->topology->getPeerNoCache(*a));
+			if ((p)&&(p->sendNextPathMtuProbe(tptr,now)))
+				++a;
+			else a = _mtuProbePeers.erase(a);
+		}
+		if (!_mtuProbePeers.empty())
+			mtuProbeCheckInterval = ZT_CORE_TIMER_TASK_GRANULARITY;
+	}
+
 	unsigned long timeUntilNextPingCheck = ZT_PING_CHECK_INVERVAL;
 	const int64_t timeSinceLastPingCheck = now - _lastPingCheck;
 	if (timeSinceLastPingCheck >= timeUntilNextPingCheck) {
@@ -326,7 +399,10 @@ ZT_ResultCode Node::processBackgroundTasks(void *tptr,int64_t now,volatile int64
 			}
 
 			// Ping active peers, upstreams, and others that we should always contact
-			_PingPeersThatNeedPing pfunc(RR,tptr,alwaysContact,now);
+			const bool probeEndpoints = ((!_lowPower)&&((now - _lastUpstreamEndpointProbe) >= ZT_UPSTREAM_ENDPOINT_PROBE_PERIOD));
+			if (probeEndpoints)
+				_lastUpstreamEndpointProbe = now;
+			_PingPeersThatNeedPing pfunc(RR,tptr,alwaysContact,now,probeEndpoints);
 			RR->topology->eachPeer<_PingPeersThatNeedPing &>(pfunc);
 
 			// Run WHOIS to create Peer for alwaysContact addresses that could not be contacted
@@ -368,13 +444,26 @@ ZT_ResultCode Node::processBackgroundTasks(void *tptr,int64_t now,volatile int64
 			RR->topology->doPeriodicTasks(tptr,now);
 			RR->sa->clean(now);
 			RR->mc->clean(now);
+
+			// Drop memberships of forgotten peers and credentials that are no longer valid
+			std::vector< SharedPtr<Network> > networks;
+			{
+				Mutex::Lock l(_networks_m);
+				Hashtable< uint64_t,SharedPtr<Network> >::Iterator i(_networks);
+				uint64_t *nwid = (uint64_t *)0;
+				SharedPtr<Network> *network = (SharedPtr<Network> *)0;
+				while (i.next(nwid,network))
+					networks.push_back(*network);
+			}
+			for(std::vector< SharedPtr<Network> >::iterator n(networks.begin());n!=networks.end();++n)
+				(*n)->clean();
 		} catch ( ... ) {
 			return ZT_RESULT_FATAL_ERROR_INTERNAL;
 		}
 	}
 
 	try {
-		*nextBackgroundTaskDeadline = now + (int64_t)std::max(std::min(bondCheckInterval,std::min(timeUntilNextPingCheck,RR->sw->doTimerTasks(tptr,now))),(unsigned long)ZT_CORE_TIMER_TASK_GRANULARITY);
+		*nextBackgroundTaskDeadline = now + (int64_t)std::max(std::min(std::min(std::min(bondCheckInterval,keepaliveCheckInterval),mtuProbeCheckInterval),std::min(timeUntilNextPingCheck,RR->sw->doTimerTasks(tptr,now))),(unsigned long)ZT_CORE_TIMER_TASK_GRANULARITY);
 	} catch ( ... ) {
 		return ZT_RESULT_FATAL_ERROR_INTERNAL;
 	}
@@ -415,6 +504,7 @@ ZT_ResultCode Node::leave(uint64_t nwid,void **uptr,void *tptr)
 		Mutex::Lock _l(_networks_m);
 		_networks.erase(nwid);
 	}
+	_updateNetworkKeepalives();
 
 	uint64_t tmp[2];
 	tmp[0] = nwid; tmp[1] = 0;
@@ -423,6 +513,179 @@ ZT_ResultCode Node::leave(uint64_t nwid,void **uptr,void *tptr)
 	return ZT_RESULT_OK;
 }
 
+// Shortest interval in a keepalive table, or m if that is shorter and nonzero
+static unsigned int _shortestKeepalive(Hashtable< Address,unsigned int > &t,unsigned int m)
+{
+	Hashtable< Address,unsigned int >::Iterator i(t);
+	Address *a = (Address *)0;
+	unsigned int *v = (unsigned int *)0;
+	while (i.next(a,v)) {
+		if ((!m)||(*v < m))
+			m = *v;
+	}
+	return m;
+}
+
+ZT_ResultCode Node::setNetworkJoinToken(uint64_t nwid,const char *token)
+{
+	if ((token)&&(strlen(token) > ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	SharedPtr<Network> nw(this->network(nwid));
+	if (!nw)
+		return ZT_RESULT_ERROR_NETWORK_NOT_FOUND;
+	nw->setJoinToken(token);
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::setPeerKeepalive(uint64_t address,unsigned int interval)
+{
+	if ((interval)&&((interval < ZT_PEER_KEEPALIVE_MIN_INTERVAL)||(interval > ZT_PATH_HEARTBEAT_PERIOD)))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	Mutex::Lock _l(_peerKeepalives_m);
+	if (interval)
+		_peerKeepalives.set(Address(address),interval);
+	else _peerKeepalives.erase(Address(address));
+	_minPeerKeepalive = _shortestKeepalive(_networkKeepalives,_shortestKeepalive(_peerKeepalives,0));
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::setNetworkKeepalive(uint64_t nwid,unsigned int interval)
+{
+	if ((interval)&&((interval < ZT_PEER_KEEPALIVE_MIN_INTERVAL)||(interval > ZT_PATH_HEARTBEAT_PERIOD)))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	SharedPtr<Network> nw(this->network(nwid));
+	if (!nw)
+		return ZT_RESULT_ERROR_NETWORK_NOT_FOUND;
+	nw->setKeepalive(interval);
+	_updateNetworkKeepalives();
+	return ZT_RESULT_OK;
+}
+
+void Node::_updateNetworkKeepalives()
+{
+	// Networks are queried without holding _peerKeepalives_m, which peerKeepalive() takes while
+	// Peer and Topology locks are held and so must never be held while taking a Network's lock.
+	std::vector< SharedPtr<Network> > networks;
+	{
+		Mutex::Lock _l(_networks_m);
+		Hashtable< uint64_t,SharedPtr<Network> >::Iterator i(_networks);
+		uint64_t *k = (uint64_t *)0;
+		SharedPtr<Network> *v = (SharedPtr<Network> *)0;
+		while (i.next(k,v))
+			networks.push_back(*v);
+	}
+
+	Hashtable< Address,unsigned int > nk;
+	for(std::vector< SharedPtr<Network> >::const_iterator nw(networks.begin());nw!=networks.end();++nw) {
+		const unsigned int interval = (*nw)->keepalive();
+		if (interval) {
+			std::vector<Address> members((*nw)->memberAddresses());
+			members.push_back((*nw)->controller());
+			for(std::vector<Address>::const_iterator a(members.begin());a!=members.end();++a) {
+				if (*a == RR->identity.address())
+					continue;
+				unsigned int &v = nk[*a];
+				if ((!v)||(interval < v))
+					v = interval;
+			}
+		}
+	}
+
+	Mutex::Lock _l(_peerKeepalives_m);
+	_networkKeepalives = nk;
+	_minPeerKeepalive = _shortestKeepalive(_networkKeepalives,_shortestKeepalive(_peerKeepalives,0));
+}
+
+ZT_ResultCode Node::setPeerNoRelay(uint64_t address,bool forbid)
+{
+	{
+		Mutex::Lock _l(_peerNoRelay_m);
+		if (!address)
+			_noRelay = forbid;
+		else if (forbid)
+			_peerNoRelay.set(Address(address),true);
+		else _peerNoRelay.erase(Address(address));
+	}
+	_applyPeerSettings(address);
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::setPeerPreferredFamily(uint64_t address,int family)
+{
+	if ((family != 0)&&(family != AF_INET)&&(family != AF_INET6))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	{
+		Mutex::Lock _l(_peerPreferredFamily_m);
+		if (!address)
+			_preferredFamily = family;
+		else if (family)
+			_peerPreferredFamily.set(Address(address),family);
+		else _peerPreferredFamily.erase(Address(address));
+	}
+	_applyPeerSettings(address);
+	return ZT_RESULT_OK;
+}
+
+void Node::applyPeerSettings(Peer &peer) const
+{
+	{
+		Mutex::Lock _l(_peerNoRelay_m);
+		peer.setNoRelay((_noRelay)||(_peerNoRelay.contains(peer.address())));
+	}
+	{
+		Mutex::Lock _l(_peerPreferredFamily_m);
+		const int *const f = _peerPreferredFamily.get(peer.address());
+		peer.setPreferredFamily((f) ? *f : _preferredFamily);
+	}
+}
+
+void Node::_applyPeerSettings(uint64_t address)
+{
+	if (address) {
+		const SharedPtr<Peer> p(RR->topology->getPeerNoCache(Address(address)));
+		if (p)
+			applyPeerSettings(*p);
+	} else {
+		const std::vector< std::pair< Address,SharedPtr<Peer> > > peers(RR->topology->allPeers());
+		for(std::vector< std::pair< Address,SharedPtr<Peer> > >::const_iterator p(peers.begin());p!=peers.end();++p)
+			applyPeerSettings(*(p->second));
+	}
+}
+
+ZT_ResultCode Node::setAqmMaxEnqueuedPackets(unsigned int max)
+{
+	if (!max)
+		max = ZT_AQM_MAX_ENQUEUED_PACKETS;
+	if ((max < ZT_AQM_MAX_ENQUEUED_PACKETS_MIN)||(max > ZT_AQM_MAX_ENQUEUED_PACKETS_MAX))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	RR->sw->setAqmMaxEnqueuedPackets(max);
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::setPeerIdleTimeout(uint64_t timeout)
+{
+	if (!timeout)
+		timeout = ZT_PEER_ACTIVITY_TIMEOUT;
+	if ((timeout < ZT_PEER_IDLE_TIMEOUT_MIN)||(timeout > ZT_PEER_IDLE_TIMEOUT_MAX))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	RR->topology->setPeerIdleTimeout((int64_t)timeout);
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::setQueueSizes(unsigned int rx,unsigned int tx)
+{
+	if (!rx)
+		rx = ZT_RX_QUEUE_SIZE;
+	if (!tx)
+		tx = ZT_TX_QUEUE_SIZE;
+	if ((rx < ZT_QUEUE_SIZE_MIN)||(rx > ZT_QUEUE_SIZE_MAX)||(tx < ZT_QUEUE_SIZE_MIN)||(tx > ZT_QUEUE_SIZE_MAX))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	RR->sw->setTxQueueSize(tx);
+	if (!RR->sw->setRxQueueSize(rx))
+		return ZT_RESULT_ERROR_UNSUPPORTED_OPERATION;
+	return ZT_RESULT_OK;
+}
+
 ZT_ResultCode Node::multicastSubscribe(void *tptr,uint64_t nwid,uint64_t multicastGroup,unsigned long multicastAdi)
 {
 	SharedPtr<Network> nw(this->network(nwid));
@@ -453,6 +716,53 @@ ZT_ResultCode Node::deorbit(void *tptr,uint64_t moonWorldId)
 	return ZT_RESULT_OK;
 }
 
+ZT_ResultCode Node::forgetPeer(void *tptr,uint64_t address)
+{
+	return (RR->topology->forgetPeer(tptr,Address(address))) ? ZT_RESULT_OK : ZT_RESULT_ERROR_UNSUPPORTED_OPERATION;
+}
+
+unsigned long Node::forgetLeafPeers(void *tptr)
+{
+	unsigned long forgotten = 0;
+	const std::vector< std::pair< Address,SharedPtr<Peer> > > peers(RR->topology->allPeers());
+	for(std::vector< std::pair< Address,SharedPtr<Peer> > >::const_iterator p(peers.begin());p!=peers.end();++p) {
+		if ((RR->topology->role(p->first) == ZT_PEER_ROLE_LEAF)&&(RR->topology->forgetPeer(tptr,p->first)))
+			++forgotten;
+	}
+	return forgotten;
+}
+
+ZT_ResultCode Node::clearPaths(uint64_t address)
+{
+	const SharedPtr<Peer> peer(RR->topology->getPeerNoCache(Address(address)));
+	if (!peer)
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	peer->clearPaths();
+	return ZT_RESULT_OK;
+}
+
+ZT_ResultCode Node::probePathMtu(void *tptr,uint64_t address)
+{
+	if (_lowPower)
+		return ZT_RESULT_ERROR_UNSUPPORTED_OPERATION;
+	const SharedPtr<Peer> peer(RR->topology->getPeerNoCache(Address(address)));
+	if ((!peer)||(peer->probePathMtu(tptr,_now) == 0))
+		return ZT_RESULT_ERROR_BAD_PARAMETER;
+	Mutex::Lock _l(_mtuProbePeers_m);
+	if (std::find(_mtuProbePeers.begin(),_mtuProbePeers.end(),peer->address()) == _mtuProbePeers.end())
+		_mtuProbePeers.push_back(peer->address());
+	return ZT_RESULT_OK;
+}
+
+void Node::setLowPower(bool enabled)
+{
+	_lowPower = enabled;
+	if (enabled) {
+		Mutex::Lock _l(_mtuProbePeers_m);
+		_mtuProbePeers.clear();
+	}
+}
+
 uint64_t Node::address() const
 {
 	return RR->identity.address().toInt();
@@ -466,11 +776,38 @@ void Node::status(ZT_NodeStatus *status) const
 	status->online = _online ? 1 : 0;
 }
 
-ZT_PeerList *Node::peers() const
+ZT_PeerList *Node::peers(int role,int64_t activeSince,unsigned long offset,unsigned long limit) const
 {
 	std::vector< std::pair< Address,SharedPtr<Peer> > > peers(RR->topology->allPeers());
 	std::sort(peers.begin(),peers.end());
 
+	// Drop peers that don't match or fall outside the page before anything is copied
+	std::vector< std::pair< Address,SharedPtr<Peer> > >::iterator w(peers.begin());
+	for(std::vector< std::pair< Address,SharedPtr<Peer> > >::iterator pi(peers.begin());pi!=peers.end();++pi) {
+		if ((limit)&&((unsigned long)(w - peers.begin()) >= limit))
+			break;
+		if ((role != -1)&&(role != (int)RR->topology->role(pi->first)))
+			continue;
+		if (activeSince > 0) {
+			bool active = false;
+			const std::vector< SharedPtr<Path> > paths(pi->second->paths(_now));
+			for(std::vector< SharedPtr<Path> >::const_iterator path(paths.begin());path!=paths.end();++path) {
+				if ((*path)->lastIn() >= activeSince) {
+					active = true;
+					break;
+				}
+			}
+			if (!active)
+				continue;
+		}
+		if (offset > 0) {
+			--offset;
+			continue;
+		}
+		*(w++) = *pi;
+	}
+	peers.erase(w,peers.end());
+
 	char *buf = (char *)::malloc(sizeof(ZT_PeerList) + (sizeof(ZT_Peer) * peers.size()));
 	if (!buf)
 		return (ZT_PeerList *)0;
@@ -495,9 +832,13 @@ ZT_PeerList *Node::peers() const
 		if (p->latency >= 0xffff)
 			p->latency = -1;
 		p->role = RR->topology->role(pi->second->identity().address());
+		p->macFailures = pi->second->macFailures();
+		p->aesGmacSiv = (pi->second->aesKeysIfSupported()) ? 1 : 0;
 
+		const unsigned int keepalive = peerKeepalive(pi->second->address());
 		std::vector< SharedPtr<Path> > paths(pi->second->paths(_now));
-		SharedPtr<Path> bestp(pi->second->getAppropriatePath(_now,false));
+		SharedPtr<Path> bestp(pi->second->getBestPath(_now,false));
+		p->relayed = ((!bestp)&&((_now - pi->second->lastReceive()) < ZT_PEER_PING_PERIOD)) ? 1 : 0;
 		p->pathCount = 0;
 		for(std::vector< SharedPtr<Path> >::iterator path(paths.begin());path!=paths.end();++path) {
 			memcpy(&(p->paths[p->pathCount].address),&((*path)->address()),sizeof(struct sockaddr_storage));
@@ -508,6 +849,11 @@ ZT_PeerList *Node::peers() const
 			p->paths[p->pathCount].expired = 0;
 			p->paths[p->pathCount].preferred = ((*path) == bestp) ? 1 : 0;
 			p->paths[p->pathCount].scope = (*path)->ipScope();
+			p->paths[p->pathCount].mtuProbeMaxSize = (*path)->mtuProbeMaxSize();
+			p->paths[p->pathCount].mtuProbeBlackhole = ((*path)->mtuProbeBlackhole(_now)) ? 1 : 0;
+			p->paths[p->pathCount].mtuProbeDone = ((*path)->mtuProbeDone(_now)) ? 1 : 0;
+			p->paths[p->pathCount].lastMtuProbe = (*path)->lastMtuProbe();
+			p->paths[p->pathCount].keepalive = (keepalive) ? keepalive : ZT_PATH_HEARTBEAT_PERIOD;
 			++p->pathCount;
 		}
 		if (pi->second->bond()) {
@@ -534,6 +880,17 @@ ZT_VirtualNetworkConfig *Node::networkConfig(uint64_t nwid) const
 	return (ZT_VirtualNetworkConfig *)0;
 }
 
+ZT_ResultCode Node::networkStats(uint64_t nwid,ZT_VirtualNetworkStats *stats) const
+{
+	Mutex::Lock _l(_networks_m);
+	const SharedPtr<Network> *nw = _networks.get(nwid);
+	if (nw) {
+		(*nw)->externalStats(stats);
+		return ZT_RESULT_OK;
+	}
+	return ZT_RESULT_ERROR_NETWORK_NOT_FOUND;
+}
+
 ZT_VirtualNetworkList *Node::networks() const
 {
 	Mutex::Lock _l(_networks_m);
@@ -870,6 +1227,78 @@ enum ZT_ResultCode ZT_Node_leave(ZT_Node *node,uint64_t nwid,void **uptr,void *t
 	}
 }
 
+enum ZT_ResultCode ZT_Node_setNetworkJoinToken(ZT_Node *node,uint64_t nwid,const char *token)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setNetworkJoinToken(nwid,token);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setPeerKeepalive(ZT_Node *node,uint64_t address,unsigned int interval)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setPeerKeepalive(address,interval);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setNetworkKeepalive(ZT_Node *node,uint64_t nwid,unsigned int interval)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setNetworkKeepalive(nwid,interval);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setPeerNoRelay(ZT_Node *node,uint64_t address,int forbid)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setPeerNoRelay(address,(forbid != 0));
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setPeerPreferredFamily(ZT_Node *node,uint64_t address,int family)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setPeerPreferredFamily(address,family);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setAqmMaxEnqueuedPackets(ZT_Node *node,unsigned int max)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setAqmMaxEnqueuedPackets(max);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setPeerIdleTimeout(ZT_Node *node,uint64_t timeout)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setPeerIdleTimeout(timeout);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_setQueueSizes(ZT_Node *node,unsigned int rx,unsigned int tx)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->setQueueSizes(rx,tx);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
 enum ZT_ResultCode ZT_Node_multicastSubscribe(ZT_Node *node,void *tptr,uint64_t nwid,uint64_t multicastGroup,unsigned long multicastAdi)
 {
 	try {
@@ -910,6 +1339,40 @@ enum ZT_ResultCode ZT_Node_deorbit(ZT_Node *node,void *tptr,uint64_t moonWorldId
 	}
 }
 
+enum ZT_ResultCode ZT_Node_forgetPeer(ZT_Node *node,void *tptr,uint64_t address)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->forgetPeer(tptr,address);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_clearPaths(ZT_Node *node,uint64_t address)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->clearPaths(address);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+enum ZT_ResultCode ZT_Node_probePathMtu(ZT_Node *node,void *tptr,uint64_t address)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->probePathMtu(tptr,address);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
+void ZT_Node_setLowPower(ZT_Node *node,int enabled)
+{
+	try {
+		reinterpret_cast<ZeroTier::Node *>(node)->setLowPower(enabled != 0);
+	} catch ( ... ) {}
+}
+
 uint64_t ZT_Node_address(ZT_Node *node)
 {
 	return reinterpret_cast<ZeroTier::Node *>(node)->address();
@@ -931,6 +1394,15 @@ ZT_PeerList *ZT_Node_peers(ZT_Node *node)
 	}
 }
 
+ZT_PeerList *ZT_Node_peersPage(ZT_Node *node,int role,int64_t activeSince,unsigned long offset,unsigned long limit)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->peers(role,activeSince,offset,limit);
+	} catch ( ... ) {
+		return (ZT_PeerList *)0;
+	}
+}
+
 ZT_VirtualNetworkConfig *ZT_Node_networkConfig(ZT_Node *node,uint64_t nwid)
 {
 	try {
@@ -940,6 +1412,15 @@ ZT_VirtualNetworkConfig *ZT_Node_networkConfig(ZT_Node *node,uint64_t nwid)
 	}
 }
 
+enum ZT_ResultCode ZT_Node_networkStats(ZT_Node *node,uint64_t nwid,ZT_VirtualNetworkStats *stats)
+{
+	try {
+		return reinterpret_cast<ZeroTier::Node *>(node)->networkStats(nwid,stats);
+	} catch ( ... ) {
+		return ZT_RESULT_FATAL_ERROR_INTERNAL;
+	}
+}
+
 ZT_VirtualNetworkList *ZT_Node_networks(ZT_Node *node)
 {
 	try {
@@ -1004,4 +1485,9 @@ void ZT_version(int *major,int *minor,int *revision)
 	if (revision) *revision = ZEROTIER_ONE_VERSION_REVISION;
 }
 
+int ZT_apiVersion(void)
+{
+	return ZT_API_VERSION;
+}
+
 } // extern "C"
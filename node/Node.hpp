@@ -43,6 +43,7 @@
 namespace ZeroTier {
 
 class World;
+class Peer;
 
 /**
  * Implementation of Node object as defined in CAPI
@@ -85,14 +86,27 @@ public:
 	ZT_ResultCode processBackgroundTasks(void *tptr,int64_t now,volatile int64_t *nextBackgroundTaskDeadline);
 	ZT_ResultCode join(uint64_t nwid,void *uptr,void *tptr);
 	ZT_ResultCode leave(uint64_t nwid,void **uptr,void *tptr);
+	ZT_ResultCode setNetworkJoinToken(uint64_t nwid,const char *token);
+	ZT_ResultCode setPeerKeepalive(uint64_t address,unsigned int interval);
+	ZT_ResultCode setNetworkKeepalive(uint64_t nwid,unsigned int interval);
+	ZT_ResultCode setPeerNoRelay(uint64_t address,bool forbid);
+	ZT_ResultCode setPeerPreferredFamily(uint64_t address,int family);
+	ZT_ResultCode setAqmMaxEnqueuedPackets(unsigned int max);
+	ZT_ResultCode setQueueSizes(unsigned int rx,unsigned int tx);
+	ZT_ResultCode setPeerIdleTimeout(uint64_t timeout);
 	ZT_ResultCode multicastSubscribe(void *tptr,uint64_t nwid,uint64_t multicastGroup,unsigned long multicastAdi);
 	ZT_ResultCode multicastUnsubscribe(uint64_t nwid,uint64_t multicastGroup,unsigned long multicastAdi);
 	ZT_ResultCode orbit(void *tptr,uint64_t moonWorldId,uint64_t moonSeed);
 	ZT_ResultCode deorbit(void *tptr,uint64_t moonWorldId);
+	ZT_ResultCode forgetPeer(void *tptr,uint64_t address);
+	ZT_ResultCode clearPaths(uint64_t address);
+	ZT_ResultCode probePathMtu(void *tptr,uint64_t address);
 	uint64_t address() const;
 	void status(ZT_NodeStatus *status) const;
-	ZT_PeerList *peers() const;
+	ZT_PeerList *peers() const { return peers(-1,0,0,0); }
+	ZT_PeerList *peers(int role,int64_t activeSince,unsigned long offset,unsigned long limit) const;
 	ZT_VirtualNetworkConfig *networkConfig(uint64_t nwid) const;
+	ZT_ResultCode networkStats(uint64_t nwid,ZT_VirtualNetworkStats *stats) const;
 	ZT_VirtualNetworkList *networks() const;
 	void freeQueryResult(void *qr);
 	int addLocalInterfaceAddress(const struct sockaddr_storage *addr);
@@ -142,6 +156,53 @@ public:
 		return SharedPtr<Network>();
 	}
 
+	// Keepalive interval for a peer in ms: its own if set, else the shortest of its networks', else 0 for the default (always 0 in low-power mode)
+	inline unsigned int peerKeepalive(const Address &a) const
+	{
+		if (_lowPower)
+			return 0;
+		Mutex::Lock _l(_peerKeepalives_m);
+		const unsigned int *v = _peerKeepalives.get(a);
+		if (!v)
+			v = _networkKeepalives.get(a);
+		return (v) ? *v : 0;
+	}
+
+	// True if network traffic to or from this peer must not be relayed (Peer::noRelay() caches this)
+	inline bool peerNoRelay(const Address &a) const
+	{
+		Mutex::Lock _l(_peerNoRelay_m);
+		return ((_noRelay)||(_peerNoRelay.contains(a)));
+	}
+
+	// Address family (AF_INET or AF_INET6) to prefer for direct paths to this peer, or 0 for none
+	inline int peerPreferredFamily(const Address &a) const
+	{
+		Mutex::Lock _l(_peerPreferredFamily_m);
+		const int *const f = _peerPreferredFamily.get(a);
+		return (f) ? *f : _preferredFamily;
+	}
+
+	/**
+	 * Copy the current relay and address family settings for a peer into it
+	 *
+	 * Topology calls this for each new peer and the setters call it for the
+	 * peers they change. Each peer's values are read and stored with the
+	 * settings' lock held, so the last one stored is always the current one.
+	 */
+	void applyPeerSettings(Peer &peer) const;
+
+	/**
+	 * Forget every peer that is not a root or moon (used by the service's panic)
+	 *
+	 * Each peer is dropped from Topology and its cached state is deleted. Its
+	 * key is wiped when the last reference to it goes away, which may be a
+	 * little later if a queued packet or bond still holds one.
+	 *
+	 * @return Number of peers forgotten
+	 */
+	unsigned long forgetLeafPeers(void *tptr);
+
 	inline bool belongsToNetwork(uint64_t nwid) const
 	{
 		Mutex::Lock _l(_networks_m);
@@ -171,6 +232,8 @@ public:
 	inline int configureVirtualNetworkPort(void *tPtr,uint64_t nwid,void **nuptr,ZT_VirtualNetworkConfigOperation op,const ZT_VirtualNetworkConfig *nc) { return _cb.virtualNetworkConfigFunction(reinterpret_cast<ZT_Node *>(this),_uPtr,tPtr,nwid,nuptr,op,nc); }
 
 	inline bool online() const { return _online; }
+	inline bool lowPower() const { return _lowPower; }
+	void setLowPower(bool enabled);
 
 	inline int stateObjectGet(void *const tPtr,ZT_StateObjectType type,const uint64_t id[2],void *const data,const unsigned int maxlen) { return _cb.stateGetFunction(reinterpret_cast<ZT_Node *>(this),_uPtr,tPtr,type,id,data,maxlen); }
 	inline void stateObjectPut(void *const tPtr,ZT_StateObjectType type,const uint64_t id[2],const void *const data,const unsigned int len) { _cb.statePutFunction(reinterpret_cast<ZT_Node *>(this),_uPtr,tPtr,type,id,data,(int)len); }
@@ -267,6 +330,9 @@ public:
 	}
 
 private:
+	void _updateNetworkKeepalives();
+	void _applyPeerSettings(uint64_t address); // to one peer or to all if address is 0
+
 	RuntimeEnvironment _RR;
 	RuntimeEnvironment *RR;
 	void *_uPtr; // _uptr (lower case) is reserved in Visual Studio :P
@@ -303,16 +369,35 @@ private:
 
 	Mutex _backgroundTasksLock;
 
+	Hashtable< Address,unsigned int > _peerKeepalives;
+	Hashtable< Address,unsigned int > _networkKeepalives; // controllers and members of networks with a keepalive, rebuilt by _updateNetworkKeepalives()
+	unsigned int _minPeerKeepalive;
+	Mutex _peerKeepalives_m;
+
+	std::vector<Address> _mtuProbePeers;
+	Mutex _mtuProbePeers_m;
+
+	Hashtable< Address,bool > _peerNoRelay;
+	bool _noRelay;
+	Mutex _peerNoRelay_m;
+
+	Hashtable< Address,int > _peerPreferredFamily;
+	int _preferredFamily;
+	Mutex _peerPreferredFamily_m;
+
 	Address _remoteTraceTarget;
 	enum Trace::Level _remoteTraceLevel;
 
 	volatile int64_t _now;
 	int64_t _lastPingCheck;
 	int64_t _lastGratuitousPingCheck;
+	int64_t _lastKeepaliveCheck;
+	int64_t _lastUpstreamEndpointProbe;
 	int64_t _lastHousekeepingRun;
 	int64_t _lastMemoizedTraceSettings;
 	volatile int64_t _prngState[2];
 	bool _online;
+	volatile bool _lowPower;
 };
 
 } // namespace ZeroTier
@@ -28,6 +28,7 @@
 #include "Utils.hpp"
 #include "Packet.hpp"
 #include "RingBuffer.hpp"
+#include "Mutex.hpp"
 
 #include "../osdep/Link.hpp"
 
@@ -127,8 +128,16 @@ public:
 		_packetsReceivedSinceLastQoS(0),
 		_bytesAckedSinceLastThroughputEstimation(0),
 		_packetsIn(0),
-		_packetsOut(0)
-		{}
+		_packetsOut(0),
+		_lastMtuProbe(0),
+		_lastMtuProbeSent(0),
+		_mtuProbesAnswered(0),
+		_mtuProbeSending(false),
+		_mtuProbeCancelled(false)
+		{
+			memset(_mtuProbeIds,0,sizeof(_mtuProbeIds));
+			memset(_mtuProbeTries,0,sizeof(_mtuProbeTries));
+		}
 
 	Path(const int64_t localSocket,const InetAddress &addr) :
 		_lastOut(0),
@@ -176,8 +185,16 @@ public:
 		_packetsReceivedSinceLastQoS(0),
 		_bytesAckedSinceLastThroughputEstimation(0),
 		_packetsIn(0),
-		_packetsOut(0)
-	{}
+		_packetsOut(0),
+		_lastMtuProbe(0),
+		_lastMtuProbeSent(0),
+		_mtuProbesAnswered(0),
+		_mtuProbeSending(false),
+		_mtuProbeCancelled(false)
+	{
+		memset(_mtuProbeIds,0,sizeof(_mtuProbeIds));
+		memset(_mtuProbeTries,0,sizeof(_mtuProbeTries));
+	}
 
 	/**
 	 * Called when a packet is received from this remote path, regardless of content
@@ -216,6 +233,138 @@ public:
 	 */
 	inline void sent(const int64_t t) { _lastOut = t; }
 
+	/**
+	 * Start a new MTU probe on this path, discarding the results of the last one
+	 *
+	 * @param now Current time
+	 */
+	inline void mtuProbeStart(const int64_t now)
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		memset(_mtuProbeIds,0,sizeof(_mtuProbeIds));
+		memset(_mtuProbeTries,0,sizeof(_mtuProbeTries));
+		_mtuProbesAnswered = 0;
+		_mtuProbeSending = true;
+		_mtuProbeCancelled = false;
+		_lastMtuProbe = now;
+		_lastMtuProbeSent = now;
+	}
+
+	/**
+	 * Get the next MTU probe size to send
+	 *
+	 * Each size is sent once in order, then sizes that have not been answered
+	 * are sent again until each has been tried ZT_PATH_MTU_PROBE_TRIES times.
+	 *
+	 * @return Index in ZT_PATH_MTU_PROBE_SIZES or -1 if no more probes are to be sent
+	 */
+	inline int mtuProbeNext()
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		int next = -1;
+		if (_mtuProbeSending) {
+			for(unsigned int k=0;k<ZT_PATH_MTU_PROBE_COUNT;++k) {
+				if (((_mtuProbesAnswered & (1U << k)) == 0)&&(_mtuProbeTries[k] < ZT_PATH_MTU_PROBE_TRIES)&&((next < 0)||(_mtuProbeTries[k] < _mtuProbeTries[next])))
+					next = (int)k;
+			}
+			_mtuProbeSending = (next >= 0);
+		}
+		return next;
+	}
+
+	/**
+	 * Stop sending MTU probes on this path, e.g. because it is no longer alive
+	 *
+	 * Probe sizes answered so far are still reported, but the path is not
+	 * flagged as a black hole.
+	 */
+	inline void mtuProbeCancel()
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		if (_mtuProbeSending) {
+			_mtuProbeSending = false;
+			_mtuProbeCancelled = true;
+		}
+	}
+
+	/**
+	 * Record that an MTU probe packet was sent
+	 *
+	 * @param k Index in ZT_PATH_MTU_PROBE_SIZES
+	 * @param packetId Packet ID of ECHO
+	 * @param now Current time
+	 */
+	inline void mtuProbeSent(const unsigned int k,const uint64_t packetId,const int64_t now)
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		if ((k < ZT_PATH_MTU_PROBE_COUNT)&&(_mtuProbeTries[k] < ZT_PATH_MTU_PROBE_TRIES))
+			_mtuProbeIds[k][_mtuProbeTries[k]++] = packetId;
+		_lastMtuProbeSent = now;
+	}
+
+	/**
+	 * Record a reply to an ECHO that may have been an MTU probe
+	 *
+	 * @param inRePacketId Packet ID of the ECHO being answered
+	 */
+	inline void mtuProbeAnswered(const uint64_t inRePacketId)
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		for(unsigned int k=0;k<ZT_PATH_MTU_PROBE_COUNT;++k) {
+			for(unsigned int t=0;t<_mtuProbeTries[k];++t) {
+				if (_mtuProbeIds[k][t] == inRePacketId)
+					_mtuProbesAnswered |= (1U << k);
+			}
+		}
+	}
+
+	/**
+	 * @return Largest answered probe size in the last MTU probe, or 0 if none
+	 */
+	inline unsigned int mtuProbeMaxSize() const
+	{
+		static const unsigned int sizes[ZT_PATH_MTU_PROBE_COUNT] = ZT_PATH_MTU_PROBE_SIZES;
+		Mutex::Lock _l(_mtuProbe_m);
+		for(int i=ZT_PATH_MTU_PROBE_COUNT-1;i>=0;--i) {
+			if ((_mtuProbesAnswered & (1U << (unsigned int)i)) != 0)
+				return sizes[i];
+		}
+		return 0;
+	}
+
+	/**
+	 * @param now Current time
+	 * @return True once all probes have been sent and the last has had time to be answered
+	 */
+	inline bool mtuProbeDone(const int64_t now) const
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		return _mtuProbeDone(now);
+	}
+
+	/**
+	 * A path looks like a black hole if small probes were answered but every try at the largest was lost
+	 *
+	 * @param now Current time
+	 * @return True if the last finished MTU probe lost all tries at sizes above the largest answered size
+	 */
+	inline bool mtuProbeBlackhole(const int64_t now) const
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		if ((!_mtuProbesAnswered)||(_mtuProbeCancelled)||(!_mtuProbeDone(now)))
+			return false;
+		return ((_mtuProbesAnswered & (1U << (ZT_PATH_MTU_PROBE_COUNT - 1))) == 0);
+	}
+
+	/**
+	 * @return Time of last MTU probe on this path or 0 if never probed
+	 */
+	inline int64_t lastMtuProbe() const
+	{
+		Mutex::Lock _l(_mtuProbe_m);
+		return _lastMtuProbe;
+	}
+
 	/**
 	 * Update path latency with a new measurement
 	 *
@@ -337,7 +486,7 @@ public:
 	/**
 	 * @return True if this path needs a heartbeat
 	 */
-	inline bool needsHeartbeat(const int64_t now) const { return ((now - _lastOut) >= ZT_PATH_HEARTBEAT_PERIOD); }
+	inline bool needsHeartbeat(const int64_t now,const int64_t period = ZT_PATH_HEARTBEAT_PERIOD) const { return ((now - _lastOut) >= period); }
 
 	/**
 	 * @return True if this path needs a heartbeat in accordance to the user-specified path monitor frequency
@@ -709,6 +858,18 @@ private:
 	 */
 	int _packetsIn;
 	int _packetsOut;
+
+	inline bool _mtuProbeDone(const int64_t now) const { return ((_lastMtuProbe > 0)&&(!_mtuProbeSending)&&((now - _lastMtuProbeSent) >= ZT_PATH_MTU_PROBE_TIMEOUT)); }
+
+	// Packet IDs and tries per size, and answers (bit mask in order of ZT_PATH_MTU_PROBE_SIZES) of the last MTU probe
+	uint64_t _mtuProbeIds[ZT_PATH_MTU_PROBE_COUNT][ZT_PATH_MTU_PROBE_TRIES];
+	unsigned int _mtuProbeTries[ZT_PATH_MTU_PROBE_COUNT];
+	int64_t _lastMtuProbe;
+	int64_t _lastMtuProbeSent;
+	unsigned int _mtuProbesAnswered;
+	bool _mtuProbeSending;
+	bool _mtuProbeCancelled;
+	Mutex _mtuProbe_m;
 };
 
 } // namespace ZeroTier
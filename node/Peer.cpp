@@ -37,15 +37,21 @@ Peer::Peer(const RuntimeEnvironment *renv,const Identity &myIdentity,const Ident
 	_lastEchoRequestReceived(0),
 	_lastCredentialRequestSent(0),
 	_lastWhoisRequestReceived(0),
+	_lastRelayNudgeSent(0),
 	_lastCredentialsReceived(0),
 	_lastTrustEstablishedPacketReceived(0),
 	_lastSentFullHello(0),
 	_lastEchoCheck(0),
+	_lastMtuProbeSent(0),
+	_mtuProbePath(0),
 	_freeRandomByte((unsigned char)((uintptr_t)this >> 4) ^ ++s_freeRandomByteCounter),
 	_vProto(0),
 	_vMajor(0),
 	_vMinor(0),
 	_vRevision(0),
+	_lastBestPathLocalSocket(-1),
+	_preferredFamily(0),
+	_noRelay(false),
 	_id(peerIdentity),
 	_directPathPushCutoffCount(0),
 	_credentialsCutoffCount(0),
@@ -176,11 +182,11 @@ void Peer::received(
 	}
 
 	// If we have a trust relationship periodically push a message enumerating
-	// all known external addresses for ourselves. If we already have a path this
-	// is done less frequently.
+	// all known external addresses for ourselves. If we already have a path or
+	// are in low-power mode this is done less frequently.
 	if (this->trustEstablished(now)) {
 		const int64_t sinceLastPush = now - _lastDirectPathPushSent;
-		if (sinceLastPush >= ((hops == 0) ? ZT_DIRECT_PATH_PUSH_INTERVAL_HAVEPATH : ZT_DIRECT_PATH_PUSH_INTERVAL)) {
+		if (sinceLastPush >= (((hops == 0)||(RR->node->lowPower())) ? ZT_DIRECT_PATH_PUSH_INTERVAL_HAVEPATH : ZT_DIRECT_PATH_PUSH_INTERVAL)) {
 			_lastDirectPathPushSent = now;
 			std::vector<InetAddress> pathsToPush(RR->node->directPaths());
 			if (!pathsToPush.empty()) {
@@ -228,25 +234,12 @@ void Peer::received(
 SharedPtr<Path> Peer::getAppropriatePath(int64_t now, bool includeExpired, int32_t flowId)
 {
 	if (!_bondToPeer) {
+		const int family = _preferredFamily.load();
 		Mutex::Lock _l(_paths_m);
-		unsigned int bestPath = ZT_MAX_PEER_NETWORK_PATHS;
-		/**
-		 * Send traffic across the highest quality path only. This algorithm will still
-		 * use the old path quality metric from protocol version 9.
-		 */
-		long bestPathQuality = 2147483647;
-		for(unsigned int i=0;i<ZT_MAX_PEER_NETWORK_PATHS;++i) {
-			if (_paths[i].p) {
-				if ((includeExpired)||((now - _paths[i].lr) < ZT_PEER_PATH_EXPIRATION)) {
-					const long q = _paths[i].p->quality(now) / _paths[i].priority;
-					if (q <= bestPathQuality) {
-						bestPathQuality = q;
-						bestPath = i;
-					}
-				}
-			} else break;
-		}
+		const unsigned int bestPath = _bestPathIndex(now,includeExpired,family);
 		if (bestPath != ZT_MAX_PEER_NETWORK_PATHS) {
+			_lastBestPathAddress = _paths[bestPath].p->address();
+			_lastBestPathLocalSocket = _paths[bestPath].p->localSocket();
 			return _paths[bestPath].p;
 		}
 		return SharedPtr<Path>();
@@ -254,6 +247,50 @@ SharedPtr<Path> Peer::getAppropriatePath(int64_t now, bool includeExpired, int32
 	return _bondToPeer->getAppropriatePath(now, flowId);
 }
 
+SharedPtr<Path> Peer::getBestPath(int64_t now, bool includeExpired) const
+{
+	if (!_bondToPeer) {
+		const int family = _preferredFamily.load();
+		Mutex::Lock _l(_paths_m);
+		const unsigned int bestPath = _bestPathIndex(now,includeExpired,family);
+		if (bestPath != ZT_MAX_PEER_NETWORK_PATHS)
+			return _paths[bestPath].p;
+		return SharedPtr<Path>();
+	}
+	return _bondToPeer->getAppropriatePath(now, -1);
+}
+
+unsigned int Peer::_bestPathIndex(int64_t now, bool includeExpired, int family) const
+{
+	unsigned int bestPath = ZT_MAX_PEER_NETWORK_PATHS;
+	unsigned int bestFamilyPath = ZT_MAX_PEER_NETWORK_PATHS;
+	/**
+	 * Send traffic across the highest quality path only. This algorithm will still
+	 * use the old path quality metric from protocol version 9.
+	 */
+	long bestPathQuality = 2147483647;
+	long bestFamilyPathQuality = 2147483647;
+	for(unsigned int i=0;i<ZT_MAX_PEER_NETWORK_PATHS;++i) {
+		if (_paths[i].p) {
+			if ((includeExpired)||((now - _paths[i].lr) < ZT_PEER_PATH_EXPIRATION)) {
+				long q = _paths[i].p->quality(now) / _paths[i].priority;
+				// Compare by address and socket, not pointer, since a freed Path's memory may be reused
+				if ((_paths[i].p->localSocket() == _lastBestPathLocalSocket)&&(_paths[i].p->address() == _lastBestPathAddress))
+					q -= q / ZT_PEER_PATH_SELECTION_HYSTERESIS; // don't flap between similar paths (e.g. v4 and v6)
+				if (q <= bestPathQuality) {
+					bestPathQuality = q;
+					bestPath = i;
+				}
+				if ((_paths[i].p->address().ss_family == family)&&(q <= bestFamilyPathQuality)) {
+					bestFamilyPathQuality = q;
+					bestFamilyPath = i;
+				}
+			}
+		} else break;
+	}
+	return (bestFamilyPath != ZT_MAX_PEER_NETWORK_PATHS) ? bestFamilyPath : bestPath;
+}
+
 void Peer::introduce(void *const tPtr,const int64_t now,const SharedPtr<Peer> &other) const
 {
 	unsigned int myBestV4ByScope[ZT_INETADDRESS_MAX_SCOPE+1];
@@ -430,6 +467,63 @@ void Peer::attemptToContactAt(void *tPtr,const int64_t localSocket,const InetAdd
 	}
 }
 
+unsigned int Peer::probePathMtu(void *tPtr,int64_t now)
+{
+	// ECHO isn't understood by very old peers (see attemptToContactAt())
+	if ( (_vProto < 5) || ((_vMajor == 1)&&(_vMinor == 1)&&(_vRevision == 0)) )
+		return 0;
+
+	unsigned int probed = 0;
+	{
+		Mutex::Lock _l(_paths_m);
+		for(unsigned int i=0;i<ZT_MAX_PEER_NETWORK_PATHS;++i) {
+			if (!_paths[i].p)
+				break;
+			if (!_paths[i].p->alive(now))
+				continue;
+			_paths[i].p->mtuProbeStart(now);
+			++probed;
+		}
+		_lastMtuProbeSent = 0;
+		_mtuProbePath = 0;
+	}
+	if (probed)
+		sendNextPathMtuProbe(tPtr,now);
+	return probed;
+}
+
+bool Peer::sendNextPathMtuProbe(void *tPtr,int64_t now)
+{
+	static const unsigned int sizes[ZT_PATH_MTU_PROBE_COUNT] = ZT_PATH_MTU_PROBE_SIZES;
+
+	Mutex::Lock _l(_paths_m);
+	if ((now - _lastMtuProbeSent) < ZT_PATH_MTU_PROBE_INTERVAL)
+		return true;
+	for(unsigned int n=0;n<ZT_MAX_PEER_NETWORK_PATHS;++n) {
+		const unsigned int i = (_mtuProbePath + n) % ZT_MAX_PEER_NETWORK_PATHS;
+		if (!_paths[i].p)
+			continue;
+		if (!_paths[i].p->alive(now)) {
+			_paths[i].p->mtuProbeCancel();
+			continue;
+		}
+		const int k = _paths[i].p->mtuProbeNext();
+		if (k < 0)
+			continue;
+		Packet outp(_id.address(),RR->identity.address(),Packet::VERB_ECHO);
+		if (sizes[k] > outp.size())
+			outp.append((unsigned char)0,sizes[k] - outp.size());
+		outp.armor(_key,true,aesKeysIfSupported());
+		RR->node->expectReplyTo(outp.packetId());
+		RR->node->putPacket(tPtr,_paths[i].p->localSocket(),_paths[i].p->address(),outp.data(),outp.size());
+		_paths[i].p->mtuProbeSent((unsigned int)k,outp.packetId(),now);
+		_lastMtuProbeSent = now;
+		_mtuProbePath = i + 1;
+		return true;
+	}
+	return false;
+}
+
 void Peer::tryMemorizedPath(void *tPtr,int64_t now)
 {
 	if ((now - _lastTriedMemorizedPath) >= ZT_TRY_MEMORIZED_PATH_INTERVAL) {
@@ -491,6 +585,8 @@ unsigned int Peer::doPingAndKeepalive(void *tPtr,int64_t now)
 
 	const bool sendFullHello = ((now - _lastSentFullHello) >= ZT_PEER_PING_PERIOD);
 	_lastSentFullHello = now;
+	const unsigned int keepalive = RR->node->peerKeepalive(_id.address());
+	const int64_t heartbeatPeriod = (keepalive) ? (int64_t)keepalive : (int64_t)ZT_PATH_HEARTBEAT_PERIOD;
 
 	// Right now we only keep pinging links that have the maximum priority. The
 	// priority is used to track cluster redirections, meaning that when a cluster
@@ -508,7 +604,7 @@ unsigned int Peer::doPingAndKeepalive(void *tPtr,int64_t now)
 		if (_paths[i].p) {
 			// Clean expired and reduced priority paths
 			if ( ((now - _paths[i].lr) < ZT_PEER_PATH_EXPIRATION) && (_paths[i].priority == maxPriority) ) {
-				if ((sendFullHello)||(_paths[i].p->needsHeartbeat(now))
+				if ((sendFullHello)||(_paths[i].p->needsHeartbeat(now,heartbeatPeriod))
 					|| (_canUseMultipath && _paths[i].p->needsGratuitousHeartbeat(now))) {
 					attemptToContactAt(tPtr,_paths[i].p->localSocket(),_paths[i].p->address(),now,sendFullHello);
 					_paths[i].p->sent(now);
@@ -586,6 +682,14 @@ void Peer::resetWithinScope(void *tPtr,InetAddress::IpScope scope,int inetAddres
 	}
 }
 
+void Peer::clearPaths()
+{
+	Mutex::Lock _l(_paths_m);
+	for(unsigned int i=0;i<ZT_MAX_PEER_NETWORK_PATHS;++i)
+		_paths[i] = _PeerPath();
+	_lastBestPathAddress = InetAddress();
+}
+
 void Peer::recordOutgoingPacket(const SharedPtr<Path> &path, const uint64_t packetId,
 	uint16_t payloadLength, const Packet::Verb verb, const int32_t flowId, int64_t now)
 {
@@ -597,6 +701,7 @@ void Peer::recordOutgoingPacket(const SharedPtr<Path> &path, const uint64_t pack
 
 void Peer::recordIncomingInvalidPacket(const SharedPtr<Path>& path)
 {
+	++_macFailures;
 	if (!_shouldCollectPathStatistics || !_bondToPeer) {
 		return;
 	}
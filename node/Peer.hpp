@@ -15,6 +15,7 @@
 #define ZT_PEER_HPP
 
 #include <vector>
+#include <atomic>
 
 #include "../include/ZeroTierOne.h"
 
@@ -179,12 +180,24 @@ public:
 	/**
 	 * Get the most appropriate direct path based on current multipath and QoS configuration
 	 *
+	 * This is for sending: the path returned becomes the one favored by path
+	 * selection hysteresis. Use getBestPath() to only look.
+	 *
 	 * @param now Current time
 	 * @param includeExpired If true, include even expired paths
 	 * @return Best current path or NULL if none
 	 */
 	SharedPtr<Path> getAppropriatePath(int64_t now, bool includeExpired, int32_t flowId = -1);
 
+	/**
+	 * Get the path getAppropriatePath() would pick, without changing path selection state
+	 *
+	 * @param now Current time
+	 * @param includeExpired If true, include even expired paths
+	 * @return Best current path or NULL if none
+	 */
+	SharedPtr<Path> getBestPath(int64_t now, bool includeExpired) const;
+
 	/**
 	 * Send VERB_RENDEZVOUS to this and another peer via the best common IP scope and path
 	 */
@@ -215,6 +228,30 @@ public:
 	 */
 	void attemptToContactAt(void *tPtr,const int64_t localSocket,const InetAddress &atAddress,int64_t now,bool sendFullHello);
 
+	/**
+	 * Start an MTU probe on every live direct path and send its first packet
+	 *
+	 * Probes are padded ECHO packets of each ZT_PATH_MTU_PROBE_SIZES size,
+	 * sent as single UDP packets without ZeroTier fragmentation. The rest are
+	 * sent by sendNextPathMtuProbe(). Results are read back from each Path.
+	 *
+	 * @param tPtr Thread pointer to be handed through to any callbacks called as a result of this call
+	 * @param now Current time
+	 * @return Number of paths probed
+	 */
+	unsigned int probePathMtu(void *tPtr,int64_t now);
+
+	/**
+	 * Send the next MTU probe packet if one is due
+	 *
+	 * One packet is sent per ZT_PATH_MTU_PROBE_INTERVAL, taking paths in turn.
+	 *
+	 * @param tPtr Thread pointer to be handed through to any callbacks called as a result of this call
+	 * @param now Current time
+	 * @return False once no path has probes left to send
+	 */
+	bool sendNextPathMtuProbe(void *tPtr,int64_t now);
+
 	/**
 	 * Try a memorized or statically defined path if any are known
 	 *
@@ -269,6 +306,14 @@ public:
 	 */
 	void resetWithinScope(void *tPtr,InetAddress::IpScope scope,int inetAddressFamily,int64_t now);
 
+	/**
+	 * Drop all physical paths to this peer
+	 *
+	 * Traffic is relayed until a path is learned again through the normal
+	 * means (HELLO, PUSH_DIRECT_PATHS, etc.).
+	 */
+	void clearPaths();
+
 	/**
 	 * @param now Current time
 	 * @return All known paths to this peer
@@ -289,6 +334,11 @@ public:
 	 */
 	inline int64_t lastReceive() const { return _lastReceive; }
 
+	/**
+	 * @return Number of packets claiming to be from this peer that failed MAC check
+	 */
+	inline unsigned int macFailures() const { return (unsigned int)_macFailures.load(); }
+
 	/**
 	 * @return True if we've heard from this peer in less than ZT_PEER_ACTIVITY_TIMEOUT
 	 */
@@ -307,7 +357,7 @@ public:
 		if (_canUseMultipath) {
 			return (int)_lastComputedAggregateMeanLatency;
 		} else {
-			SharedPtr<Path> bp(getAppropriatePath(now,false));
+			SharedPtr<Path> bp(getBestPath(now,false));
 			if (bp)
 				return bp->latency();
 			return 0xffff;
@@ -417,6 +467,35 @@ public:
 		return false;
 	}
 
+	/**
+	 * @return True if network traffic with this peer must not be relayed
+	 */
+	inline bool noRelay() const { return _noRelay.load(); }
+
+	/**
+	 * Set whether relaying is forbidden, called by Node::applyPeerSettings()
+	 */
+	inline void setNoRelay(const bool noRelay) { _noRelay.store(noRelay); }
+
+	/**
+	 * Set the address family to prefer for direct paths, called by Node::applyPeerSettings()
+	 *
+	 * @param family AF_INET, AF_INET6, or 0 for no preference
+	 */
+	inline void setPreferredFamily(const int family) { _preferredFamily.store(family); }
+
+	/**
+	 * Rate limit gate for relayed NOPs sent in place of held frames when relaying is forbidden
+	 */
+	inline bool rateGateRelayNudge(const int64_t now)
+	{
+		if ((now - _lastRelayNudgeSent) >= ZT_PEER_GENERAL_RATE_LIMIT) {
+			_lastRelayNudgeSent = now;
+			return true;
+		}
+		return false;
+	}
+
 	/**
 	 * Rate limit gate for inbound ECHO requests. This rate limiter works
 	 * by draining a certain number of requests per unit time. Each peer may
@@ -548,6 +627,10 @@ private:
 		long priority; // >= 1, higher is better
 	};
 
+	// Index of the best non-bonded path or ZT_MAX_PEER_NETWORK_PATHS if none; _paths_m must be locked
+	// Paths of the given address family (AF_INET or AF_INET6) win if there are any, 0 means no preference
+	unsigned int _bestPathIndex(int64_t now, bool includeExpired, int family) const;
+
 	uint8_t _key[ZT_SYMMETRIC_KEY_SIZE];
 	AES _aesKeys[2];
 
@@ -561,10 +644,13 @@ private:
 	int64_t _lastEchoRequestReceived;
 	int64_t _lastCredentialRequestSent;
 	int64_t _lastWhoisRequestReceived;
+	int64_t _lastRelayNudgeSent;
 	int64_t _lastCredentialsReceived;
 	int64_t _lastTrustEstablishedPacketReceived;
 	int64_t _lastSentFullHello;
 	int64_t _lastEchoCheck;
+	int64_t _lastMtuProbeSent;
+	unsigned int _mtuProbePath;
 
 	unsigned char _freeRandomByte;
 
@@ -574,6 +660,10 @@ private:
 	uint16_t _vRevision;
 
 	_PeerPath _paths[ZT_MAX_PEER_NETWORK_PATHS];
+	InetAddress _lastBestPathAddress; // last path returned by getAppropriatePath(), for hysteresis
+	int64_t _lastBestPathLocalSocket;
+	std::atomic<int> _preferredFamily; // Node::peerPreferredFamily(), kept current by Node so packet paths don't lock
+	std::atomic<bool> _noRelay; // Node::peerNoRelay(), kept current by Node so packet paths don't lock
 	Mutex _paths_m;
 
 	Identity _id;
@@ -582,6 +672,8 @@ private:
 	unsigned int _credentialsCutoffCount;
 	unsigned int _echoRequestCutoffCount;
 
+	AtomicCounter _macFailures;
+
 	AtomicCounter __refCount;
 
 	bool _remotePeerMultipathEnabled;
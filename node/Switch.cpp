@@ -38,10 +38,40 @@ Switch::Switch(const RuntimeEnvironment *renv) :
 	RR(renv),
 	_lastBeaconResponse(0),
 	_lastCheckedQueues(0),
+	_rxQueue(new RXQueueEntry[ZT_RX_QUEUE_SIZE]),
+	_rxQueueSize(ZT_RX_QUEUE_SIZE),
+	_txQueueSize(ZT_TX_QUEUE_SIZE),
+	_aqmMaxEnqueuedPackets(ZT_AQM_MAX_ENQUEUED_PACKETS),
 	_lastUniteAttempt(8) // only really used on root servers and upstreams, and it'll grow there just fine
 {
 }
 
+Switch::~Switch()
+{
+	delete [] _rxQueue;
+}
+
+bool Switch::setRxQueueSize(unsigned int n)
+{
+	if (n == _rxQueueSize)
+		return true;
+	if (_rxQueuePtr.load() != 0)
+		return false;
+	RXQueueEntry *const old = _rxQueue;
+	_rxQueue = new RXQueueEntry[n];
+	_rxQueueSize = n;
+	delete [] old;
+	return true;
+}
+
+void Switch::setTxQueueSize(unsigned int n)
+{
+	Mutex::Lock _l(_txQueue_m);
+	_txQueueSize = n;
+	while (_txQueue.size() > n)
+		_txQueue.pop_front();
+}
+
 // Returns true if packet appears valid; pos and proto will be set
 static bool _ipv6GetPayload(const uint8_t *frameData,unsigned int frameLen,unsigned int &pos,unsigned int &proto)
 {
@@ -285,6 +315,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 	if ((fromBridged = (from != network->mac()))) {
 		if (!network->config().permitsBridging(RR->identity.address())) {
 			RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"not a bridge");
+			network->countDroppedFrame(false);
 			return;
 		}
 	}
@@ -377,6 +408,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 			} else if (!network->config().enableBroadcast()) {
 				// Don't transmit broadcasts if this network doesn't want them
 				RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"broadcast disabled");
+				network->countDroppedFrame(false);
 				return;
 			}
 		} else if ((etherType == ZT_ETHERTYPE_IPV6)&&(len >= (40 + 8 + 16))) {
@@ -465,6 +497,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 		// Check this after NDP emulation, since that has to be allowed in exactly this case
 		if (network->config().multicastLimit == 0) {
 			RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"multicast disabled");
+			network->countDroppedFrame(false);
 			return;
 		}
 
@@ -478,6 +511,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 		// First pass sets noTee to false, but noTee is set to true in OutboundMulticast to prevent duplicates.
 		if (!network->filterOutgoingPacket(tPtr,false,RR->identity.address(),Address(),from,to,(const uint8_t *)data,len,etherType,vlanId,qosBucket)) {
 			RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"filter blocked");
+			network->countDroppedFrame(false);
 			return;
 		}
 
@@ -493,6 +527,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 			len);
 	} else if (to == network->mac()) {
 		// Destination is this node, so just reinject it
+		network->countFrame(true,len,false,etherType);
 		RR->node->putFrame(tPtr,network->id(),network->userPtr(),from,to,etherType,vlanId,data,len);
 	} else if (to[0] == MAC::firstOctetForNetwork(network->id())) {
 		// Destination is another ZeroTier peer on the same network
@@ -502,6 +537,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 
 		if (!network->filterOutgoingPacket(tPtr,false,RR->identity.address(),toZT,from,to,(const uint8_t *)data,len,etherType,vlanId,qosBucket)) {
 			RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"filter blocked");
+			network->countDroppedFrame(false);
 			return;
 		}
 
@@ -537,6 +573,7 @@ void Switch::onLocalEthernet(void *tPtr,const SharedPtr<Network> &network,const
 		// and design as for multicast.
 		if (!network->filterOutgoingPacket(tPtr,false,RR->identity.address(),Address(),from,to,(const uint8_t *)data,len,etherType,vlanId,qosBucket)) {
 			RR->t->outgoingNetworkFrameDropped(tPtr,network,from,to,etherType,vlanId,len,"filter blocked");
+			network->countDroppedFrame(false);
 			return;
 		}
 
@@ -653,7 +690,7 @@ void Switch::aqm_enqueue(void *tPtr, const SharedPtr<Network> &network, Packet &
 
 	// Drop a packet if necessary
 	ManagedQueue *selectedQueueToDropFrom = nullptr;
-	if (nqcb->_currEnqueuedPackets > ZT_AQM_MAX_ENQUEUED_PACKETS)
+	if (nqcb->_currEnqueuedPackets > (int)_aqmMaxEnqueuedPackets)
 	{
 		// DEBUG_INFO("too many enqueued packets (%d), finding packet to drop", nqcb->_currEnqueuedPackets);
 		int maxQueueLength = 0;
@@ -854,7 +891,7 @@ void Switch::send(void *tPtr,Packet &packet,bool encrypt,int32_t flowId)
 	if (!_trySend(tPtr,packet,encrypt,flowId)) {
 		{
 			Mutex::Lock _l(_txQueue_m);
-			if (_txQueue.size() >= ZT_TX_QUEUE_SIZE) {
+			if (_txQueue.size() >= _txQueueSize) {
 				_txQueue.pop_front();
 			}
 			_txQueue.push_back(TXQueueEntry(dest,RR->node->now(),packet,encrypt,flowId));
@@ -894,7 +931,7 @@ void Switch::doAnythingWaitingForPeer(void *tPtr,const SharedPtr<Peer> &peer)
 	}
 
 	const int64_t now = RR->node->now();
-	for(unsigned int ptr=0;ptr<ZT_RX_QUEUE_SIZE;++ptr) {
+	for(unsigned int ptr=0;ptr<_rxQueueSize;++ptr) {
 		RXQueueEntry *const rq = &(_rxQueue[ptr]);
 		Mutex::Lock rql(rq->lock);
 		if ((rq->timestamp)&&(rq->complete)) {
@@ -945,7 +982,7 @@ unsigned long Switch::doTimerTasks(void *tPtr,int64_t now)
 	for(std::vector<Address>::const_iterator i(needWhois.begin());i!=needWhois.end();++i)
 		requestWhois(tPtr,now,*i);
 
-	for(unsigned int ptr=0;ptr<ZT_RX_QUEUE_SIZE;++ptr) {
+	for(unsigned int ptr=0;ptr<_rxQueueSize;++ptr) {
 		RXQueueEntry *const rq = &(_rxQueue[ptr]);
 		Mutex::Lock rql(rq->lock);
 		if ((rq->timestamp)&&(rq->complete)) {
@@ -1021,6 +1058,17 @@ bool Switch::_trySend(void *tPtr,Packet &packet,bool encrypt,int32_t flowId)
 			if (!viaPath) {
 				peer->tryMemorizedPath(tPtr,now); // periodically attempt memorized or statically defined paths, if any are known
 				const SharedPtr<Peer> relay(RR->topology->getUpstreamPeer());
+				if ((peer->noRelay())&&((packet.verb() == Packet::VERB_FRAME)||(packet.verb() == Packet::VERB_EXT_FRAME)||(packet.verb() == Packet::VERB_MULTICAST_FRAME))) {
+					// Hold frames until a direct path exists, but relay a NOP so upstreams introduce us
+					if ((relay)&&(peer->rateGateRelayNudge(now))) {
+						const SharedPtr<Path> relayPath(relay->getAppropriatePath(now,false,flowId));
+						if (relayPath) {
+							Packet nop(destination,RR->identity.address(),Packet::VERB_NOP);
+							_sendViaSpecificPath(tPtr,peer,relayPath,now,nop,true,flowId);
+						}
+					}
+					return false;
+				}
 				if ( (!relay) || (!(viaPath = relay->getAppropriatePath(now,false,flowId))) ) {
 					if (!(viaPath = peer->getAppropriatePath(now,true,flowId)))
 						return false;
@@ -68,6 +68,7 @@ class Switch
 
 public:
 	Switch(const RuntimeEnvironment *renv);
+	~Switch();
 
 	/**
 	 * Called when a packet is received from the real network
@@ -134,6 +135,40 @@ public:
 	 */
 	void aqm_dequeue(void *tPtr);
 
+	/**
+	 * Set the maximum number of packets queued by AQM for each network
+	 *
+	 * @param n Maximum (caller must check it against ZT_AQM_MAX_ENQUEUED_PACKETS_MIN/MAX)
+	 */
+	inline void setAqmMaxEnqueuedPackets(unsigned int n)
+	{
+		Mutex::Lock _l(_aqm_m);
+		_aqmMaxEnqueuedPackets = n;
+	}
+
+	/**
+	 * Resize the RX queue
+	 *
+	 * Entries are used from packet processing threads without a lock, so this
+	 * only works before the first packet has been received.
+	 *
+	 * @param n New size (caller must check it against ZT_QUEUE_SIZE_MIN/MAX)
+	 * @return True if resized (or already this size), false if packets have been received
+	 */
+	bool setRxQueueSize(unsigned int n);
+
+	/**
+	 * Set the size of the TX queue, dropping the oldest entries if it is now too long
+	 *
+	 * @param n New size (caller must check it against ZT_QUEUE_SIZE_MIN/MAX)
+	 */
+	void setTxQueueSize(unsigned int n);
+
+	/**
+	 * @return Current TX queue size limit, also used for each multicast group's send queue
+	 */
+	inline unsigned int txQueueSize() const { return _txQueueSize; }
+
 	/**
 	 * Calls the dequeue mechanism and adjust queue state variables
 	 *
@@ -231,26 +266,27 @@ private:
 		volatile int32_t flowId;
 		Mutex lock;
 	};
-	RXQueueEntry _rxQueue[ZT_RX_QUEUE_SIZE];
+	RXQueueEntry *_rxQueue;
+	unsigned int _rxQueueSize; // only changed before the first packet, see setRxQueueSize()
 	AtomicCounter _rxQueuePtr;
 
 	// Returns matching or next available RX queue entry
 	inline RXQueueEntry *_findRXQueueEntry(uint64_t packetId)
 	{
 		const unsigned int current = static_cast<unsigned int>(_rxQueuePtr.load());
-		for(unsigned int k=1;k<=ZT_RX_QUEUE_SIZE;++k) {
-			RXQueueEntry *rq = &(_rxQueue[(current - k) % ZT_RX_QUEUE_SIZE]);
+		for(unsigned int k=1;k<=_rxQueueSize;++k) {
+			RXQueueEntry *rq = &(_rxQueue[(current - k) % _rxQueueSize]);
 			if ((rq->packetId == packetId)&&(rq->timestamp))
 				return rq;
 		}
 		++_rxQueuePtr;
-		return &(_rxQueue[static_cast<unsigned int>(current) % ZT_RX_QUEUE_SIZE]);
+		return &(_rxQueue[static_cast<unsigned int>(current) % _rxQueueSize]);
 	}
 
 	// Returns current entry in rx queue ring buffer and increments ring pointer
 	inline RXQueueEntry *_nextRXQueueEntry()
 	{
-		return &(_rxQueue[static_cast<unsigned int>((++_rxQueuePtr) - 1) % ZT_RX_QUEUE_SIZE]);
+		return &(_rxQueue[static_cast<unsigned int>((++_rxQueuePtr) - 1) % _rxQueueSize]);
 	}
 
 	// ZeroTier-layer TX queue entry
@@ -272,7 +308,9 @@ private:
 	};
 	std::list< TXQueueEntry > _txQueue;
 	Mutex _txQueue_m;
+	volatile unsigned int _txQueueSize; // written with _txQueue_m locked
 	Mutex _aqm_m;
+	unsigned int _aqmMaxEnqueuedPackets; // guarded by _aqm_m
 
 	// Tracks sending of VERB_RENDEZVOUS to relaying peers
 	struct _LastUniteKey
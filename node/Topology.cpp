@@ -28,6 +28,7 @@ static const unsigned char ZT_DEFAULT_WORLD[ZT_DEFAULT_WORLD_LENGTH] = {0x01,0x0
 Topology::Topology(const RuntimeEnvironment *renv,void *tPtr) :
 	RR(renv),
 	_numConfiguredPhysicalPaths(0),
+	_peerIdleTimeout(ZT_PEER_ACTIVITY_TIMEOUT),
 	_amUpstream(false)
 {
 	uint8_t tmp[ZT_WORLD_MAX_SERIALIZED_LENGTH];
@@ -65,8 +66,10 @@ SharedPtr<Peer> Topology::addPeer(void *tPtr,const SharedPtr<Peer> &peer)
 	{
 		Mutex::Lock _l(_peers_m);
 		SharedPtr<Peer> &hp = _peers[peer->address()];
-		if (!hp)
+		if (!hp) {
 			hp = peer;
+			RR->node->applyPeerSettings(*hp);
+		}
 		np = hp;
 	}
 	return np;
@@ -97,6 +100,8 @@ SharedPtr<Peer> Topology::getPeer(void *tPtr,const Address &zta)
 			ap = Peer::deserializeFromCache(RR->node->now(),tPtr,buf,RR);
 			if (!ap) {
 				_peers.erase(zta);
+			} else {
+				RR->node->applyPeerSettings(*ap);
 			}
 			return SharedPtr<Peer>();
 		}
@@ -328,6 +333,22 @@ void Topology::removeMoon(void *tPtr,const uint64_t id)
 	_memoizeUpstreams(tPtr);
 }
 
+bool Topology::forgetPeer(void *tPtr,const Address &zta)
+{
+	{
+		Mutex::Lock _l(_upstreams_m);
+		if (std::find(_upstreamAddresses.begin(),_upstreamAddresses.end(),zta) != _upstreamAddresses.end())
+			return false;
+	}
+	{
+		Mutex::Lock _l(_peers_m);
+		_peers.erase(zta);
+	}
+	uint64_t tmpid[2]; tmpid[0] = zta.toInt(); tmpid[1] = 0;
+	RR->node->stateObjectDelete(tPtr,ZT_STATE_OBJECT_PEER,tmpid);
+	return true;
+}
+
 void Topology::doPeriodicTasks(void *tPtr,int64_t now)
 {
 	{
@@ -337,7 +358,7 @@ void Topology::doPeriodicTasks(void *tPtr,int64_t now)
 		Address *a = (Address *)0;
 		SharedPtr<Peer> *p = (SharedPtr<Peer> *)0;
 		while (i.next(a,p)) {
-			if ( (!(*p)->isAlive(now)) && (std::find(_upstreamAddresses.begin(),_upstreamAddresses.end(),*a) == _upstreamAddresses.end()) ) {
+			if ( ((now - (*p)->lastReceive()) >= _peerIdleTimeout) && (std::find(_upstreamAddresses.begin(),_upstreamAddresses.end(),*a) == _upstreamAddresses.end()) ) {
 				_savePeer(tPtr,*p);
 				_peers.erase(*a);
 			}
@@ -371,6 +392,7 @@ void Topology::_memoizeUpstreams(void *tPtr)
 			SharedPtr<Peer> &hp = _peers[id.address()];
 			if (!hp) {
 				hp = new Peer(RR,RR->identity,id);
+				RR->node->applyPeerSettings(*hp);
 			}
 		}
 	}
@@ -382,8 +404,10 @@ void Topology::_memoizeUpstreams(void *tPtr)
 			} else if (std::find(_upstreamAddresses.begin(),_upstreamAddresses.end(),i->identity.address()) == _upstreamAddresses.end()) {
 				_upstreamAddresses.push_back(i->identity.address());
 				SharedPtr<Peer> &hp = _peers[i->identity.address()];
-				if (!hp)
+				if (!hp) {
 					hp = new Peer(RR,RR->identity,i->identity);
+					RR->node->applyPeerSettings(*hp);
+				}
 			}
 		}
 	}
@@ -269,6 +269,31 @@ public:
 	 */
 	void removeMoon(void *tPtr,const uint64_t id);
 
+	/**
+	 * Forget a peer and its paths, including its cached state
+	 *
+	 * Upstreams (roots and moons) can't be forgotten. Other peers will be
+	 * learned again normally if they are contacted.
+	 *
+	 * @param tPtr Thread pointer to be handed through to any callbacks called as a result of this call
+	 * @param zta ZeroTier address of peer
+	 * @return False if peer is an upstream and was not removed
+	 */
+	bool forgetPeer(void *tPtr,const Address &zta);
+
+	/**
+	 * Set how long a peer we have not heard from is kept in memory
+	 *
+	 * Upstreams are always kept.
+	 *
+	 * @param timeout Timeout in milliseconds
+	 */
+	inline void setPeerIdleTimeout(const int64_t timeout)
+	{
+		Mutex::Lock _l(_peers_m);
+		_peerIdleTimeout = timeout;
+	}
+
 	/**
 	 * Clean and flush database
 	 */
@@ -286,7 +311,7 @@ public:
 		Address *a = (Address *)0;
 		SharedPtr<Peer> *p = (SharedPtr<Peer> *)0;
 		while (i.next(a,p)) {
-			const SharedPtr<Path> pp((*p)->getAppropriatePath(now,false));
+			const SharedPtr<Path> pp((*p)->getBestPath(now,false));
 			if (pp)
 				++cnt;
 		}
@@ -438,6 +463,7 @@ private:
 	volatile unsigned int _numConfiguredPhysicalPaths;
 
 	Hashtable< Address,SharedPtr<Peer> > _peers;
+	int64_t _peerIdleTimeout; // guarded by _peers_m
 	Mutex _peers_m;
 
 	Hashtable< Path::HashKey,SharedPtr<Path> > _paths;
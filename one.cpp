@@ -87,6 +87,8 @@
 #include "node/BondController.hpp"
 
 #include "service/OneService.hpp"
+#include "service/ManagementAuth.hpp"
+#include "service/StateChecksum.hpp"
 
 #include "ext/json/json.hpp"
 
@@ -144,6 +146,9 @@ static void cliPrintHelp(const char *pn,FILE *out)
 	fprintf(out,"  listmoons               - List moons (federated root sets)" ZT_EOL_S);
 	fprintf(out,"  orbit <world ID> <seed> - Join a moon via any member root" ZT_EOL_S);
 	fprintf(out,"  deorbit <world ID>      - Leave a moon" ZT_EOL_S);
+	fprintf(out,"  mtuprobe <address>      - Probe MTU of direct paths to a peer" ZT_EOL_S);
+	fprintf(out,"  panic                   - Leave all networks and forget all peers" ZT_EOL_S);
+	fprintf(out,"  rearm <token>           - Rejoin networks left by panic" ZT_EOL_S);
 	fprintf(out,"  dump                    - Debug settings dump for support" ZT_EOL_S);
 	fprintf(out,ZT_EOL_S"Available settings:" ZT_EOL_S);
 	fprintf(out,"  Settings to use with [get/set] may include property names from " ZT_EOL_S);
@@ -164,6 +169,26 @@ static std::string cliFixJsonCRs(const std::string &s)
 	return r;
 }
 
+// Blank out credentials in local.conf settings before they go into a support dump
+static void cliRedactLocalConf(nlohmann::json &lc)
+{
+	// Only look up keys that are present so the redacted config is otherwise unchanged
+	if ((!lc.is_object())||(!lc.count("settings")))
+		return;
+	nlohmann::json &settings = lc["settings"];
+	if (!settings.is_object())
+		return;
+	nlohmann::json::iterator i(settings.find("redis"));
+	if ((i != settings.end())&&(i->is_object())&&(i->count("password")))
+		(*i)["password"] = "(redacted)";
+	i = settings.find("vault");
+	if ((i != settings.end())&&(i->is_object())&&(i->count("vaultToken")))
+		(*i)["vaultToken"] = "(redacted)";
+	i = settings.find("controllerDbPath");
+	if ((i != settings.end())&&(OSUtils::jsonString(*i,"").substr(0,9) == "postgres:"))
+		*i = "postgres:(redacted)";
+}
+
 #ifdef __WINDOWS__
 static int cli(int argc, _TCHAR* argv[])
 #else
@@ -905,7 +930,11 @@ static int cli(int argc,char **argv)
 				responseHeaders,
 				responseBody);
 			if (scode == 200) {
-				printf("200 orbit OK" ZT_EOL_S);
+				if (json) {
+					printf("%s",cliFixJsonCRs(responseBody).c_str());
+				} else {
+					printf("200 orbit OK" ZT_EOL_S);
+				}
 				return 0;
 			} else {
 				printf("%u %s %s" ZT_EOL_S,scode,command.c_str(),responseBody.c_str());
@@ -932,6 +961,124 @@ static int cli(int argc,char **argv)
 			printf("%u %s %s" ZT_EOL_S,scode,command.c_str(),responseBody.c_str());
 			return 1;
 		}
+	} else if (command == "mtuprobe") {
+		if (arg1.length() != 10) {
+			printf("mtuprobe requires a 10-digit peer address" ZT_EOL_S);
+			return 2;
+		}
+		requestHeaders["Content-Type"] = "application/json";
+		requestHeaders["Content-Length"] = "2";
+		unsigned int scode = Http::POST(
+			1024 * 1024 * 16,
+			60000,
+			(const struct sockaddr *)&addr,
+			(std::string("/peer/") + arg1 + "/mtuprobe").c_str(),
+			requestHeaders,
+			"{}",
+			2,
+			responseHeaders,
+			responseBody);
+		if (scode != 200) {
+			printf("%u %s unknown peer or no direct paths %s" ZT_EOL_S,scode,command.c_str(),responseBody.c_str());
+			return 1;
+		}
+
+		// Poll the peer's paths until every probe has finished, giving up after
+		// long enough for the most paths a peer can have to be probed
+		requestHeaders.erase("Content-Type");
+		requestHeaders.erase("Content-Length");
+		nlohmann::json j;
+		const int64_t giveUp = OSUtils::now() + ZT_PING_CHECK_INVERVAL + ZT_PATH_MTU_PROBE_TIMEOUT + ((int64_t)ZT_MAX_PEER_NETWORK_PATHS * ZT_PATH_MTU_PROBE_COUNT * ZT_PATH_MTU_PROBE_TRIES * ZT_PATH_MTU_PROBE_INTERVAL);
+		for(;;) {
+			Thread::sleep(1000);
+			responseBody.clear();
+			scode = Http::GET(1024 * 1024 * 16,60000,(const struct sockaddr *)&addr,(std::string("/peer/") + arg1).c_str(),requestHeaders,responseHeaders,responseBody);
+			try {
+				j = OSUtils::jsonParse(responseBody);
+			} catch ( ... ) {
+				printf("%u %s invalid JSON response" ZT_EOL_S,scode,command.c_str());
+				return 1;
+			}
+			if ((scode != 200)||(!j.is_object())) {
+				printf("%u %s %s" ZT_EOL_S,scode,command.c_str(),responseBody.c_str());
+				return 1;
+			}
+			bool done = true;
+			nlohmann::json &paths = j["paths"];
+			if (paths.is_array()) {
+				for(unsigned long k=0;k<paths.size();++k) {
+					if ((paths[k].find("mtuProbeDone") != paths[k].end())&&(!OSUtils::jsonBool(paths[k]["mtuProbeDone"],false)))
+						done = false;
+				}
+			}
+			if ((done)||(OSUtils::now() >= giveUp))
+				break;
+		}
+		if (json) {
+			printf("%s" ZT_EOL_S,OSUtils::jsonDump(j["paths"]).c_str());
+		} else {
+			printf("200 mtuprobe\n<path> <max probe size> <status>" ZT_EOL_S);
+			nlohmann::json &paths = j["paths"];
+			if (paths.is_array()) {
+				for(unsigned long k=0;k<paths.size();++k) {
+					nlohmann::json &p = paths[k];
+					if (p.find("mtuProbeMaxSize") == p.end())
+						continue;
+					const unsigned int maxSize = (unsigned int)OSUtils::jsonInt(p["mtuProbeMaxSize"],0);
+					const char *st = "OK";
+					if (!OSUtils::jsonBool(p["mtuProbeDone"],false))
+						st = "INCOMPLETE";
+					else if (!maxSize)
+						st = "NO_REPLY";
+					else if (OSUtils::jsonBool(p["mtuProbeBlackhole"],false))
+						st = "BLACKHOLE";
+					printf("%s %u %s" ZT_EOL_S,OSUtils::jsonString(p["address"],"-").c_str(),maxSize,st);
+				}
+			}
+		}
+		return 0;
+	} else if ((command == "panic")||(command == "rearm")) {
+		std::string jsons("{}");
+		if (command == "rearm") {
+			if (arg1.empty()) {
+				printf("rearm requires the token printed by panic" ZT_EOL_S);
+				return 2;
+			}
+			nlohmann::json j;
+			j["token"] = arg1;
+			jsons = OSUtils::jsonDump(j,-1);
+		}
+		char cl[128];
+		OSUtils::ztsnprintf(cl,sizeof(cl),"%u",(unsigned int)jsons.length());
+		requestHeaders["Content-Type"] = "application/json";
+		requestHeaders["Content-Length"] = cl;
+		unsigned int scode = Http::POST(
+			1024 * 1024 * 16,
+			60000,
+			(const struct sockaddr *)&addr,
+			(command == "panic") ? "/panic" : "/panic/rearm",
+			requestHeaders,
+			jsons.data(),
+			(unsigned long)jsons.length(),
+			responseHeaders,
+			responseBody);
+		if (scode == 200) {
+			if (json) {
+				printf("%s",cliFixJsonCRs(responseBody).c_str());
+			} else if (command == "panic") {
+				std::string token;
+				try {
+					token = OSUtils::jsonString(OSUtils::jsonParse(responseBody)["token"],"");
+				} catch ( ... ) {}
+				printf("200 panic OK, re-arm token: %s" ZT_EOL_S,token.c_str());
+			} else {
+				printf("200 rearm OK" ZT_EOL_S);
+			}
+			return 0;
+		} else {
+			printf("%u %s %s" ZT_EOL_S,scode,command.c_str(),responseBody.c_str());
+			return 1;
+		}
 	} else if (command == "set") {
 		if (arg1.length() != 16) {
 			fprintf(stderr,"invalid format: must be a 16-digit (network) ID\n");
@@ -943,7 +1090,7 @@ static int cli(int argc,char **argv)
 		}
 		std::size_t eqidx = arg2.find('=');
 		if (eqidx != std::string::npos) {
-			if ((arg2.substr(0,eqidx) == "allowManaged")||(arg2.substr(0,eqidx) == "allowGlobal")||(arg2.substr(0,eqidx) == "allowDefault")||(arg2.substr(0,eqidx) == "allowDNS")) {
+			if ((arg2.substr(0,eqidx) == "allowManaged")||(arg2.substr(0,eqidx) == "allowGlobal")||(arg2.substr(0,eqidx) == "allowDefault")||(arg2.substr(0,eqidx) == "allowDNS")||(arg2.substr(0,eqidx) == "paused")) {
 				char jsons[1024];
 				OSUtils::ztsnprintf(jsons,sizeof(jsons),"{\"%s\":%s}",
 					arg2.substr(0,eqidx).c_str(),
@@ -1089,7 +1236,13 @@ static int cli(int argc,char **argv)
 			printf("Error connecting to the ZeroTier service: %s\n\nPlease check that the service is running and that TCP port 9993 can be contacted via 127.0.0.1." ZT_EOL_S, responseBody.c_str());
 			return 1;
 		}
-		dump << responseBody << ZT_EOL_S;
+		try {
+			nlohmann::json status(OSUtils::jsonParse(responseBody));
+			cliRedactLocalConf(status["config"]);
+			dump << OSUtils::jsonDump(status) << ZT_EOL_S;
+		} catch ( ... ) {
+			dump << "(invalid JSON response)" << ZT_EOL_S;
+		}
 
 		responseHeaders.clear();
 		responseBody = "";
@@ -1134,7 +1287,13 @@ static int cli(int argc,char **argv)
 			dump << "None Present" << ZT_EOL_S;
 		}
 		else {
-			dump << localConf << ZT_EOL_S;
+			try {
+				nlohmann::json lc(OSUtils::jsonParse(localConf));
+				cliRedactLocalConf(lc);
+				dump << OSUtils::jsonDump(lc) << ZT_EOL_S;
+			} catch ( ... ) {
+				dump << "(not valid JSON, not included)" << ZT_EOL_S;
+			}
 		}
 
 		dump << ZT_EOL_S << "Network Interfaces" << ZT_EOL_S << "------------------" << ZT_EOL_S << ZT_EOL_S;
@@ -1371,7 +1530,7 @@ static int cli(int argc,char **argv)
 		getcwd(cwd, sizeof(cwd));
 		sprintf(cwd, "%s%szerotier_dump.txt", cwd, ZT_PATH_SEPARATOR_S);
 		fprintf(stdout, "Writing dump to: %s\n", cwd);
-		int fd = open(cwd, O_CREAT|O_RDWR,0664);
+		int fd = open(cwd, O_CREAT|O_RDWR|O_TRUNC,0664);
 		if (fd == -1) {
 			fprintf(stderr, "Error creating file.\n");
 			return 1;
@@ -1411,6 +1570,7 @@ static void idtoolPrintHelp(FILE *out,const char *pn)
 	fprintf(out,"  getpublic <identity.secret>" ZT_EOL_S);
 	fprintf(out,"  sign <identity.secret> <file>" ZT_EOL_S);
 	fprintf(out,"  verify <identity.secret/public> <file> <signature>" ZT_EOL_S);
+	fprintf(out,"  signrequest <identity.secret> <method> <url> [<body file>]" ZT_EOL_S);
 	fprintf(out,"  initmoon <identity.public of first seed>" ZT_EOL_S);
 	fprintf(out,"  genmoon <moon json>" ZT_EOL_S);
 }
@@ -1535,6 +1695,29 @@ static int idtool(int argc,char **argv)
 		C25519::Signature signature = id.sign(inf.data(),(unsigned int)inf.length());
 		char hexbuf[1024];
 		printf("%s",Utils::hex(signature.data,ZT_C25519_SIGNATURE_LEN,hexbuf));
+	} else if (!strcmp(argv[1],"signrequest")) {
+		if (argc < 5) {
+			idtoolPrintHelp(stdout,argv[0]);
+			return 1;
+		}
+
+		Identity id = getIdFromArg(argv[2]);
+		if (!id) {
+			fprintf(stderr,"Identity argument invalid or file unreadable: %s" ZT_EOL_S,argv[2]);
+			return 1;
+		}
+
+		if (!id.hasPrivate()) {
+			fprintf(stderr,"%s does not contain a private key (must use private to sign)" ZT_EOL_S,argv[2]);
+			return 1;
+		}
+
+		std::string body;
+		if ((argc >= 6)&&(!OSUtils::readFile(argv[5],body))) {
+			fprintf(stderr,"%s is not readable" ZT_EOL_S,argv[5]);
+			return 1;
+		}
+		printf("%s",ManagementAuth::sign(id,argv[3],std::string(argv[4]),OSUtils::now(),body).c_str());
 	} else if (!strcmp(argv[1],"verify")) {
 		if (argc < 5) {
 			idtoolPrintHelp(stdout,argv[0]);
@@ -1985,6 +2168,195 @@ static BOOL IsCurrentUserLocalAdministrator(void)
 /* main() and friends                                                       */
 /****************************************************************************/
 
+// Check a local.conf for mistakes without starting the service (-c)
+static int checkLocalConf(const char *path)
+{
+	std::string buf;
+	if (!OSUtils::readFile(path,buf)) {
+		fprintf(stderr,"%s: unable to read file" ZT_EOL_S,path);
+		return 2;
+	}
+
+	std::vector< std::pair<std::string,std::string> > errors,warnings;
+	OneService::checkLocalConf(buf,errors,warnings);
+	for(std::vector< std::pair<std::string,std::string> >::const_iterator w(warnings.begin());w!=warnings.end();++w)
+		fprintf(stderr,"%s: %s: warning: %s" ZT_EOL_S,path,w->first.c_str(),w->second.c_str());
+	for(std::vector< std::pair<std::string,std::string> >::const_iterator e(errors.begin());e!=errors.end();++e) {
+		if (e->first.empty())
+			fprintf(stderr,"%s: %s" ZT_EOL_S,path,e->second.c_str());
+		else fprintf(stderr,"%s: %s: %s" ZT_EOL_S,path,e->first.c_str(),e->second.c_str());
+	}
+
+	if (!errors.empty()) {
+		fprintf(stderr,"%s: %lu error(s)" ZT_EOL_S,path,(unsigned long)errors.size());
+		return 1;
+	}
+	printf("%s: OK" ZT_EOL_S,path);
+	return 0;
+}
+
+// Check that this host can run the service (-t)
+static int preflight(const std::string &homeDir,unsigned int port)
+{
+	unsigned long failures = 0,warnings = 0;
+	auto report = [&failures,&warnings](int level,const char *check,const std::string &detail) {
+		printf("%-6s %-12s %s" ZT_EOL_S,(level == 0) ? "ok" : ((level == 1) ? "WARN" : "FAIL"),check,detail.c_str());
+		if (level == 1)
+			++warnings;
+		else if (level == 2)
+			++failures;
+	};
+
+	// State directory must be writable for identity, networks.d, peers.d, etc.
+	{
+		const std::string tp(homeDir + ZT_PATH_SEPARATOR_S + ".preflight");
+		if (OSUtils::writeFile(tp.c_str(),std::string("test"))) {
+			OSUtils::rm(tp.c_str());
+			report(0,"home",homeDir + " is writable");
+		} else report(2,"home",homeDir + " is not writable");
+	}
+
+	// Identity must be valid if present (it is generated on first run otherwise)
+	{
+		std::string idbuf;
+		if (OSUtils::readFile((homeDir + ZT_PATH_SEPARATOR_S "identity.secret").c_str(),idbuf)) {
+			Identity id;
+			if ((id.fromString(idbuf.c_str()))&&(id.hasPrivate())&&(id.locallyValidate())) {
+				char tmp[16];
+				report(0,"identity",std::string("identity.secret is valid (") + id.address().toString(tmp) + ")");
+			} else report(2,"identity","identity.secret is invalid");
+		} else report(0,"identity","no identity.secret yet, one will be generated");
+	}
+
+	// Trust store: planet and moons must parse and list at least one root
+	{
+		std::vector<std::string> worlds;
+		worlds.push_back("planet");
+		std::vector<std::string> moons(OSUtils::listDirectory((homeDir + ZT_PATH_SEPARATOR_S "moons.d").c_str()));
+		for(std::vector<std::string>::const_iterator m(moons.begin());m!=moons.end();++m) {
+			if ((m->length() == 21)&&(m->substr(16) == ".moon"))
+				worlds.push_back(std::string("moons.d" ZT_PATH_SEPARATOR_S) + *m);
+		}
+		for(std::vector<std::string>::const_iterator w(worlds.begin());w!=worlds.end();++w) {
+			std::string wbuf;
+			if (!OSUtils::readFile((homeDir + ZT_PATH_SEPARATOR_S + *w).c_str(),wbuf)) {
+				if (*w == "planet")
+					report(0,"trust",std::string("no planet file, using the built-in planet"));
+				continue;
+			}
+			if (stripStateChecksum(wbuf) < 0) {
+				report(2,"trust",*w + " does not match its checksum");
+				continue;
+			}
+			try {
+				World world;
+				world.deserialize(Buffer<ZT_WORLD_MAX_SERIALIZED_LENGTH>(wbuf.data(),(unsigned int)wbuf.length()),0);
+				if (world.roots().empty())
+					report(2,"trust",*w + " lists no roots");
+				else report(0,"trust",*w + " is valid (" + std::to_string(world.roots().size()) + " root(s))");
+			} catch ( ... ) {
+				report(2,"trust",*w + " is invalid");
+			}
+		}
+	}
+
+	// local.conf must pass the same checks as -c
+	{
+		const std::string lcp(homeDir + ZT_PATH_SEPARATOR_S "local.conf");
+		if (OSUtils::fileExists(lcp.c_str())) {
+			fflush(stdout);
+			if (checkLocalConf(lcp.c_str()) == 0)
+				report(0,"local.conf","no errors");
+			else report(2,"local.conf","has errors (see above)");
+		}
+	}
+
+	// Clock must be sane since credentials and worlds carry timestamps
+	{
+		const int64_t now = OSUtils::now();
+		if (now < 1577836800000LL) // 2020-01-01
+			report(2,"clock","system clock is set before 2020");
+		else report(0,"clock","system clock looks sane");
+	}
+
+	// UDP port for ZeroTier traffic on the wildcard and on each settings.bind address, and TCP port for the local API
+	{
+		std::vector<InetAddress> binds;
+		std::string lcbuf;
+		if (OSUtils::readFile((homeDir + ZT_PATH_SEPARATOR_S "local.conf").c_str(),lcbuf)) {
+			try {
+				nlohmann::json lc(OSUtils::jsonParse(lcbuf));
+				nlohmann::json &settings = lc["settings"];
+				if (settings.is_object()) {
+					if (settings.count("primaryPort"))
+						port = (unsigned int)OSUtils::jsonInt(settings["primaryPort"],(uint64_t)port) & 0xffff;
+					nlohmann::json &bind = settings["bind"];
+					if (bind.is_array()) {
+						for(unsigned long i=0;i<bind.size();++i) {
+							const InetAddress ip(OSUtils::jsonString(bind[i],"").c_str());
+							if ((ip.ss_family == AF_INET)||(ip.ss_family == AF_INET6))
+								binds.push_back(ip);
+						}
+					}
+				}
+			} catch ( ... ) {} // reported by the local.conf check
+		}
+
+		auto tryBind = [&report,&port](InetAddress ba,int type,bool optional) {
+			char ipbuf[64];
+			const std::string what(std::string((type == SOCK_DGRAM) ? "UDP " : "TCP ") + ba.toIpString(ipbuf) + "/" + std::to_string(port));
+			ba.setPort(port);
+			const int fd = (int)::socket(ba.ss_family,type,0);
+			if (fd < 0) {
+				if (!optional)
+					report(2,"port",what + ": unable to create socket: " + strerror(errno));
+				return;
+			}
+			const bool bound = (::bind(fd,(const struct sockaddr *)&ba,(ba.ss_family == AF_INET6) ? sizeof(struct sockaddr_in6) : sizeof(struct sockaddr_in)) == 0);
+			const int err = errno;
+#ifdef __WINDOWS__
+			::closesocket(fd);
+#else
+			::close(fd);
+#endif
+			if (bound)
+				report(0,"port",what + " is available");
+			else if (err == EADDRINUSE)
+				report(1,"port",what + " is in use (is zerotier-one already running?)");
+			else if (err == EADDRNOTAVAIL)
+				report(2,"port",what + ": address is not on this host");
+			else report(2,"port",what + ": " + strerror(err));
+		};
+
+		if (port) {
+			if (binds.empty()) {
+				tryBind(InetAddress("0.0.0.0"),SOCK_DGRAM,false);
+				tryBind(InetAddress("::"),SOCK_DGRAM,true); // hosts without IPv6 can't create the socket
+			} else {
+				for(std::vector<InetAddress>::const_iterator b(binds.begin());b!=binds.end();++b)
+					tryBind(*b,SOCK_DGRAM,false);
+			}
+			tryBind(InetAddress("127.0.0.1"),SOCK_STREAM,false);
+		} else report(0,"port","random port, not checked");
+	}
+
+	// Virtual network ports need the TUN/TAP driver
+#ifdef __LINUX__
+	{
+		const int fd = ::open("/dev/net/tun",O_RDWR);
+		if (fd >= 0) {
+			::close(fd);
+			report(0,"tun","/dev/net/tun can be opened");
+		} else report(2,"tun",std::string("/dev/net/tun: ") + strerror(errno));
+	}
+#else
+	report(0,"tun","not checked on this platform");
+#endif
+
+	printf("%lu failure(s), %lu warning(s)" ZT_EOL_S,failures,warnings);
+	return (failures) ? 1 : 0;
+}
+
 static void printHelp(const char *cn,FILE *out)
 {
 	fprintf(out,
@@ -2000,6 +2372,8 @@ static void printHelp(const char *cn,FILE *out)
 	fprintf(out,"  -v                - Show version" ZT_EOL_S);
 	fprintf(out,"  -U                - Skip privilege check and do not attempt to drop privileges" ZT_EOL_S);
 	fprintf(out,"  -p<port>          - Port for UDP and TCP/HTTP (default: 9993, 0 for random)" ZT_EOL_S);
+	fprintf(out,"  -c<file>          - Check a local.conf file for errors and exit" ZT_EOL_S);
+	fprintf(out,"  -t                - Check that this host can run the service and exit" ZT_EOL_S);
 
 #ifdef __UNIX_LIKE__
 	fprintf(out,"  -d                - Fork and run as daemon (Unix-ish OSes)" ZT_EOL_S);
@@ -2125,6 +2499,7 @@ int main(int argc,char **argv)
 	std::string homeDir;
 	unsigned int port = ZT_DEFAULT_PORT;
 	bool skipRootCheck = false;
+	bool preflightOnly = false;
 
 	for(int i=1;i<argc;++i) {
 		if (argv[i][0] == '-') {
@@ -2152,6 +2527,17 @@ int main(int argc,char **argv)
 					printf("%d.%d.%d" ZT_EOL_S,ZEROTIER_ONE_VERSION_MAJOR,ZEROTIER_ONE_VERSION_MINOR,ZEROTIER_ONE_VERSION_REVISION);
 					return 0;
 
+				case 'c': // Check local.conf and exit
+					if (!argv[i][2]) {
+						printHelp(argv[0],stdout);
+						return 1;
+					}
+					return checkLocalConf(argv[i] + 2);
+
+				case 't': // Check whether this host can run the service and exit
+					preflightOnly = true;
+					break;
+
 				case 'i': // Invoke idtool personality
 					if (argv[i][2]) {
 						printHelp(argv[0],stdout);
@@ -2248,6 +2634,9 @@ int main(int argc,char **argv)
 		return 1;
 	}
 
+	if (preflightOnly)
+		return preflight(homeDir,port);
+
 #ifdef __UNIX_LIKE__
 #ifndef ZT_ONE_NO_ROOT_CHECK
 	if ((!skipRootCheck)&&(getuid() != 0)) {
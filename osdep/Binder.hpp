@@ -78,14 +78,62 @@ class Binder
 private:
 	struct _Binding
 	{
-		_Binding() : udpSock((PhySocket *)0),tcpListenSock((PhySocket *)0) {}
+		_Binding() : udpSock((PhySocket *)0),tcpListenSock((PhySocket *)0),udpTos(0) {}
 		PhySocket *udpSock;
 		PhySocket *tcpListenSock;
 		InetAddress address;
+		unsigned int udpTos; // TOS currently set on udpSock, changed only under its _tosLock
 	};
 
 public:
-	Binder() : _bindingCount(0) {}
+	Binder() : _bindingCount(0),_tos(0),_tosOverrides(false) {}
+
+	/**
+	 * Set the TOS / traffic class byte for all current and future UDP sockets
+	 *
+	 * @param phy Physical interface
+	 * @param tos TOS byte (DSCP << 2), 0 to leave sockets at the OS default
+	 */
+	template<typename PHY_HANDLER_TYPE>
+	void setTos(Phy<PHY_HANDLER_TYPE> &phy,unsigned int tos)
+	{
+		Mutex::Lock _l(_lock);
+		if (tos == _tos)
+			return;
+		_tos = tos;
+		for(unsigned int b=0,c=_bindingCount;b<c;++b) {
+			Mutex::Lock _tl(_tosLock[b]);
+			phy.setIpTos(_bindings[b].udpSock,tos);
+			_bindings[b].udpTos = tos;
+		}
+	}
+
+	/**
+	 * Set whether udpSend() may be given a TOS byte for single packets
+	 *
+	 * While this is off, sends on sockets that can't mark single packets
+	 * skip the per-socket lock, since nothing switches the socket's TOS.
+	 *
+	 * @param phy Physical interface
+	 * @param on True if some packets may be sent with their own TOS
+	 */
+	template<typename PHY_HANDLER_TYPE>
+	void setTosOverrides(Phy<PHY_HANDLER_TYPE> &phy,bool on)
+	{
+		Mutex::Lock _l(_lock);
+		if (on == _tosOverrides)
+			return;
+		_tosOverrides = on;
+		if (!on) {
+			for(unsigned int b=0,c=_bindingCount;b<c;++b) {
+				Mutex::Lock _tl(_tosLock[b]);
+				if (_bindings[b].udpTos != _tos) {
+					phy.setIpTos(_bindings[b].udpSock,_tos);
+					_bindings[b].udpTos = _tos;
+				}
+			}
+		}
+	}
 
 	/**
 	 * Close all bound ports, should be called on shutdown
@@ -400,6 +448,9 @@ public:
 						_bindings[_bindingCount].tcpListenSock = tcps;
 						_bindings[_bindingCount].address = ii->first;
 						phy.setIfName(udps,(char*)ii->second.c_str(),(int)ii->second.length());
+						if (_tos)
+							phy.setIpTos(udps,_tos);
+						_bindings[_bindingCount].udpTos = _tos;
 						++_bindingCount;
 					}
 				} else {
@@ -422,18 +473,39 @@ public:
 		return aa;
 	}
 
+	/**
+	 * Send from one bound UDP socket
+	 *
+	 * Where the platform can't mark a single packet, the socket's TOS is
+	 * switched for the send and left there until a packet needs another
+	 * value. While setTosOverrides() is on, every send on that socket holds
+	 * its lock so no other packet goes out with the switched value.
+	 *
+	 * @param udpSock Bound UDP socket (check with isUdpSocketValid())
+	 * @param tos TOS byte for this packet only, or -1 for the one set with setTos()
+	 */
+	template<typename PHY_HANDLER_TYPE>
+	inline bool udpSend(Phy<PHY_HANDLER_TYPE> &phy,PhySocket *udpSock,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int ttl,int tos = -1)
+	{
+		for(unsigned int b=0,c=_bindingCount;b<c;++b) {
+			if (_bindings[b].udpSock == udpSock)
+				return _udpSend(phy,b,addr,data,len,ttl,tos);
+		}
+		return false;
+	}
+
 	/**
 	 * Send from all bound UDP sockets
+	 *
+	 * @param tos TOS byte for this packet only, or -1 for the one set with setTos()
 	 */
 	template<typename PHY_HANDLER_TYPE>
-	inline bool udpSendAll(Phy<PHY_HANDLER_TYPE> &phy,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int ttl)
+	inline bool udpSendAll(Phy<PHY_HANDLER_TYPE> &phy,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int ttl,int tos = -1)
 	{
 		bool r = false;
 		Mutex::Lock _l(_lock);
 		for(unsigned int b=0,c=_bindingCount;b<c;++b) {
-			if (ttl) phy.setIp4UdpTtl(_bindings[b].udpSock,ttl);
-			if (phy.udpSend(_bindings[b].udpSock,(const struct sockaddr *)addr,data,len)) r = true;
-			if (ttl) phy.setIp4UdpTtl(_bindings[b].udpSock,255);
+			if (_udpSend(phy,b,addr,data,len,ttl,tos)) r = true;
 		}
 		return r;
 	}
@@ -474,10 +546,38 @@ public:
 	}
 
 private:
+	template<typename PHY_HANDLER_TYPE>
+	inline bool _udpSend(Phy<PHY_HANDLER_TYPE> &phy,const unsigned int b,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int ttl,int tos)
+	{
+		PhySocket *const udpSock = _bindings[b].udpSock;
+		if ((phy.canSendWithTos(udpSock))||((tos < 0)&&(!_tosOverrides))) {
+			if (ttl) phy.setIp4UdpTtl(udpSock,ttl);
+			const bool r = phy.udpSend(udpSock,(const struct sockaddr *)addr,data,len,tos);
+			if (ttl) phy.setIp4UdpTtl(udpSock,255);
+			return r;
+		}
+		Mutex::Lock _tl(_tosLock[b]);
+		const unsigned int want = (tos >= 0) ? (unsigned int)tos : (unsigned int)_tos;
+		if (_bindings[b].udpTos != want) {
+			phy.setIpTos(udpSock,want);
+			_bindings[b].udpTos = want;
+		}
+		if (ttl) phy.setIp4UdpTtl(udpSock,ttl);
+		const bool r = phy.udpSend(udpSock,(const struct sockaddr *)addr,data,len);
+		if (ttl) phy.setIp4UdpTtl(udpSock,255);
+		if ((!_tosOverrides)&&(want != _tos)) { // overrides were switched off during this send
+			phy.setIpTos(udpSock,_tos);
+			_bindings[b].udpTos = _tos;
+		}
+		return r;
+	}
 
 	std::set<std::string> linkIfNames;
 	_Binding _bindings[ZT_BINDER_MAX_BINDINGS];
 	std::atomic<unsigned int> _bindingCount;
+	std::atomic<unsigned int> _tos;
+	std::atomic<bool> _tosOverrides;
+	Mutex _tosLock[ZT_BINDER_MAX_BINDINGS]; // held around sends on sockets that can't mark single packets
 	Mutex _lock;
 };
 
@@ -56,6 +56,21 @@ public:
 		}
 	}
 
+	/**
+	 * Post unless limit entries are already waiting, without blocking
+	 *
+	 * @return True if posted
+	 */
+	inline bool postIfRoom(T t,const unsigned long limit)
+	{
+		std::lock_guard<std::mutex> lock(m);
+		if (q.size() >= limit)
+			return false;
+		q.push(t);
+		c.notify_one();
+		return true;
+	}
+
 	inline void stop(void)
 	{
 		std::lock_guard<std::mutex> lock(m);
@@ -35,6 +35,7 @@
 
 #ifdef __WINDOWS__
 #include <windows.h>
+#include <io.h>
 #include <wincrypt.h>
 #include <ShlObj.h>
 #include <netioapi.h>
@@ -84,6 +85,23 @@ bool OSUtils::redirectUnixOutputs(const char *stdoutPath,const char *stderrPath)
 	}
 	return false;
 }
+
+bool OSUtils::unixPeerIsRootOrSelf(int fd)
+{
+#ifdef __LINUX__
+	struct ucred cred;
+	socklen_t credLen = sizeof(cred);
+	if (getsockopt(fd,SOL_SOCKET,SO_PEERCRED,&cred,&credLen) != 0)
+		return false;
+	const uid_t uid = cred.uid;
+#else
+	uid_t uid = 0;
+	gid_t gid = 0;
+	if (getpeereid(fd,&uid,&gid) != 0)
+		return false;
+#endif
+	return ((uid == 0)||(uid == geteuid()));
+}
 #endif // __UNIX_LIKE__
 
 std::vector<std::string> OSUtils::listDirectory(const char *path,bool includeDirectories)
@@ -258,6 +276,28 @@ void OSUtils::lockDownFile(const char *path,bool isDir)
 #endif
 }
 
+FILE *OSUtils::fopenPrivate(const char *path,bool append)
+{
+#ifdef __UNIX_LIKE__
+	const int fd = ::open(path,O_WRONLY|O_CREAT|(append ? O_APPEND : O_TRUNC),0600);
+	if (fd < 0)
+		return (FILE *)0;
+	if (fchmod(fd,0600)) { // O_CREAT does not change the modes of an existing file
+		::close(fd);
+		return (FILE *)0;
+	}
+	FILE *f = fdopen(fd,append ? "a" : "w");
+	if (!f)
+		::close(fd);
+	return f;
+#else
+	FILE *f = fopen(path,append ? "a" : "w");
+	if (f)
+		lockDownFile(path,false); // nothing new written yet
+	return f;
+#endif
+}
+
 uint64_t OSUtils::getLastModified(const char *path)
 {
 	struct stat s;
@@ -322,6 +362,44 @@ bool OSUtils::writeFile(const char *path,const void *buf,unsigned int len)
 	return false;
 }
 
+bool OSUtils::syncFile(FILE *f)
+{
+	if (fflush(f) != 0)
+		return false;
+#ifdef __WINDOWS__
+	return (_commit(_fileno(f)) == 0);
+#else
+	return (fsync(fileno(f)) == 0);
+#endif
+}
+
+int64_t OSUtils::parseDuration(const char *s,const int64_t unit)
+{
+	int64_t n = 0;
+	const char *p = s;
+	while ((*p >= '0')&&(*p <= '9')) {
+		n = (n * 10) + (int64_t)(*p - '0');
+		if (n > 0xffffffffLL)
+			return -1;
+		++p;
+	}
+	if (p == s)
+		return -1;
+	if (!*p)
+		return n * unit;
+	if (!strcmp(p,"ms"))
+		return n;
+	if (!p[1]) {
+		switch(*p) {
+			case 's': return n * 1000LL;
+			case 'm': return n * 60000LL;
+			case 'h': return n * 3600000LL;
+			case 'd': return n * 86400000LL;
+		}
+	}
+	return -1;
+}
+
 std::vector<std::string> OSUtils::split(const char *s,const char *const sep,const char *esc,const char *quot)
 {
 	std::vector<std::string> fields;
@@ -513,6 +591,23 @@ bool OSUtils::jsonBool(const nlohmann::json &jv,const bool dfl)
 	return dfl;
 }
 
+int64_t OSUtils::jsonDuration(const nlohmann::json &jv,const int64_t dfl,const int64_t unit)
+{
+	try {
+		if (jv.is_number_unsigned()) {
+			// Same limit as parseDuration() so the multiply below can't overflow
+			const uint64_t n = (uint64_t)jv;
+			if (n <= 0xffffffffULL)
+				return (int64_t)n * unit;
+		} else if (jv.is_string()) {
+			const int64_t d = parseDuration(jv.get<std::string>().c_str(),unit);
+			if (d >= 0)
+				return d;
+		}
+	} catch ( ... ) {}
+	return dfl;
+}
+
 std::string OSUtils::jsonString(const nlohmann::json &jv,const char *dfl)
 {
 	try {
@@ -81,6 +81,17 @@ public:
 	 */
 	static bool redirectUnixOutputs(const char *stdoutPath,const char *stderrPath = (const char *)0)
 		throw();
+
+	/**
+	 * Check that the process on the other end of a Unix socket runs as root or as our user
+	 *
+	 * The peer's user ID is read with SO_PEERCRED on Linux and getpeereid()
+	 * elsewhere, so it is the one the peer had when it connected.
+	 *
+	 * @param fd Connected Unix domain socket
+	 * @return True if the peer's user ID is 0 or our effective user ID
+	 */
+	static bool unixPeerIsRootOrSelf(int fd);
 #endif // __UNIX_LIKE__
 
 	/**
@@ -116,8 +127,8 @@ public:
 	static inline bool rename(const char *o,const char *n)
 	{
 #ifdef __WINDOWS__
-		DeleteFileA(n);
-		return (::rename(o,n) == 0);
+		// Replace in one step so a crash can't leave neither the old nor the new file in place
+		return (MoveFileExA(o,n,MOVEFILE_REPLACE_EXISTING|MOVEFILE_WRITE_THROUGH) != FALSE);
 #else
 		return (::rename(o,n) == 0);
 #endif
@@ -161,6 +172,18 @@ public:
 	 */
 	static void lockDownFile(const char *path,bool isDir);
 
+	/**
+	 * Open a file for writing that only the owner can access
+	 *
+	 * The file is created (or truncated) with owner-only modes before anything
+	 * is written to it, so secrets never sit in a world readable file.
+	 *
+	 * @param path Path to open
+	 * @param append If true, keep existing contents and append instead of truncating
+	 * @return FILE opened for writing or NULL on failure
+	 */
+	static FILE *fopenPrivate(const char *path,bool append = false);
+
 	/**
 	 * Get file last modification time
 	 *
@@ -252,6 +275,23 @@ public:
 	 */
 	static bool writeFile(const char *path,const void *buf,unsigned int len);
 
+	/**
+	 * Flush an open file and wait until its contents have reached the disk
+	 *
+	 * @param f Open file
+	 * @return True on success
+	 */
+	static bool syncFile(FILE *f);
+
+	/**
+	 * Parse a duration such as "250ms", "30s", "5m", "12h", or "90d"
+	 *
+	 * @param s String to parse
+	 * @param unit Milliseconds per unit for a bare number with no suffix
+	 * @return Duration in milliseconds or -1 if invalid
+	 */
+	static int64_t parseDuration(const char *s,const int64_t unit = 1);
+
 	/**
 	 * Split a string by delimiter, with optional escape and quote characters
 	 *
@@ -290,6 +330,7 @@ public:
 	static double jsonDouble(const nlohmann::json &jv,const double dfl);
 	static uint64_t jsonIntHex(const nlohmann::json &jv,const uint64_t dfl);
 	static bool jsonBool(const nlohmann::json &jv,const bool dfl);
+	static int64_t jsonDuration(const nlohmann::json &jv,const int64_t dfl,const int64_t unit = 1);
 	static std::string jsonString(const nlohmann::json &jv,const char *dfl);
 	static std::string jsonBinFromHex(const nlohmann::json &jv);
 #endif // OMIT_JSON_SUPPORT
@@ -45,6 +45,7 @@
 #include <sys/types.h>
 #include <sys/select.h>
 #include <sys/socket.h>
+#include <sys/uio.h>
 #include <sys/un.h>
 #include <arpa/inet.h>
 #include <netinet/in.h>
@@ -452,21 +453,121 @@ public:
 #endif
 	}
 
+	/**
+	 * Set the IP TOS (IPv4) or traffic class (IPv6) byte for a UDP socket
+	 *
+	 * The low two (ECN) bits are left to the OS. Marking packets ECN-capable
+	 * is only safe if a congestion mark on the outer packet is carried into
+	 * the inner one on receipt (RFC 6040), and nothing above this layer can.
+	 *
+	 * @param sock UDP socket
+	 * @param tos TOS / traffic class byte (DSCP << 2)
+	 * @return True on success
+	 */
+	inline bool setIpTos(PhySocket *sock,unsigned int tos)
+	{
+		PhySocketImpl &sws = *(reinterpret_cast<PhySocketImpl *>(sock));
+		tos &= 0xfc;
+#if defined(_WIN32) || defined(_WIN64)
+		DWORD tmp = (DWORD)tos;
+#define ZT_PHY_SOCKOPT_CAST (const char *)
+#else
+		int tmp = (int)tos;
+#define ZT_PHY_SOCKOPT_CAST (void *)
+#endif
+		bool r = false;
+		if (reinterpret_cast<const struct sockaddr *>(&(sws.saddr))->sa_family == AF_INET6) {
+#ifdef IPV6_TCLASS
+			r = (::setsockopt(sws.sock,IPPROTO_IPV6,IPV6_TCLASS,ZT_PHY_SOCKOPT_CAST &tmp,sizeof(tmp)) == 0);
+#endif
+		} else {
+#ifdef IP_TOS
+			r = (::setsockopt(sws.sock,IPPROTO_IP,IP_TOS,ZT_PHY_SOCKOPT_CAST &tmp,sizeof(tmp)) == 0);
+#endif
+		}
+#undef ZT_PHY_SOCKOPT_CAST
+		return r;
+	}
+
+	/**
+	 * @param sock UDP socket
+	 * @return True if udpSend() can give packets sent on this socket their own TOS / traffic class byte
+	 */
+	inline bool canSendWithTos(PhySocket *sock) const
+	{
+#if defined(_WIN32) || defined(_WIN64)
+		return false;
+#else
+		const PhySocketImpl &sws = *(reinterpret_cast<const PhySocketImpl *>(sock));
+		if (reinterpret_cast<const struct sockaddr *>(&(sws.saddr))->sa_family == AF_INET6) {
+#ifdef IPV6_TCLASS
+			return true; // RFC 3542 ancillary data
+#else
+			return false;
+#endif
+		}
+#if (defined(__linux__) || defined(linux) || defined(__LINUX__) || defined(__linux)) && defined(IP_TOS)
+		return true;
+#else
+		return false; // not all BSDs accept IP_TOS ancillary data on send
+#endif
+#endif
+	}
+
 	/**
 	 * Send a UDP packet
 	 *
+	 * A TOS byte given here is sent as IP_TOS or IPV6_TCLASS ancillary data,
+	 * so other packets sent on the socket at the same time are not affected.
+	 * It is ignored unless canSendWithTos() is true for the socket.
+	 *
 	 * @param sock UDP socket
 	 * @param remoteAddress Destination address (must be correct type for socket)
 	 * @param data Data to send
 	 * @param len Length of packet
+	 * @param tos TOS / traffic class byte (DSCP << 2) for this packet only, or -1 for the socket's own
 	 * @return True if packet appears to have been sent successfully
 	 */
-	inline bool udpSend(PhySocket *sock,const struct sockaddr *remoteAddress,const void *data,unsigned long len)
+	inline bool udpSend(PhySocket *sock,const struct sockaddr *remoteAddress,const void *data,unsigned long len,int tos = -1)
 	{
 		PhySocketImpl &sws = *(reinterpret_cast<PhySocketImpl *>(sock));
 #if defined(_WIN32) || defined(_WIN64)
 		return ((long)::sendto(sws.sock,reinterpret_cast<const char *>(data),len,0,remoteAddress,(remoteAddress->sa_family == AF_INET6) ? sizeof(struct sockaddr_in6) : sizeof(struct sockaddr_in)) == (long)len);
 #else
+		if ((tos >= 0)&&(canSendWithTos(sock))) {
+			struct iovec iov;
+			iov.iov_base = const_cast<void *>(data);
+			iov.iov_len = len;
+			union {
+				char buf[CMSG_SPACE(sizeof(int))];
+				struct cmsghdr align;
+			} cm;
+			memset(&cm,0,sizeof(cm));
+			struct msghdr mh;
+			memset(&mh,0,sizeof(mh));
+			mh.msg_name = const_cast<struct sockaddr *>(remoteAddress);
+			mh.msg_namelen = (remoteAddress->sa_family == AF_INET6) ? sizeof(struct sockaddr_in6) : sizeof(struct sockaddr_in);
+			mh.msg_iov = &iov;
+			mh.msg_iovlen = 1;
+			mh.msg_control = cm.buf;
+			mh.msg_controllen = sizeof(cm.buf);
+			struct cmsghdr *const c = CMSG_FIRSTHDR(&mh);
+			c->cmsg_len = CMSG_LEN(sizeof(int));
+			if (reinterpret_cast<const struct sockaddr *>(&(sws.saddr))->sa_family == AF_INET6) {
+#ifdef IPV6_TCLASS
+				c->cmsg_level = IPPROTO_IPV6;
+				c->cmsg_type = IPV6_TCLASS;
+#endif
+			} else {
+#ifdef IP_TOS
+				c->cmsg_level = IPPROTO_IP;
+				c->cmsg_type = IP_TOS;
+#endif
+			}
+			const int v = tos & 0xfc; // ECN bits are left to the OS, see setIpTos()
+			memcpy(CMSG_DATA(c),&v,sizeof(v));
+			return ((long)::sendmsg(sws.sock,&mh,0) == (long)len);
+		}
 		return ((long)::sendto(sws.sock,data,len,0,remoteAddress,(remoteAddress->sa_family == AF_INET6) ? sizeof(struct sockaddr_in6) : sizeof(struct sockaddr_in)) == (long)len);
 #endif
 	}
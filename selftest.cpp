@@ -22,6 +22,8 @@
 #include <vector>
 #include <thread>
 
+#include "version.h"
+
 #include "node/Constants.hpp"
 #include "node/Hashtable.hpp"
 #include "node/RuntimeEnvironment.hpp"
@@ -41,11 +43,24 @@
 #include "node/CertificateOfMembership.hpp"
 #include "node/Node.hpp"
 #include "node/IncomingPacket.hpp"
+#include "node/Path.hpp"
+#include "node/Trace.hpp"
+#include "node/Switch.hpp"
 
 #include "osdep/OSUtils.hpp"
 #include "osdep/Phy.hpp"
 #include "osdep/PortMapper.hpp"
 #include "osdep/Thread.hpp"
+#include "osdep/Http.hpp"
+
+#include "controller/EmbeddedNetworkController.hpp"
+
+#include "service/OneService.hpp"
+#include "service/ApiRateLimiter.hpp"
+#include "service/ManagementAuth.hpp"
+#include "service/NetworkConfigDiff.hpp"
+#include "service/TrafficAlert.hpp"
+#include "service/StateChecksum.hpp"
 
 #if defined(ZT_USE_X64_ASM_SALSA2012) && defined(ZT_ARCH_X64)
 #include "ext/x64-salsa2012-asm/salsa2012.h"
@@ -58,6 +73,15 @@
 #include <tchar.h>
 #endif
 
+#ifdef __UNIX_LIKE__
+#include <utime.h>
+#include <unistd.h>
+#include <sys/socket.h>
+#include <sys/stat.h>
+#include <sys/un.h>
+#include <sys/wait.h>
+#endif
+
 using namespace ZeroTier;
 
 //////////////////////////////////////////////////////////////////////////////
@@ -609,6 +633,376 @@ static int testCertificate()
 	return 0;
 }
 
+static int testController()
+{
+	class _Sender : public NetworkController::Sender
+	{
+	public:
+		_Sender() : result(-1),revoked(0) {}
+		virtual void ncSendConfig(uint64_t nwid,uint64_t requestPacketId,const Address &destination,const NetworkConfig &nc,bool sendLegacyFormatConfig) { result = (int)NetworkController::NC_ERROR_NONE; }
+		virtual void ncSendRevocation(const Address &destination,const Revocation &rev) { revoked = destination.toInt(); }
+		virtual void ncSendError(uint64_t nwid,uint64_t requestPacketId,const Address &destination,NetworkController::ErrorCode errorCode) { result = (int)errorCode; }
+		std::atomic<int> result;
+		std::atomic<uint64_t> revoked;
+	};
+
+	char tmp[256];
+	OSUtils::ztsnprintf(tmp,sizeof(tmp),"zt-selftest-controller-%.8x",(unsigned int)rand());
+	const std::string dbPath(tmp);
+	OSUtils::rmDashRf(dbPath.c_str());
+
+	Identity signingId,memberId;
+	signingId.generate();
+	memberId.generate();
+
+	int r = 0;
+	{
+		_Sender sender;
+		EmbeddedNetworkController ctl((Node *)0,dbPath.c_str(),dbPath.c_str(),0,(RedisConfig *)0);
+		ctl.init(signingId,&sender);
+
+		std::map<std::string,std::string> args,headers;
+		std::string rb,rct;
+		std::vector<std::string> path;
+		// Gives each test its own network ID on this controller
+		unsigned int networks = 0;
+		auto nextNetworkId = [&](char nw[24]) {
+			OSUtils::ztsnprintf(nw,24,"%.10llx%.6x",(unsigned long long)signingId.address().toInt(),++networks);
+		};
+		char nwa[24],nwb[24];
+		nextNetworkId(nwa);
+		nextNetworkId(nwb);
+
+		// Asks the controller for a config as id, presenting token if not empty, and waits for its answer.
+		// Only requests with a packet ID mark the member online, and those are limited to one a second.
+		auto join = [&](const char *nwids,const Identity &id,const std::string &token,const uint64_t requestPacketId) -> int {
+			Dictionary<ZT_NETWORKCONFIG_METADATA_DICT_CAPACITY> md;
+			if (token.length() > 0)
+				md.add(ZT_NETWORKCONFIG_REQUEST_METADATA_KEY_AUTH,(std::string("token:") + token).c_str());
+			sender.result = -1;
+			ctl.request(Utils::hexStrToU64(nwids),InetAddress(),requestPacketId,id,md);
+			for(int i=0;((i<100)&&(sender.result < 0));++i)
+				Thread::sleep(50);
+			return sender.result;
+		};
+		auto getMember = [&](const char *nwids,const Address &a) -> nlohmann::json {
+			std::vector<std::string> mpath;
+			mpath.push_back("network");
+			mpath.push_back(nwids);
+			mpath.push_back("member");
+			mpath.push_back(a.toString(tmp));
+			ctl.handleControlPlaneHttpGET(mpath,args,headers,"",rb,rct);
+			return OSUtils::jsonParse(rb);
+		};
+
+		std::cout << "[controller] Cloned network does not accept the source network's join tokens... "; std::cout.flush();
+		path.push_back("network");
+		path.push_back(nwa);
+		ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"private\":true}",rb,rct);
+		path.push_back("token");
+		ctl.handleControlPlaneHttpPOST(path,args,headers,"{}",rb,rct);
+		const std::string token(OSUtils::jsonString(OSUtils::jsonParse(rb)["token"],""));
+		path[1] = nwb;
+		path[2] = "clone";
+		const unsigned int cloneStatus = ctl.handleControlPlaneHttpPOST(path,args,headers,std::string("{\"from\":\"") + nwa + "\"}",rb,rct);
+		if ((token.length() == 0)||(cloneStatus != 200)) {
+			std::cout << "FAIL (setup)" << std::endl;
+			r = -1;
+		} else if (join(nwa,memberId,token,0) != (int)NetworkController::NC_ERROR_NONE) {
+			std::cout << "FAIL (token rejected on source network)" << std::endl;
+			r = -1;
+		} else if (join(nwb,memberId,token,0) != (int)NetworkController::NC_ERROR_ACCESS_DENIED) {
+			std::cout << "FAIL (token accepted on clone)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+
+		std::cout << "[controller] Token authorization expires and the same token cannot renew it... "; std::cout.flush();
+		char nwd[24];
+		nextNetworkId(nwd);
+		path.clear();
+		path.push_back("network");
+		path.push_back(nwd);
+		ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"private\":true,\"tokenAuthorizationTime\":1}",rb,rct);
+		path.push_back("token");
+		ctl.handleControlPlaneHttpPOST(path,args,headers,"{}",rb,rct);
+		const std::string guestToken(OSUtils::jsonString(OSUtils::jsonParse(rb)["token"],""));
+		if (join(nwd,memberId,guestToken,0) != (int)NetworkController::NC_ERROR_NONE) {
+			std::cout << "FAIL (token rejected)" << std::endl;
+			r = -1;
+		} else {
+			Thread::sleep(10);
+			const int afterExpiry = join(nwd,memberId,guestToken,0);
+			const int again = join(nwd,memberId,guestToken,0);
+			const nlohmann::json m(getMember(nwd,memberId.address()));
+			if ((afterExpiry != (int)NetworkController::NC_ERROR_ACCESS_DENIED)||(again != (int)NetworkController::NC_ERROR_ACCESS_DENIED)) {
+				std::cout << "FAIL (token authorized the member again)" << std::endl;
+				r = -1;
+			} else if ((OSUtils::jsonBool(m["authorized"],true))||(!m["ipAssignments"].empty())) {
+				std::cout << "FAIL (member still authorized or holding IPs)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		std::cout << "[controller] Token authorization expires and is revoked while the member is not asking for configs... "; std::cout.flush();
+		{
+			char nwj[24];
+			nextNetworkId(nwj);
+			path.clear();
+			path.push_back("network");
+			path.push_back(nwj);
+			ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"private\":true,\"tokenAuthorizationTime\":1000,\"v4AssignMode\":{\"zt\":true},\"ipAssignmentPools\":[{\"ipRangeStart\":\"10.147.22.1\",\"ipRangeEnd\":\"10.147.22.254\"}],\"routes\":[{\"target\":\"10.147.22.0/24\"}]}",rb,rct);
+			path.push_back("token");
+			ctl.handleControlPlaneHttpPOST(path,args,headers,"{}",rb,rct);
+			const std::string sweptToken(OSUtils::jsonString(OSUtils::jsonParse(rb)["token"],""));
+			sender.revoked = 0;
+			if ((join(nwj,memberId,sweptToken,1) != (int)NetworkController::NC_ERROR_NONE)||(getMember(nwj,memberId.address())["ipAssignments"].empty())) {
+				std::cout << "FAIL (token rejected or no IP assigned)" << std::endl;
+				r = -1;
+			} else {
+				nlohmann::json m;
+				for(int i=0;i<100;++i) {
+					m = getMember(nwj,memberId.address());
+					if (!OSUtils::jsonBool(m["authorized"],true))
+						break;
+					Thread::sleep(50);
+				}
+				path[2] = "history";
+				std::map<std::string,std::string> hargs;
+				hargs["member"] = memberId.address().toString(tmp);
+				ctl.handleControlPlaneHttpGET(path,hargs,headers,"",rb,rct);
+				const nlohmann::json hist(OSUtils::jsonParse(rb));
+				if ((OSUtils::jsonBool(m["authorized"],true))||(!m["ipAssignments"].empty())) {
+					std::cout << "FAIL (member still authorized or holding IPs)" << std::endl;
+					r = -1;
+				} else if (sender.revoked != memberId.address().toInt()) {
+					std::cout << "FAIL (no revocation sent)" << std::endl;
+					r = -1;
+				} else if ((!hist.is_array())||(hist.empty())||(OSUtils::jsonString(hist.back()["by"],"") != "controller")||(hist.back()["changes"]["authorized"] != nlohmann::json::array({ true,false }))) {
+					std::cout << "FAIL (expiry not recorded in history)" << std::endl;
+					r = -1;
+				} else {
+					std::cout << "PASS" << std::endl;
+				}
+			}
+		}
+
+		std::cout << "[controller] autoAuthorize matches by address or identity and a deauthorization sticks... "; std::cout.flush();
+		{
+			Identity byAddr,byId,unlisted;
+			byAddr.generate();
+			byId.generate();
+			unlisted.generate();
+			char nwe[24],idstr[ZT_IDENTITY_STRING_BUFFER_LENGTH];
+			nextNetworkId(nwe);
+			const std::string addrEntry(byAddr.address().toString(tmp));
+			const std::string idEntry(byId.toString(false,idstr));
+			path.clear();
+			path.push_back("network");
+			path.push_back(nwe);
+			ctl.handleControlPlaneHttpPOST(path,args,headers,std::string("{\"private\":true,\"autoAuthorize\":[\"") + addrEntry + "\",\"" + idEntry + "\"]}",rb,rct);
+			const int addrResult = join(nwe,byAddr,std::string(),0);
+			const int idResult = join(nwe,byId,std::string(),0);
+			const int unlistedResult = join(nwe,unlisted,std::string(),0);
+			if ((addrResult != (int)NetworkController::NC_ERROR_NONE)||(idResult != (int)NetworkController::NC_ERROR_NONE)) {
+				std::cout << "FAIL (listed member rejected)" << std::endl;
+				r = -1;
+			} else if (unlistedResult != (int)NetworkController::NC_ERROR_ACCESS_DENIED) {
+				std::cout << "FAIL (unlisted member accepted)" << std::endl;
+				r = -1;
+			} else if ((OSUtils::jsonString(getMember(nwe,byAddr.address())["lastAuthorizedCredential"],"") != addrEntry)||(OSUtils::jsonString(getMember(nwe,byId.address())["lastAuthorizedCredential"],"") != idEntry)) {
+				std::cout << "FAIL (wrong lastAuthorizedCredential)" << std::endl;
+				r = -1;
+			} else {
+				path.push_back("member");
+				path.push_back(addrEntry);
+				ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"authorized\":false}",rb,rct);
+				if (join(nwe,byAddr,std::string(),0) != (int)NetworkController::NC_ERROR_ACCESS_DENIED) {
+					std::cout << "FAIL (deauthorized member authorized again)" << std::endl;
+					r = -1;
+				} else {
+					std::cout << "PASS" << std::endl;
+				}
+			}
+		}
+
+		std::cout << "[controller] Export of a network imports into a new network with its members... "; std::cout.flush();
+		{
+			char nwg[24],nwh[24];
+			nextNetworkId(nwg);
+			nextNetworkId(nwh);
+			path.clear();
+			path.push_back("network");
+			path.push_back(nwg);
+			ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"name\":\"exported\",\"private\":true}",rb,rct);
+			path.push_back("member");
+			path.push_back(memberId.address().toString(tmp));
+			ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"authorized\":true,\"ipAssignments\":[\"10.147.20.5\"]}",rb,rct);
+			path.resize(2);
+			path.push_back("export");
+			ctl.handleControlPlaneHttpGET(path,args,headers,"",rb,rct);
+			const std::string exported(rb);
+			path[1] = nwh;
+			path[2] = "import";
+			const unsigned int importStatus = ctl.handleControlPlaneHttpPOST(path,args,headers,exported,rb,rct);
+			const nlohmann::json res(OSUtils::jsonParse(rb));
+			const nlohmann::json m(getMember(nwh,memberId.address()));
+			path.resize(2);
+			ctl.handleControlPlaneHttpGET(path,args,headers,"",rb,rct);
+			const nlohmann::json n(OSUtils::jsonParse(rb));
+			if ((importStatus != 200)||(OSUtils::jsonInt(res["imported"],0ULL) != 1)||(!res["failed"].empty())) {
+				std::cout << "FAIL (import result " << importStatus << ")" << std::endl;
+				r = -1;
+			} else if (OSUtils::jsonString(n["name"],"") != "exported") {
+				std::cout << "FAIL (network settings not imported)" << std::endl;
+				r = -1;
+			} else if ((!OSUtils::jsonBool(m["authorized"],false))||(m["ipAssignments"] != nlohmann::json::array({ "10.147.20.5" }))) {
+				std::cout << "FAIL (member not imported)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		std::cout << "[controller] ZeroTier Central network and member objects are imported from their config... "; std::cout.flush();
+		{
+			char nwi[24];
+			nextNetworkId(nwi);
+			const std::string addrs(memberId.address().toString(tmp));
+			path.clear();
+			path.push_back("network");
+			path.push_back(nwi);
+			path.push_back("import");
+			const std::string central(std::string("{\"network\":{\"id\":\"8056c2e21c000001\",\"description\":\"x\",\"config\":{\"name\":\"central\",\"private\":true}},") +
+				"\"members\":[{\"id\":\"8056c2e21c000001-" + addrs + "\",\"nodeId\":\"" + addrs + "\",\"name\":\"m\",\"config\":{\"authorized\":true,\"ipAssignments\":[\"10.147.21.5\"]}}]}");
+			const unsigned int importStatus = ctl.handleControlPlaneHttpPOST(path,args,headers,central,rb,rct);
+			const nlohmann::json res(OSUtils::jsonParse(rb));
+			const nlohmann::json m(getMember(nwi,memberId.address()));
+			path.resize(2);
+			ctl.handleControlPlaneHttpGET(path,args,headers,"",rb,rct);
+			const nlohmann::json n(OSUtils::jsonParse(rb));
+			if ((importStatus != 200)||(OSUtils::jsonInt(res["imported"],0ULL) != 1)) {
+				std::cout << "FAIL (import result " << importStatus << ")" << std::endl;
+				r = -1;
+			} else if ((OSUtils::jsonString(n["name"],"") != "central")||(OSUtils::jsonString(n["id"],"") != nwi)) {
+				std::cout << "FAIL (network config not unwrapped)" << std::endl;
+				r = -1;
+			} else if ((!OSUtils::jsonBool(m["authorized"],false))||(m["ipAssignments"] != nlohmann::json::array({ "10.147.21.5" }))) {
+				std::cout << "FAIL (member config not unwrapped)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		std::cout << "[controller] History pages by sequence number and records network deletion... "; std::cout.flush();
+		char nwc[24];
+		nextNetworkId(nwc);
+		path.clear();
+		path.push_back("network");
+		path.push_back(nwc);
+		for(int i=0;i<20;++i) {
+			OSUtils::ztsnprintf(tmp,sizeof(tmp),"{\"name\":\"n%d\"}",i);
+			ctl.handleControlPlaneHttpPOST(path,args,headers,tmp,rb,rct);
+		}
+		ctl.handleControlPlaneHttpDELETE(path,args,headers,"",rb,rct);
+		path.push_back("history");
+		std::map<std::string,std::string> hargs;
+		hargs["limit"] = "3";
+		std::set<uint64_t> seqs;
+		unsigned int entries = 0;
+		bool deleted = false;
+		for(;;) {
+			ctl.handleControlPlaneHttpGET(path,hargs,headers,"",rb,rct);
+			const nlohmann::json page(OSUtils::jsonParse(rb));
+			if ((!page.is_array())||(page.empty()))
+				break;
+			for(auto h=page.begin();h!=page.end();++h) {
+				seqs.insert(OSUtils::jsonInt((*h)["seq"],0ULL));
+				++entries;
+				if (OSUtils::jsonString((*h)["op"],"") == "delete")
+					deleted = true;
+			}
+			OSUtils::ztsnprintf(tmp,sizeof(tmp),"%llu",(unsigned long long)OSUtils::jsonInt(page[0]["seq"],0ULL));
+			hargs["beforeSeq"] = tmp;
+		}
+		if ((entries != 21)||(seqs.size() != 21)) {
+			std::cout << "FAIL (" << entries << " entries, " << seqs.size() << " distinct)" << std::endl;
+			r = -1;
+		} else if (!deleted) {
+			std::cout << "FAIL (no delete entry)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+
+#ifdef __UNIX_LIKE__
+		std::cout << "[controller] History retention keeps the configured rotated logs and removes old logs of deleted networks... "; std::cout.flush();
+		const std::string oldLog(dbPath + ZT_PATH_SEPARATOR_S "history" ZT_PATH_SEPARATOR_S + nwc + ".log");
+		struct utimbuf ut;
+		ut.actime = ut.modtime = time((time_t *)0) - (31 * 86400);
+		utime(oldLog.c_str(),&ut);
+		char nwf[24],nwg[24];
+		nextNetworkId(nwf);
+		nextNetworkId(nwg);
+		path.clear();
+		path.push_back("network");
+		path.push_back(nwf);
+		ctl.handleControlPlaneHttpPOST(path,args,headers,"{\"name\":\"f\"}",rb,rct);
+		ctl.handleControlPlaneHttpDELETE(path,args,headers,"",rb,rct);
+		const bool keptOnDelete = OSUtils::fileExists(oldLog.c_str());
+
+		// A second controller on the same database picks up the retention settings and sweeps on its own
+		OSUtils::writeFile((dbPath + ZT_PATH_SEPARATOR_S "local.conf").c_str(),std::string("{\"settings\":{\"controllerHistory\":{\"rotateSize\":4096,\"keep\":2}}}"));
+		_Sender sender2;
+		EmbeddedNetworkController ctl2((Node *)0,dbPath.c_str(),dbPath.c_str(),0,(RedisConfig *)0);
+		ctl2.init(signingId,&sender2);
+		for(int i=0;((i<100)&&(OSUtils::fileExists(oldLog.c_str())));++i)
+			Thread::sleep(50);
+
+		const std::string gLog(dbPath + ZT_PATH_SEPARATOR_S "history" ZT_PATH_SEPARATOR_S + nwg + ".log");
+		path[1] = nwg;
+		for(int i=0;i<100;++i) {
+			OSUtils::ztsnprintf(tmp,sizeof(tmp),"{\"name\":\"g%d\"}",i);
+			ctl2.handleControlPlaneHttpPOST(path,args,headers,tmp,rb,rct);
+		}
+		path.push_back("history");
+		hargs.clear();
+		hargs["limit"] = "1000";
+		ctl2.handleControlPlaneHttpGET(path,hargs,headers,"",rb,rct);
+		const nlohmann::json gHistory(OSUtils::jsonParse(rb));
+		bool contiguous = ((gHistory.is_array())&&(gHistory.size() > 0)&&(OSUtils::jsonInt(gHistory[gHistory.size() - 1]["seq"],0ULL) >= 100));
+		for(unsigned long i=1;((contiguous)&&(i<gHistory.size()));++i)
+			contiguous = (OSUtils::jsonInt(gHistory[i]["seq"],0ULL) == (OSUtils::jsonInt(gHistory[i - 1]["seq"],0ULL) + 1));
+
+		if (!keptOnDelete) {
+			std::cout << "FAIL (logs expired by a network DELETE instead of the sweep)" << std::endl;
+			r = -1;
+		} else if (OSUtils::fileExists(oldLog.c_str())) {
+			std::cout << "FAIL (expired log kept)" << std::endl;
+			r = -1;
+		} else if (!OSUtils::fileExists((dbPath + ZT_PATH_SEPARATOR_S "history" ZT_PATH_SEPARATOR_S + nwf + ".log").c_str())) {
+			std::cout << "FAIL (recent log removed)" << std::endl;
+			r = -1;
+		} else if ((!OSUtils::fileExists((gLog + ".1").c_str()))||(!OSUtils::fileExists((gLog + ".2").c_str()))||(OSUtils::fileExists((gLog + ".3").c_str()))) {
+			std::cout << "FAIL (rotated logs not kept as configured)" << std::endl;
+			r = -1;
+		} else if ((!contiguous)||(gHistory.size() >= 100)) {
+			std::cout << "FAIL (history across rotated logs is not the newest " << gHistory.size() << " entries in order)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+#endif
+	}
+	OSUtils::rmDashRf(dbPath.c_str());
+
+	return r;
+}
+
 static int testPacket()
 {
 	unsigned char salsaKey[32];
@@ -899,8 +1293,1237 @@ static int testOther()
 	}
 	std::cout << "PASS (junk value to prevent optimization-out of test: " << foo << ")" << std::endl;
 
+	std::cout << "[other] Testing duration parsing... "; std::cout.flush();
+	if ((OSUtils::parseDuration("90d") != 7776000000LL)||(OSUtils::parseDuration("250ms") != 250)||(OSUtils::parseDuration("10",1000) != 10000)||
+	    (OSUtils::parseDuration("4294967296") != -1)||(OSUtils::parseDuration("5x") != -1)) {
+		std::cout << "FAILED (parseDuration)" << std::endl;
+		return -1;
+	}
+	if ((OSUtils::jsonDuration(OSUtils::jsonParse("\"12h\""),-1) != 43200000LL)||(OSUtils::jsonDuration(OSUtils::jsonParse("30"),-1,1000) != 30000)||
+	    (OSUtils::jsonDuration(OSUtils::jsonParse("4294967295"),-1,1000) != 4294967295000LL)||
+	    (OSUtils::jsonDuration(OSUtils::jsonParse("4294967296"),-1,1000) != -1)||
+	    (OSUtils::jsonDuration(OSUtils::jsonParse("9223372036854776"),-1,1000) != -1)) {
+		std::cout << "FAILED (jsonDuration)" << std::endl;
+		return -1;
+	}
+	std::cout << "PASS" << std::endl;
+
+	return 0;
+}
+
+// A core node that is never connected to anything: nothing is persisted and packets sent to the wire are only recorded
+static int _testNodeStateGet(ZT_Node *,void *,void *,enum ZT_StateObjectType,const uint64_t [2],void *,unsigned int) { return -1; }
+static void _testNodeStatePut(ZT_Node *,void *,void *,enum ZT_StateObjectType,const uint64_t [2],const void *,int) {}
+static void _testNodeFrame(ZT_Node *,void *,void *,uint64_t,void **,uint64_t,uint64_t,unsigned int,unsigned int,const void *,unsigned int) {}
+static int _testNodeConfig(ZT_Node *,void *,void *,uint64_t,void **,enum ZT_VirtualNetworkConfigOperation,const ZT_VirtualNetworkConfig *) { return 0; }
+static void _testNodeEvent(ZT_Node *,void *,void *,enum ZT_Event,const void *) {}
+static int _testNodeWireSend(ZT_Node *,void *uptr,void *,int64_t,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int)
+{
+	reinterpret_cast< std::vector< std::pair<InetAddress,std::string> > * >(uptr)->push_back(std::pair<InetAddress,std::string>(InetAddress(addr),std::string((const char *)data,len)));
 	return 0;
 }
+struct _TestNode
+{
+	_TestNode() :
+		node(_newNode(&sent)),
+		rr(node),
+		trace(&rr)
+	{
+		rr.t = &trace;
+		rr.identity = node->identity();
+	}
+	~_TestNode() { delete node; }
+
+	static Node *_newNode(std::vector< std::pair<InetAddress,std::string> > *sent)
+	{
+		struct ZT_Node_Callbacks cb;
+		memset(&cb,0,sizeof(cb));
+		cb.stateGetFunction = _testNodeStateGet;
+		cb.statePutFunction = _testNodeStatePut;
+		cb.wirePacketSendFunction = _testNodeWireSend;
+		cb.virtualNetworkFrameFunction = _testNodeFrame;
+		cb.virtualNetworkConfigFunction = _testNodeConfig;
+		cb.eventCallback = _testNodeEvent;
+		return new Node(sent,(void *)0,&cb,OSUtils::now());
+	}
+
+	std::vector< std::pair<InetAddress,std::string> > sent;
+	Node *const node;
+	RuntimeEnvironment rr; // peers made by a test use this, the node itself has its own
+	Trace trace;
+};
+
+// Has id say HELLO to the node from a physical address
+static void _testNodeHello(_TestNode &tn,const Identity &id,const uint8_t key[ZT_SYMMETRIC_KEY_SIZE],const InetAddress &from,const int64_t now)
+{
+	volatile int64_t nextDeadline = 0;
+	Packet hello(tn.node->identity().address(),id.address(),Packet::VERB_HELLO);
+	hello.append((unsigned char)ZT_PROTO_VERSION);
+	hello.append((unsigned char)ZEROTIER_ONE_VERSION_MAJOR);
+	hello.append((unsigned char)ZEROTIER_ONE_VERSION_MINOR);
+	hello.append((uint16_t)ZEROTIER_ONE_VERSION_REVISION);
+	hello.append((int64_t)now);
+	id.serialize(hello,false);
+	hello.armor(key,false,nullptr);
+	tn.node->processWirePacket((void *)0,now,1,reinterpret_cast<const struct sockaddr_storage *>(&from),hello.data(),hello.size(),&nextDeadline);
+}
+
+// Has id say HELLO to the node from a physical address, then answers the HELLO the node sends back, which confirms the direct path
+static void _testNodeLearnPeer(_TestNode &tn,const Identity &id,const uint8_t key[ZT_SYMMETRIC_KEY_SIZE],const InetAddress &from,const int64_t now)
+{
+	volatile int64_t nextDeadline = 0;
+	_testNodeHello(tn,id,key,from,now);
+	uint64_t helloId = 0;
+	for(std::vector< std::pair<InetAddress,std::string> >::const_iterator s(tn.sent.begin());s!=tn.sent.end();++s) {
+		const Packet pk(s->second.data(),(unsigned int)s->second.length());
+		if ((s->first == from)&&(pk.cipher() == ZT_PROTO_CIPHER_SUITE__C25519_POLY1305_NONE)&&(pk.verb() == Packet::VERB_HELLO))
+			helloId = pk.packetId();
+	}
+	Packet ok(tn.node->identity().address(),id.address(),Packet::VERB_OK);
+	ok.append((unsigned char)Packet::VERB_HELLO);
+	ok.append(helloId);
+	ok.append((int64_t)now);
+	ok.append((unsigned char)ZT_PROTO_VERSION);
+	ok.append((unsigned char)ZEROTIER_ONE_VERSION_MAJOR);
+	ok.append((unsigned char)ZEROTIER_ONE_VERSION_MINOR);
+	ok.append((uint16_t)ZEROTIER_ONE_VERSION_REVISION);
+	ok.armor(key,true,nullptr);
+	tn.node->processWirePacket((void *)0,now,1,reinterpret_cast<const struct sockaddr_storage *>(&from),ok.data(),ok.size(),&nextDeadline);
+}
+
+static int testPeer()
+{
+	_TestNode tn;
+	Identity pid;
+	pid.generate();
+	SharedPtr<Peer> peer(new Peer(&tn.rr,tn.rr.identity,pid));
+	const int64_t now = tn.node->now();
+	SharedPtr<Path> p4(new Path(1,InetAddress("1.2.3.4/9993")));
+	SharedPtr<Path> p6(new Path(1,InetAddress("2001:4860::1/9993")));
+	p4->received(now);
+	p6->received(now);
+	peer->received((void *)0,p4,0,1,0,Packet::VERB_OK,0,Packet::VERB_HELLO,false,0,-1);
+	peer->received((void *)0,p6,0,2,0,Packet::VERB_OK,0,Packet::VERB_HELLO,false,0,-1);
+	p4->updateLatency(100,now);
+	p6->updateLatency(96,now);
+
+	std::cout << "[peer] Best path does not flap between paths of similar latency... "; std::cout.flush();
+	if (peer->getAppropriatePath(now,false) != p6) {
+		std::cout << "FAIL (lower latency path not chosen at first)" << std::endl;
+		return -1;
+	}
+	unsigned int switches = 0;
+	SharedPtr<Path> last(p6);
+	for(unsigned int i=0;i<20;++i) {
+		// Latencies cross each other each round, but never by more than the hysteresis margin
+		p4->updateLatency((i & 1) ? 98 : 90,now);
+		p6->updateLatency((i & 1) ? 90 : 98,now);
+		const SharedPtr<Path> bp(peer->getAppropriatePath(now,false));
+		if (bp != last)
+			++switches;
+		last = bp;
+	}
+	if (switches != 0) {
+		std::cout << "FAIL (switched " << switches << " times)" << std::endl;
+		return -1;
+	}
+	for(unsigned int i=0;i<4;++i)
+		p4->updateLatency(20,now);
+	if (peer->getAppropriatePath(now,false) != p4) {
+		std::cout << "FAIL (clearly better path not chosen)" << std::endl;
+		return -1;
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] Preferred address family wins over lower latency... "; std::cout.flush();
+	// This peer is not in the node's topology, so it is handed the settings the node pushes to the peers it knows
+	auto prefer = [&](uint64_t address,int family) {
+		tn.node->setPeerPreferredFamily(address,family);
+		tn.node->applyPeerSettings(*peer);
+	};
+	prefer(pid.address().toInt(),AF_INET6);
+	if (peer->getAppropriatePath(now,false) != p6) {
+		std::cout << "FAIL (per-peer IPv6 preference ignored)" << std::endl;
+		return -1;
+	}
+	prefer(pid.address().toInt(),0);
+	if (peer->getAppropriatePath(now,false) != p4) {
+		std::cout << "FAIL (cleared preference still applied)" << std::endl;
+		return -1;
+	}
+	prefer(0,AF_INET6);
+	if (peer->getAppropriatePath(now,false) != p6) {
+		std::cout << "FAIL (node-wide IPv6 preference ignored)" << std::endl;
+		return -1;
+	}
+	prefer(0,0);
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] noRelay drops frames that reach us through a relay... "; std::cout.flush();
+	{
+		// pid says HELLO directly so the node learns it, then sends frames for a network the node has joined
+		// but has no config for yet. Each frame that gets past the relay check is counted as dropped there.
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		pid.agree(tn.node->identity(),key);
+		const InetAddress from("1.2.3.4/9993");
+		_testNodeHello(tn,pid,key,from,OSUtils::now());
+		volatile int64_t nextDeadline = 0;
+		const uint64_t nwid = 0x8056c2e21c000001ULL;
+		tn.node->join(nwid,(void *)0,(void *)0);
+		auto frame = [&](bool relayed) -> uint64_t {
+			Packet f(tn.node->identity().address(),pid.address(),Packet::VERB_FRAME);
+			f.append(nwid);
+			f.append((uint16_t)ZT_ETHERTYPE_IPV4);
+			for(unsigned int i=0;i<20;++i)
+				f.append((uint8_t)0);
+			f.armor(key,true,nullptr);
+			if (relayed)
+				f.incrementHops();
+			tn.node->processWirePacket((void *)0,OSUtils::now(),1,reinterpret_cast<const struct sockaddr_storage *>(&from),f.data(),f.size(),&nextDeadline);
+			ZT_VirtualNetworkStats st;
+			memset(&st,0,sizeof(st));
+			tn.node->networkStats(nwid,&st);
+			return st.rxDropped;
+		};
+		if (frame(true) != 1) {
+			std::cout << "FAIL (relayed frame dropped without noRelay)" << std::endl;
+			return -1;
+		}
+		tn.node->setPeerNoRelay(pid.address().toInt(),true);
+		if (frame(true) != 1) {
+			std::cout << "FAIL (relayed frame accepted with noRelay)" << std::endl;
+			return -1;
+		}
+		if (frame(false) != 2) {
+			std::cout << "FAIL (direct frame dropped with noRelay)" << std::endl;
+			return -1;
+		}
+		tn.node->setPeerNoRelay(pid.address().toInt(),false);
+		tn.node->setPeerNoRelay(0,true);
+		if (frame(true) != 2) {
+			std::cout << "FAIL (relayed frame accepted with node-wide noRelay)" << std::endl;
+			return -1;
+		}
+		tn.node->setPeerNoRelay(0,false);
+		if (frame(true) != 3) {
+			std::cout << "FAIL (relayed frame still dropped after noRelay was cleared)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] Idle direct paths get keepalives at the interval set for the peer or its network, except in low-power mode... "; std::cout.flush();
+	{
+		_TestNode tk;
+		Identity kid;
+		kid.generate();
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		kid.agree(tk.node->identity(),key);
+		const InetAddress from("5.6.7.8/9993");
+		int64_t t = OSUtils::now();
+		volatile int64_t nextDeadline = 0;
+
+		_testNodeLearnPeer(tk,kid,key,from,t);
+
+		// Runs the node for a while and returns the longest time the path to kid went without anything being sent over it
+		auto longestIdle = [&](int64_t duration) -> int64_t {
+			int64_t lastSend = t,longest = 0;
+			for(const int64_t end=t+duration;t<end;t+=250) {
+				tk.sent.clear();
+				tk.node->processBackgroundTasks((void *)0,t,&nextDeadline);
+				for(std::vector< std::pair<InetAddress,std::string> >::const_iterator s(tk.sent.begin());s!=tk.sent.end();++s) {
+					if (s->first == from) {
+						longest = std::max(longest,t - lastSend);
+						lastSend = t;
+					}
+				}
+			}
+			return std::max(longest,t - lastSend);
+		};
+		auto pathKeepalive = [&]() -> unsigned int {
+			unsigned int k = 0;
+			ZT_PeerList *pl = tk.node->peers();
+			for(unsigned long i=0;i<pl->peerCount;++i) {
+				if ((pl->peers[i].address == kid.address().toInt())&&(pl->peers[i].pathCount > 0))
+					k = pl->peers[i].paths[0].keepalive;
+			}
+			tk.node->freeQueryResult((void *)pl);
+			return k;
+		};
+
+		if ((pathKeepalive() != ZT_PATH_HEARTBEAT_PERIOD)||(longestIdle(10000) < 10000)) {
+			std::cout << "FAIL (idle path was kept alive without a keepalive set)" << std::endl;
+			return -1;
+		}
+
+		// A keepalive goes out once the path has been idle for the interval, and the check for that runs every half interval
+		tk.node->setPeerKeepalive(kid.address().toInt(),2000);
+		int64_t idle = longestIdle(10000);
+		if ((pathKeepalive() != 2000)||(idle < 2000)||(idle > 3000)) {
+			std::cout << "FAIL (path idle for " << idle << "ms with a 2s peer keepalive)" << std::endl;
+			return -1;
+		}
+
+		tk.node->setLowPower(true);
+		if ((pathKeepalive() != ZT_PATH_HEARTBEAT_PERIOD)||(longestIdle(10000) < 10000)) {
+			std::cout << "FAIL (keepalives sent in low-power mode)" << std::endl;
+			return -1;
+		}
+		if (tk.node->probePathMtu((void *)0,kid.address().toInt()) != ZT_RESULT_ERROR_UNSUPPORTED_OPERATION) {
+			std::cout << "FAIL (MTU probe started in low-power mode)" << std::endl;
+			return -1;
+		}
+		tk.node->setLowPower(false);
+
+		// kid is the controller of this network, so the network's keepalive applies to it
+		const uint64_t nwid = (kid.address().toInt() << 24) | 0x1ULL;
+		if ((tk.node->setNetworkKeepalive(nwid,3000) != ZT_RESULT_ERROR_NETWORK_NOT_FOUND)||(tk.node->join(nwid,(void *)0,(void *)0) != ZT_RESULT_OK)||
+		    (tk.node->setNetworkKeepalive(nwid,500) != ZT_RESULT_ERROR_BAD_PARAMETER)||(tk.node->setNetworkKeepalive(nwid,3000) != ZT_RESULT_OK)) {
+			std::cout << "FAIL (setNetworkKeepalive result)" << std::endl;
+			return -1;
+		}
+		if (pathKeepalive() != 2000) {
+			std::cout << "FAIL (network keepalive overrode the peer's own)" << std::endl;
+			return -1;
+		}
+		tk.node->setPeerKeepalive(kid.address().toInt(),0);
+		idle = longestIdle(10000);
+		if ((pathKeepalive() != 3000)||(idle < 3000)||(idle > 4500)) {
+			std::cout << "FAIL (path idle for " << idle << "ms with a 3s network keepalive)" << std::endl;
+			return -1;
+		}
+		tk.node->leave(nwid,(void **)0,(void *)0);
+		if ((pathKeepalive() != ZT_PATH_HEARTBEAT_PERIOD)||(longestIdle(10000) < 10000)) {
+			std::cout << "FAIL (network keepalive still applied after leaving)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] MTU probes find the largest size that gets through and flag black holes... "; std::cout.flush();
+	{
+		static const unsigned int sizes[ZT_PATH_MTU_PROBE_COUNT] = ZT_PATH_MTU_PROBE_SIZES;
+		_TestNode tm;
+		Identity mid;
+		mid.generate();
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		mid.agree(tm.node->identity(),key);
+		const InetAddress from("9.8.7.6/9993");
+		int64_t t = OSUtils::now();
+		volatile int64_t nextDeadline = 0;
+		_testNodeLearnPeer(tm,mid,key,from,t);
+
+		// Probe results as reported for mid's path: { maxSize, blackhole, done }
+		auto results = [&]() -> std::vector<unsigned int> {
+			std::vector<unsigned int> r(3,0xffffffff);
+			ZT_PeerList *pl = tm.node->peers();
+			for(unsigned long i=0;i<pl->peerCount;++i) {
+				if ((pl->peers[i].address == mid.address().toInt())&&(pl->peers[i].pathCount > 0)) {
+					r[0] = pl->peers[i].paths[0].mtuProbeMaxSize;
+					r[1] = (unsigned int)pl->peers[i].paths[0].mtuProbeBlackhole;
+					r[2] = (unsigned int)pl->peers[i].paths[0].mtuProbeDone;
+				}
+			}
+			tm.node->freeQueryResult((void *)pl);
+			return r;
+		};
+		// Starts a probe and runs the node for 20s, answering probes up to answerUpTo bytes; returns how often each size was sent
+		auto probe = [&](unsigned int answerUpTo,std::vector<unsigned int> &midway) -> std::map<unsigned int,unsigned int> {
+			std::map<unsigned int,unsigned int> tries;
+			auto answer = [&]() {
+				for(std::vector< std::pair<InetAddress,std::string> >::const_iterator s(tm.sent.begin());s!=tm.sent.end();++s) {
+					if ((s->first != from)||(std::find(sizes,sizes + ZT_PATH_MTU_PROBE_COUNT,(unsigned int)s->second.length()) == (sizes + ZT_PATH_MTU_PROBE_COUNT)))
+						continue;
+					++tries[(unsigned int)s->second.length()];
+					if (s->second.length() <= answerUpTo) {
+						Packet ok(tm.node->identity().address(),mid.address(),Packet::VERB_OK);
+						ok.append((unsigned char)Packet::VERB_ECHO);
+						ok.append(Packet(s->second.data(),(unsigned int)s->second.length()).packetId());
+						ok.armor(key,true,nullptr);
+						tm.node->processWirePacket((void *)0,t,1,reinterpret_cast<const struct sockaddr_storage *>(&from),ok.data(),ok.size(),&nextDeadline);
+					}
+				}
+				tm.sent.clear();
+			};
+			tm.sent.clear();
+			if (tm.node->probePathMtu((void *)0,mid.address().toInt()) != ZT_RESULT_OK)
+				return tries;
+			answer(); // the first probe goes out right away
+			const int64_t start = t;
+			while ((t += 250) < (start + 20000)) {
+				tm.node->processBackgroundTasks((void *)0,t,&nextDeadline);
+				answer();
+				if (t == (start + 8000)) // after the last first try, before it has timed out
+					midway = results();
+			}
+			return tries;
+		};
+
+		std::vector<unsigned int> midway;
+		std::map<unsigned int,unsigned int> tries(probe(0xffffffff,midway));
+		std::vector<unsigned int> r(results());
+		if (tries.size() != ZT_PATH_MTU_PROBE_COUNT) {
+			std::cout << "FAIL (" << tries.size() << " probe sizes sent)" << std::endl;
+			return -1;
+		}
+		for(std::map<unsigned int,unsigned int>::const_iterator k(tries.begin());k!=tries.end();++k) {
+			if (k->second != 1) {
+				std::cout << "FAIL (answered " << k->first << " byte probe sent " << k->second << " times)" << std::endl;
+				return -1;
+			}
+		}
+		if (midway[2] != 0) {
+			std::cout << "FAIL (done before the probes had time to be answered)" << std::endl;
+			return -1;
+		}
+		if ((r[0] != sizes[ZT_PATH_MTU_PROBE_COUNT - 1])||(r[1] != 0)||(r[2] != 1)) {
+			std::cout << "FAIL (all answered: max " << r[0] << " blackhole " << r[1] << " done " << r[2] << ")" << std::endl;
+			return -1;
+		}
+
+		// Everything above 1280 bytes is lost, as when IP fragments are dropped somewhere on the path
+		tries = probe(1280,midway);
+		r = results();
+		if ((tries[1280] != 1)||(tries[sizes[ZT_PATH_MTU_PROBE_COUNT - 1]] != ZT_PATH_MTU_PROBE_TRIES)||(tries[ZT_MIN_PHYSMTU] != ZT_PATH_MTU_PROBE_TRIES)) {
+			std::cout << "FAIL (lost probes not retried " << ZT_PATH_MTU_PROBE_TRIES << " times)" << std::endl;
+			return -1;
+		}
+		if ((midway[0] != 1280)||(midway[1] != 0)) {
+			std::cout << "FAIL (flagged as a black hole before done)" << std::endl;
+			return -1;
+		}
+		if ((r[0] != 1280)||(r[1] != 1)||(r[2] != 1)) {
+			std::cout << "FAIL (larger sizes lost: max " << r[0] << " blackhole " << r[1] << " done " << r[2] << ")" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] Housekeeping keeps the multicast groups of active bridged hosts and drops silent ones... "; std::cout.flush();
+	{
+		_TestNode tb;
+		const uint64_t nwid = 0x8056c2e21c000001ULL;
+		tb.node->join(nwid,(void *)0,(void *)0);
+		SharedPtr<Network> network(tb.node->network(nwid));
+		NetworkConfig *nc = new NetworkConfig();
+		nc->networkId = nwid;
+		nc->timestamp = 1;
+		nc->issuedTo = tb.node->identity().address();
+		nc->multicastLimit = 32;
+		nc->specialists[0] = tb.node->identity().address().toInt() | ZT_NETWORKCONFIG_SPECIALIST_TYPE_ACTIVE_BRIDGE;
+		nc->specialistCount = 1;
+		network->setConfiguration((void *)0,*nc,false);
+		delete nc;
+
+		// Two hosts behind our bridge each send to their own group, only the first keeps doing so
+		const MAC activeHost(0x020000000001ULL),silentHost(0x020000000002ULL);
+		const MulticastGroup activeGroup(MAC(0x01005e0000fbULL),0),silentGroup(MAC(0x01005e0000fcULL),0);
+		const uint8_t payload[64] = { 0 };
+		volatile int64_t nextDeadline = 0;
+		int64_t t = tb.node->now();
+		auto send = [&](const MAC &from,const MulticastGroup &to) {
+			tb.node->processVirtualNetworkFrame((void *)0,t,nwid,from.toInt(),to.mac().toInt(),0x88b5,0,payload,sizeof(payload),&nextDeadline);
+		};
+		send(activeHost,activeGroup);
+		send(silentHost,silentGroup);
+		const bool learned = ((network->subscribedToMulticastGroup(activeGroup,true))&&(network->subscribedToMulticastGroup(silentGroup,true)));
+		const int64_t end = t + (ZT_MULTICAST_LIKE_EXPIRE * 3);
+		while ((t += ZT_HOUSEKEEPING_PERIOD) < end) {
+			if ((t % (ZT_MULTICAST_LIKE_EXPIRE / 2)) < ZT_HOUSEKEEPING_PERIOD)
+				send(activeHost,activeGroup);
+			tb.node->processBackgroundTasks((void *)0,t,&nextDeadline);
+		}
+		if (!learned) {
+			std::cout << "FAIL (bridged groups not learned)" << std::endl;
+			return -1;
+		}
+		if (!network->subscribedToMulticastGroup(activeGroup,true)) {
+			std::cout << "FAIL (group of a still active bridged host dropped)" << std::endl;
+			return -1;
+		}
+		if (network->subscribedToMulticastGroup(silentGroup,true)) {
+			std::cout << "FAIL (group of a silent bridged host kept)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] RX queue size can be set until the first packet, TX queue size at any time... "; std::cout.flush();
+	{
+		_TestNode tq;
+		if ((tq.node->setQueueSizes(ZT_QUEUE_SIZE_MIN - 1,0) != ZT_RESULT_ERROR_BAD_PARAMETER)||(tq.node->setQueueSizes(0,ZT_QUEUE_SIZE_MAX + 1) != ZT_RESULT_ERROR_BAD_PARAMETER)) {
+			std::cout << "FAIL (out of range size accepted)" << std::endl;
+			return -1;
+		}
+		if (tq.node->setQueueSizes(256,16) != ZT_RESULT_OK) {
+			std::cout << "FAIL (sizes rejected before the first packet)" << std::endl;
+			return -1;
+		}
+
+		// A packet from an unknown peer waits in the RX queue for the WHOIS reply
+		Identity qid;
+		qid.generate();
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		qid.agree(tq.node->identity(),key);
+		Packet echo(tq.node->identity().address(),qid.address(),Packet::VERB_ECHO);
+		echo.armor(key,true,nullptr);
+		const InetAddress from("1.2.3.4/9993");
+		volatile int64_t nextDeadline = 0;
+		tq.node->processWirePacket((void *)0,tq.node->now(),1,reinterpret_cast<const struct sockaddr_storage *>(&from),echo.data(),echo.size(),&nextDeadline);
+
+		if (tq.node->setQueueSizes(64,64) != ZT_RESULT_ERROR_UNSUPPORTED_OPERATION) {
+			std::cout << "FAIL (RX queue resized after a packet was received)" << std::endl;
+			return -1;
+		}
+		if (tq.node->setQueueSizes(256,64) != ZT_RESULT_OK) {
+			std::cout << "FAIL (TX queue size not changed later)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] Panic forgets LEAF peers and keeps roots... "; std::cout.flush();
+	{
+		_TestNode tp;
+		Identity lid;
+		lid.generate();
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		lid.agree(tp.node->identity(),key);
+		_testNodeLearnPeer(tp,lid,key,InetAddress("5.6.7.8/9993"),tp.node->now());
+
+		// Returns { LEAF peers, other peers, whether lid is known }
+		auto census = [&]() -> std::vector<unsigned long> {
+			std::vector<unsigned long> c(3,0);
+			ZT_PeerList *pl = tp.node->peers();
+			for(unsigned long i=0;i<pl->peerCount;++i) {
+				++c[(pl->peers[i].role == ZT_PEER_ROLE_LEAF) ? 0 : 1];
+				if (pl->peers[i].address == lid.address().toInt())
+					c[2] = 1;
+			}
+			tp.node->freeQueryResult((void *)pl);
+			return c;
+		};
+
+		const std::vector<unsigned long> before(census());
+		const unsigned long forgotten = tp.node->forgetLeafPeers((void *)0);
+		const std::vector<unsigned long> after(census());
+		if ((before[0] != 1)||(before[1] == 0)||(before[2] != 1)) {
+			std::cout << "FAIL (setup: " << before[0] << " leaves, " << before[1] << " roots)" << std::endl;
+			return -1;
+		}
+		if ((forgotten != 1)||(after[0] != 0)||(after[2] != 0)) {
+			std::cout << "FAIL (" << forgotten << " forgotten, " << after[0] << " leaves left)" << std::endl;
+			return -1;
+		}
+		if ((after[1] != before[1])||(tp.node->forgetLeafPeers((void *)0) != 0)) {
+			std::cout << "FAIL (roots forgotten)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	std::cout << "[peer] Peer lists are filtered by role and activity before they are paged... "; std::cout.flush();
+	{
+		_TestNode tl;
+		Identity lid;
+		lid.generate();
+		uint8_t key[ZT_SYMMETRIC_KEY_SIZE];
+		lid.agree(tl.node->identity(),key);
+		const int64_t t = tl.node->now();
+		_testNodeLearnPeer(tl,lid,key,InetAddress("5.6.7.8/9993"),t);
+
+		// Addresses in a peer list, in order
+		auto addresses = [&](ZT_PeerList *pl) -> std::vector<uint64_t> {
+			std::vector<uint64_t> a;
+			if (pl) {
+				for(unsigned long i=0;i<pl->peerCount;++i)
+					a.push_back(pl->peers[i].address);
+				tl.node->freeQueryResult((void *)pl);
+			}
+			return a;
+		};
+
+		const std::vector<uint64_t> all(addresses(tl.node->peers()));
+		const std::vector<uint64_t> leaves(addresses(tl.node->peers(ZT_PEER_ROLE_LEAF,0,0,0)));
+		const std::vector<uint64_t> active(addresses(tl.node->peers(-1,t,0,0)));
+		const std::vector<uint64_t> later(addresses(tl.node->peers(-1,t + 1,0,0)));
+		const std::vector<uint64_t> page(addresses(tl.node->peers(-1,0,1,2)));
+		const std::vector<uint64_t> none(addresses(tl.node->peers(-2,0,0,0)));
+		if (all.size() < 3) {
+			std::cout << "FAIL (setup: " << all.size() << " peers)" << std::endl;
+			return -1;
+		}
+		if ((leaves.size() != 1)||(leaves[0] != lid.address().toInt())||(!none.empty())) {
+			std::cout << "FAIL (role filter returned " << leaves.size() << " LEAF peers, " << none.size() << " for no role)" << std::endl;
+			return -1;
+		}
+		if ((active != leaves)||(!later.empty())) {
+			std::cout << "FAIL (activeSince filter returned " << active.size() << " then " << later.size() << " peers)" << std::endl;
+			return -1;
+		}
+		if ((page.size() != 2)||(page[0] != all[1])||(page[1] != all[2])) {
+			std::cout << "FAIL (page of " << page.size() << " peers)" << std::endl;
+			return -1;
+		}
+	}
+	std::cout << "PASS" << std::endl;
+
+	return 0;
+}
+
+#ifdef __UNIX_LIKE__
+// Binds UDP sockets on a free port for IPv4 and IPv6 (if available) and returns the port, or 0 on failure
+static unsigned int _occupyPort(int &busy4,int &busy6)
+{
+	unsigned int busyPort = 0;
+	busy4 = ::socket(AF_INET,SOCK_DGRAM,0);
+	busy6 = ::socket(AF_INET6,SOCK_DGRAM,0);
+	struct sockaddr_in in4;
+	memset(&in4,0,sizeof(in4));
+	in4.sin_family = AF_INET;
+	socklen_t sl = sizeof(in4);
+	if ((::bind(busy4,(const struct sockaddr *)&in4,sizeof(in4)) == 0)&&(::getsockname(busy4,(struct sockaddr *)&in4,&sl) == 0))
+		busyPort = Utils::ntoh((uint16_t)in4.sin_port);
+	if (busy6 >= 0) {
+		int f = 1;
+		::setsockopt(busy6,IPPROTO_IPV6,IPV6_V6ONLY,(void *)&f,sizeof(f));
+		struct sockaddr_in6 in6;
+		memset(&in6,0,sizeof(in6));
+		in6.sin6_family = AF_INET6;
+		in6.sin6_port = in4.sin_port;
+		::bind(busy6,(const struct sockaddr *)&in6,sizeof(in6));
+	}
+	return busyPort;
+}
+#endif
+
+static int testService()
+{
+	char tmp[256];
+	OSUtils::ztsnprintf(tmp,sizeof(tmp),"zt-selftest-service-%.8x",(unsigned int)rand());
+	const std::string homePath(tmp);
+	OSUtils::rmDashRf(homePath.c_str());
+	int r = 0;
+
+#ifdef __UNIX_LIKE__
+	{
+		std::cout << "[service] Corrupt peers.d, networks.d, and moons.d state is set aside at startup... "; std::cout.flush();
+		const std::string peersPath(homePath + ZT_PATH_SEPARATOR_S "peers.d");
+		const std::string networksPath(homePath + ZT_PATH_SEPARATOR_S "networks.d");
+		const std::string moonsPath(homePath + ZT_PATH_SEPARATOR_S "moons.d");
+		OSUtils::mkdir(homePath);
+		OSUtils::mkdir(peersPath);
+		OSUtils::mkdir(networksPath);
+		OSUtils::mkdir(moonsPath);
+
+		// Enough of a cached peer to pass the check: format 1, identity, version fields and path count
+		Identity pid;
+		pid.generate();
+		Buffer<ZT_PEER_MAX_SERIALIZED_STATE_SIZE> pb;
+		pb.append((uint8_t)1);
+		pid.serialize(pb);
+		for(unsigned int i=0;i<10;++i)
+			pb.append((uint8_t)0);
+		OSUtils::ztsnprintf(tmp,sizeof(tmp),"%s" ZT_PATH_SEPARATOR_S "%.10llx.peer",peersPath.c_str(),(unsigned long long)pid.address().toInt());
+		const std::string goodPeer(tmp);
+		std::string goodPeerData((const char *)pb.data(),pb.size());
+		appendStateChecksum(goodPeerData);
+		OSUtils::writeFile(goodPeer.c_str(),goodPeerData);
+		const std::string badPeer(peersPath + ZT_PATH_SEPARATOR_S "0123456789.peer");
+		OSUtils::writeFile(badPeer.c_str(),std::string("\x01garbage"));
+
+		NetworkConfig *nc = new NetworkConfig();
+		nc->networkId = 0x8056c2e21c000001ULL;
+		nc->timestamp = 1;
+		nc->issuedTo = pid.address();
+		Utils::scopy(nc->name,sizeof(nc->name),"selftest");
+		Dictionary<ZT_NETWORKCONFIG_DICT_CAPACITY> *nd = new Dictionary<ZT_NETWORKCONFIG_DICT_CAPACITY>();
+		nc->toDictionary(*nd,false);
+		const std::string goodNetwork(networksPath + ZT_PATH_SEPARATOR_S "8056c2e21c000001.conf");
+		OSUtils::writeFile(goodNetwork.c_str(),nd->data(),nd->sizeBytes());
+		delete nd;
+		delete nc;
+		const std::string badNetwork(networksPath + ZT_PATH_SEPARATOR_S "8056c2e21c000002.conf");
+		OSUtils::writeFile(badNetwork.c_str(),std::string("not a network config"));
+		const std::string placeholder(networksPath + ZT_PATH_SEPARATOR_S "8056c2e21c000003.conf");
+		OSUtils::writeFile(placeholder.c_str(),std::string("\n"));
+
+		// A moon is not parsed at startup, so only its checksum can show that it changed on disk
+		std::string moonData("moon data");
+		appendStateChecksum(moonData);
+		moonData[0] = 'n';
+		const std::string badMoon(moonsPath + ZT_PATH_SEPARATOR_S "000000feedbeef01.moon");
+		OSUtils::writeFile(badMoon.c_str(),moonData);
+
+		// A write interrupted before its rename: the .tmp of a missing file is promoted, one next to its file is dropped
+		Identity tid;
+		tid.generate();
+		Buffer<ZT_PEER_MAX_SERIALIZED_STATE_SIZE> tb;
+		tb.append((uint8_t)1);
+		tid.serialize(tb);
+		for(unsigned int i=0;i<10;++i)
+			tb.append((uint8_t)0);
+		OSUtils::ztsnprintf(tmp,sizeof(tmp),"%s" ZT_PATH_SEPARATOR_S "%.10llx.peer",peersPath.c_str(),(unsigned long long)tid.address().toInt());
+		const std::string restoredPeer(tmp);
+		OSUtils::writeFile((restoredPeer + ".tmp").c_str(),tb.data(),tb.size());
+		const std::string staleTmp(goodNetwork + ".tmp");
+		OSUtils::writeFile(staleTmp.c_str(),std::string("half a write"));
+
+		// Run the real service on a port that is already taken with port fallback off: it repairs
+		// state at startup and then stops at the port check, before it joins any network or creates a tap.
+		OSUtils::writeFile((homePath + ZT_PATH_SEPARATOR_S "local.conf").c_str(),std::string("{\"settings\":{\"portFallback\":false}}"));
+		int busy4,busy6;
+		const unsigned int busyPort = _occupyPort(busy4,busy6);
+		OneService *const svc = OneService::newInstance(homePath.c_str(),busyPort);
+		const OneService::ReasonForTermination rft = (busyPort) ? svc->run() : OneService::ONE_STILL_RUNNING;
+		delete svc;
+		::close(busy4);
+		if (busy6 >= 0)
+			::close(busy6);
+
+		unsigned int peersSetAside = 0,networksSetAside = 0;
+		std::vector<std::string> files(OSUtils::listDirectory(peersPath.c_str()));
+		for(std::vector<std::string>::const_iterator f(files.begin());f!=files.end();++f) {
+			if (f->find("0123456789.peer.corrupt-") == 0)
+				++peersSetAside;
+		}
+		files = OSUtils::listDirectory(networksPath.c_str());
+		for(std::vector<std::string>::const_iterator f(files.begin());f!=files.end();++f) {
+			if (f->find(".corrupt-") != std::string::npos)
+				++networksSetAside;
+		}
+		unsigned int moonsSetAside = 0;
+		files = OSUtils::listDirectory(moonsPath.c_str());
+		for(std::vector<std::string>::const_iterator f(files.begin());f!=files.end();++f) {
+			if (f->find("000000feedbeef01.moon.corrupt-") == 0)
+				++moonsSetAside;
+		}
+		std::string badNetworkNow,placeholderNow;
+		OSUtils::readFile(badNetwork.c_str(),badNetworkNow);
+		OSUtils::readFile(placeholder.c_str(),placeholderNow);
+		if (rft != OneService::ONE_UNRECOVERABLE_ERROR) {
+			std::cout << "FAIL (service did not stop at the busy port)" << std::endl;
+			r = -1;
+		} else if ((!OSUtils::fileExists(goodPeer.c_str()))||(!OSUtils::fileExists(goodNetwork.c_str()))||(placeholderNow != "\n")) {
+			std::cout << "FAIL (valid state set aside)" << std::endl;
+			r = -1;
+		} else if ((OSUtils::fileExists(badPeer.c_str()))||(peersSetAside != 1)) {
+			std::cout << "FAIL (corrupt peer not set aside)" << std::endl;
+			r = -1;
+		} else if ((badNetworkNow != "\n")||(networksSetAside != 1)) {
+			std::cout << "FAIL (corrupt network config not replaced with a placeholder)" << std::endl;
+			r = -1;
+		} else if ((OSUtils::fileExists(badMoon.c_str()))||(moonsSetAside != 1)) {
+			std::cout << "FAIL (moon that does not match its checksum not set aside)" << std::endl;
+			r = -1;
+		} else if ((!OSUtils::fileExists(restoredPeer.c_str()))||(OSUtils::fileExists((restoredPeer + ".tmp").c_str()))||(OSUtils::fileExists(staleTmp.c_str()))) {
+			std::cout << "FAIL (interrupted writes not finished or discarded)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+#endif
+
+	{
+		std::cout << "[service] State checksums are checked and removed, and objects without one are read as they are... "; std::cout.flush();
+		const std::string plain("\x01some peer state");
+		std::string withSum(plain);
+		appendStateChecksum(withSum);
+		std::string stripped(withSum),unchanged(plain),damaged(withSum);
+		damaged[1] ^= 1;
+		const int strippedResult = stripStateChecksum(stripped);
+		const int unchangedResult = stripStateChecksum(unchanged);
+		const int damagedResult = stripStateChecksum(damaged);
+		if ((withSum.length() != (plain.length() + ZT_STATE_CHECKSUM_TRAILER_SIZE))||(strippedResult != 1)||(stripped != plain)) {
+			std::cout << "FAIL (checksum not removed)" << std::endl;
+			r = -1;
+		} else if ((unchangedResult != 0)||(unchanged != plain)) {
+			std::cout << "FAIL (object without a checksum changed)" << std::endl;
+			r = -1;
+		} else if (damagedResult != -1) {
+			std::cout << "FAIL (damaged object accepted)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+	{
+		std::cout << "[service] local.conf is checked the way the service reads it... "; std::cout.flush();
+		std::vector< std::pair<std::string,std::string> > emptyErrors,emptyWarnings,badErrors,badWarnings,jsonErrors,jsonWarnings;
+		const bool emptyOk = OneService::checkLocalConf(std::string("\n"),emptyErrors,emptyWarnings);
+		const bool badOk = OneService::checkLocalConf(std::string("{\"settings\":{\"portFallback\":1,\"noSuchSetting\":true,\"bind\":[\"10.0.0.1\"]}}"),badErrors,badWarnings);
+		const bool jsonOk = OneService::checkLocalConf(std::string("{\n  \"settings\": {,\n}"),jsonErrors,jsonWarnings);
+		if ((!emptyOk)||(!emptyErrors.empty())||(!emptyWarnings.empty())) {
+			std::cout << "FAIL (empty file rejected)" << std::endl;
+			r = -1;
+		} else if ((badOk)||(badErrors.size() != 1)||(badErrors[0].first != "settings.portFallback")||(badWarnings.size() != 1)||(badWarnings[0].first != "settings.noSuchSetting")) {
+			std::cout << "FAIL (wrong errors or warnings for settings)" << std::endl;
+			r = -1;
+		} else if ((jsonOk)||(jsonErrors.size() != 1)||(jsonErrors[0].second.find("line 2, column") != 0)) {
+			std::cout << "FAIL (invalid JSON not located)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+	{
+		std::cout << "[service] A config update that changes only routes leaves IPs and DNS alone... "; std::cout.flush();
+		ZT_VirtualNetworkConfig *oldc = new ZT_VirtualNetworkConfig();
+		memset(oldc,0,sizeof(ZT_VirtualNetworkConfig));
+		oldc->nwid = 0x8056c2e21c000001ULL;
+		oldc->assignedAddressCount = 1;
+		*reinterpret_cast<InetAddress *>(&(oldc->assignedAddresses[0])) = InetAddress("10.147.17.5/24");
+		oldc->routeCount = 1;
+		*reinterpret_cast<InetAddress *>(&(oldc->routes[0].target)) = InetAddress("10.147.17.0/24");
+		Utils::scopy(oldc->dns.domain,sizeof(oldc->dns.domain),"zt.example.com");
+		*reinterpret_cast<InetAddress *>(&(oldc->dns.server_addr[0])) = InetAddress("10.147.17.1/0");
+		ZT_VirtualNetworkConfig *newc = new ZT_VirtualNetworkConfig();
+		memcpy(newc,oldc,sizeof(ZT_VirtualNetworkConfig));
+		newc->netconfRevision = oldc->netconfRevision + 1;
+
+		bool ipsChanged = true,routesChanged = true,dnsChanged = true;
+		diffNetworkConfig(*oldc,*newc,ipsChanged,routesChanged,dnsChanged);
+		if ((ipsChanged)||(routesChanged)||(dnsChanged)) {
+			std::cout << "FAIL (identical config reported as changed)" << std::endl;
+			r = -1;
+		} else {
+			newc->routeCount = 2;
+			*reinterpret_cast<InetAddress *>(&(newc->routes[1].target)) = InetAddress("192.168.100.0/24");
+			*reinterpret_cast<InetAddress *>(&(newc->routes[1].via)) = InetAddress("10.147.17.1/0");
+			diffNetworkConfig(*oldc,*newc,ipsChanged,routesChanged,dnsChanged);
+			if ((ipsChanged)||(!routesChanged)||(dnsChanged)) {
+				std::cout << "FAIL (ips " << ipsChanged << " routes " << routesChanged << " dns " << dnsChanged << ")" << std::endl;
+				r = -1;
+			} else {
+				newc->routeCount = 1;
+				newc->routes[0].metric = 100;
+				diffNetworkConfig(*oldc,*newc,ipsChanged,routesChanged,dnsChanged);
+				if ((ipsChanged)||(!routesChanged)||(dnsChanged)) {
+					std::cout << "FAIL (route metric change not detected)" << std::endl;
+					r = -1;
+				} else {
+					std::cout << "PASS" << std::endl;
+				}
+			}
+		}
+		delete newc;
+		delete oldc;
+	}
+
+	{
+		std::cout << "[service] Traffic alerts fire on spikes, storms, and unknown ethertypes at most once a minute... "; std::cout.flush();
+		// Three 5s samples: a quiet 100 frames/s baseline, then whatever the latest interval adds
+		std::vector<NetworkStatsSample> samples(3);
+		const int64_t start = 1000000;
+		for(unsigned int i=0;i<3;++i) {
+			memset(&(samples[i].second),0,sizeof(ZT_VirtualNetworkStats));
+			samples[i].first = start + ((int64_t)i * 5000);
+		}
+		samples[1].second.rxPackets = 500;
+		const int64_t now = samples[2].first;
+		uint64_t rate = 0,avgRate = 0;
+
+		const char *quiet,*spike,*small,*multicast,*ethertype,*limited,*later;
+		samples[2].second.rxPackets = 1000;
+		quiet = trafficAlert(samples,10,1000,1000,100,0,now,rate,avgRate);
+		samples[2].second.rxPackets = 500 + (5 * 5000);
+		spike = trafficAlert(samples,10,1000,1000,100,0,now,rate,avgRate);
+		const uint64_t spikeRate = rate,spikeAvgRate = avgRate;
+		small = trafficAlert(samples,10,10000,1000,100,0,now,rate,avgRate);
+		samples[2].second.rxMulticast = 5 * 2000;
+		multicast = trafficAlert(samples,10,1000,1000,100,0,now,rate,avgRate);
+		samples[2].second.rxMulticast = 0;
+		samples[2].second.rxUnknownEtherType = 5 * 200;
+		ethertype = trafficAlert(samples,10,1000,1000,100,0,now,rate,avgRate);
+		limited = trafficAlert(samples,10,1000,1000,100,now - 59999,now,rate,avgRate);
+		later = trafficAlert(samples,10,1000,1000,100,now - 60000,now,rate,avgRate);
+
+		if (quiet) {
+			std::cout << "FAIL (alert '" << quiet << "' at the baseline rate)" << std::endl;
+			r = -1;
+		} else if ((!spike)||(strcmp(spike,"spike"))||(spikeRate != 5000)||(spikeAvgRate != 100)) {
+			std::cout << "FAIL (spike: " << (spike ? spike : "none") << " " << spikeRate << "/" << spikeAvgRate << " frames/s)" << std::endl;
+			r = -1;
+		} else if (small) {
+			std::cout << "FAIL (spike below minPacketsPerSecond alerted)" << std::endl;
+			r = -1;
+		} else if ((!multicast)||(strcmp(multicast,"multicast"))||(!ethertype)||(strcmp(ethertype,"ethertype"))) {
+			std::cout << "FAIL (multicast: " << (multicast ? multicast : "none") << ", ethertype: " << (ethertype ? ethertype : "none") << ")" << std::endl;
+			r = -1;
+		} else if (limited) {
+			std::cout << "FAIL (second alert within a minute was not suppressed)" << std::endl;
+			r = -1;
+		} else if ((!later)||(strcmp(later,"ethertype"))) {
+			std::cout << "FAIL (no alert a minute after the last one)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+	{
+		std::cout << "[service] API rate limit counts token holders under the token and others by source... "; std::cout.flush();
+		const std::string authToken("0123456789abcdefghijklm");
+		ApiRateLimiter rl;
+		rl.setLimit(3);
+		const std::string withoutToken(ApiRateLimiter::key(std::string(),authToken,"10.0.0.1"));
+		bool limitedAt[5];
+		for(int i=0;i<5;++i)
+			limitedAt[i] = rl.limited(withoutToken,10999);
+		const bool tokenLimited = rl.limited(ApiRateLimiter::key(authToken,authToken,"10.0.0.1"),10999);
+		const bool wrongTokenLimited = rl.limited(ApiRateLimiter::key(authToken.substr(1) + "x",authToken,"10.0.0.1"),10999);
+		const bool otherSourceLimited = rl.limited(ApiRateLimiter::key(std::string(),authToken,"10.0.0.2"),10999);
+		const bool nextSecondLimited = rl.limited(withoutToken,11000);
+		for(int i=0;i<3;++i)
+			rl.limited(ApiRateLimiter::key(authToken,authToken,"10.0.0.3"),12000);
+		const bool tokenElsewhereLimited = rl.limited(ApiRateLimiter::key(authToken,authToken,"10.0.0.4"),12000);
+		rl.setLimit(0);
+		if ((limitedAt[2])||(!limitedAt[3])||(!limitedAt[4])) {
+			std::cout << "FAIL (not limited after 3 requests)" << std::endl;
+			r = -1;
+		} else if (tokenLimited) {
+			std::cout << "FAIL (token holder limited by callers without the token)" << std::endl;
+			r = -1;
+		} else if (!wrongTokenLimited) {
+			std::cout << "FAIL (wrong token counted apart)" << std::endl;
+			r = -1;
+		} else if (otherSourceLimited) {
+			std::cout << "FAIL (sources without the token share a count)" << std::endl;
+			r = -1;
+		} else if (nextSecondLimited) {
+			std::cout << "FAIL (count not reset in the next second)" << std::endl;
+			r = -1;
+		} else if (!tokenElsewhereLimited) {
+			std::cout << "FAIL (token holders at different sources counted apart)" << std::endl;
+			r = -1;
+		} else if (rl.limited(withoutToken,12000)) {
+			std::cout << "FAIL (limit of 0 still limits)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+	{
+		std::cout << "[service] Management network listeners take only members of that network... "; std::cout.flush();
+		const InetAddress assigned[2] = { InetAddress("10.147.17.5/24"),InetAddress("fd00:1234::5/64") };
+		InetAddress local("10.147.17.5/9993"),local6("fd00:1234::5/9993");
+		if (!ManagementAuth::fromNetwork(assigned,2,local,InetAddress("10.147.17.9/40000"))) {
+			std::cout << "FAIL (member rejected)" << std::endl;
+			r = -1;
+		} else if (!ManagementAuth::fromNetwork(assigned,2,local6,InetAddress("fd00:1234::9/40000"))) {
+			std::cout << "FAIL (IPv6 member rejected)" << std::endl;
+			r = -1;
+		} else if (ManagementAuth::fromNetwork(assigned,2,local,InetAddress("192.168.1.9/40000"))) {
+			std::cout << "FAIL (non-management address accepted)" << std::endl;
+			r = -1;
+		} else if (ManagementAuth::fromNetwork(assigned,2,local,InetAddress("fd00:1234::9/40000"))) {
+			std::cout << "FAIL (member of another management subnet accepted on this address)" << std::endl;
+			r = -1;
+		} else if (ManagementAuth::fromNetwork(assigned,2,InetAddress(),InetAddress("10.147.17.9/40000"))) {
+			std::cout << "FAIL (accepted on a listener outside the management network)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+	{
+		std::cout << "[service] Management network requests must be signed by a pinned identity... "; std::cout.flush();
+		Identity admin,other;
+		admin.generate();
+		other.generate();
+		char idstr[ZT_IDENTITY_STRING_BUFFER_LENGTH];
+		std::vector<Identity> pinned;
+		pinned.push_back(Identity(admin.toString(false,idstr)));
+		ManagementAuth ma;
+		ma.setPinned(pinned);
+		const int64_t now = 1600000000000LL;
+		const std::string body("{\"allowManaged\":false}");
+		const std::string sig(ManagementAuth::sign(admin,"POST","/network/8056c2e21c000001",now - 1000,body));
+		const bool unsigned_ = ma.verify(std::string(),"POST","/network/8056c2e21c000001",body,now);
+		const bool otherSigned = ma.verify(ManagementAuth::sign(other,"POST","/network/8056c2e21c000001",now - 999,body),"POST","/network/8056c2e21c000001",body,now);
+		const bool changedBody = ma.verify(sig,"POST","/network/8056c2e21c000001",std::string("{}"),now);
+		const bool changedUrl = ma.verify(sig,"POST","/network/8056c2e21c000002",body,now);
+		const bool stale = ma.verify(ManagementAuth::sign(admin,"GET","/status",now - (ZT_MANAGEMENT_AUTH_MAX_CLOCK_SKEW + 1),std::string()),"GET","/status",std::string(),now);
+		const bool valid = ma.verify(sig,"POST","/network/8056c2e21c000001",body,now);
+		const bool replayed = ma.verify(sig,"POST","/network/8056c2e21c000001",body,now);
+		const bool next = ma.verify(ManagementAuth::sign(admin,"GET","/status",now,std::string()),"GET","/status",std::string(),now);
+		if ((unsigned_)||(otherSigned)) {
+			std::cout << "FAIL (accepted without a pinned identity's signature)" << std::endl;
+			r = -1;
+		} else if ((changedBody)||(changedUrl)) {
+			std::cout << "FAIL (signature accepted for a different request)" << std::endl;
+			r = -1;
+		} else if (stale) {
+			std::cout << "FAIL (stale signature accepted)" << std::endl;
+			r = -1;
+		} else if ((!valid)||(!next)) {
+			std::cout << "FAIL (valid signature rejected)" << std::endl;
+			r = -1;
+		} else if (replayed) {
+			std::cout << "FAIL (replayed signature accepted)" << std::endl;
+			r = -1;
+		} else {
+			std::cout << "PASS" << std::endl;
+		}
+	}
+
+#ifdef __UNIX_LIKE__
+	{
+		std::cout << "[service] The API Unix socket accepts only root and the service's user... "; std::cout.flush();
+		struct sockaddr_un sun;
+		memset(&sun,0,sizeof(sun));
+		sun.sun_family = AF_UNIX;
+		OSUtils::ztsnprintf(sun.sun_path,sizeof(sun.sun_path),"/tmp/zt-selftest-%.8x.sock",(unsigned int)rand());
+		const int ls = socket(AF_UNIX,SOCK_STREAM,0);
+		if ((ls < 0)||(bind(ls,(const struct sockaddr *)&sun,sizeof(sun)) != 0)||(chmod(sun.sun_path,0777) != 0)||(listen(ls,4) != 0)) {
+			std::cout << "FAIL (cannot listen on " << sun.sun_path << ")" << std::endl;
+			r = -1;
+		} else {
+			const int cs = socket(AF_UNIX,SOCK_STREAM,0);
+			connect(cs,(const struct sockaddr *)&sun,sizeof(sun));
+			int as = accept(ls,(struct sockaddr *)0,(socklen_t *)0);
+			const bool selfAllowed = OSUtils::unixPeerIsRootOrSelf(as);
+			::close(as);
+			::close(cs);
+
+			// Another user connects from a child process, which needs root to switch to it
+			bool otherAllowed = false;
+			const bool asRoot = (geteuid() == 0);
+			if (asRoot) {
+				const pid_t pid = fork();
+				if (pid == 0) {
+					if ((setgid(65534) != 0)||(setuid(65534) != 0))
+						_exit(1);
+					const int os = socket(AF_UNIX,SOCK_STREAM,0);
+					if (connect(os,(const struct sockaddr *)&sun,sizeof(sun)) != 0)
+						_exit(1);
+					char c;
+					(void)::read(os,&c,1); // wait for the parent to hang up
+					_exit(0);
+				}
+				as = accept(ls,(struct sockaddr *)0,(socklen_t *)0);
+				otherAllowed = OSUtils::unixPeerIsRootOrSelf(as);
+				::close(as);
+				int status = 0;
+				waitpid(pid,&status,0);
+			}
+
+			if (!selfAllowed) {
+				std::cout << "FAIL (our own user rejected)" << std::endl;
+				r = -1;
+			} else if (otherAllowed) {
+				std::cout << "FAIL (another user accepted)" << std::endl;
+				r = -1;
+			} else if (!asRoot) {
+				std::cout << "PASS (another user not tried, needs root)" << std::endl;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+		if (ls >= 0)
+			::close(ls);
+		OSUtils::rm(sun.sun_path);
+	}
+#endif
+
+	{
+		// A running service driven through its local HTTP API. On Unix its primary port is taken, so it falls back to a nearby one.
+		const std::string svcPath(homePath + ZT_PATH_SEPARATOR_S "running");
+		OSUtils::mkdir(homePath);
+		OSUtils::mkdir(svcPath);
+		OSUtils::writeFile((svcPath + ZT_PATH_SEPARATOR_S "local.conf").c_str(),std::string("{\"settings\":{\"apiAuditLog\":true,\"apiUnixSocket\":true,\"portMappingEnabled\":false,\"allowTcpFallbackRelay\":false}}"));
+		unsigned int busyPort = 0;
+#ifdef __UNIX_LIKE__
+		int busy4,busy6;
+		busyPort = _occupyPort(busy4,busy6);
+#endif
+		OneService *const svc = OneService::newInstance(svcPath.c_str(),busyPort);
+		std::thread svcThread([svc]() { svc->run(); });
+		std::string portStr,authToken;
+		for(int i=0;((i<600)&&(!OSUtils::readFile((svcPath + ZT_PATH_SEPARATOR_S "zerotier-one.port").c_str(),portStr)));++i)
+			Thread::sleep(50);
+		OSUtils::readFile((svcPath + ZT_PATH_SEPARATOR_S "authtoken.secret").c_str(),authToken);
+		InetAddress apiAddr("127.0.0.1/0");
+		apiAddr.setPort(Utils::strToUInt(portStr.c_str()));
+		auto api = [&](const char *method,const std::string &path,const std::string &body,const std::string &token,std::string &responseBody) -> unsigned int {
+			std::map<std::string,std::string> requestHeaders,responseHeaders;
+			requestHeaders["Host"] = "127.0.0.1";
+			if (token.length() > 0)
+				requestHeaders["X-ZT1-Auth"] = token;
+			responseBody.clear();
+			if (!strcmp(method,"POST")) {
+				requestHeaders["Content-Type"] = "application/json";
+				char cl[24];
+				OSUtils::ztsnprintf(cl,sizeof(cl),"%lu",(unsigned long)body.length());
+				requestHeaders["Content-Length"] = cl;
+				return Http::POST(1024 * 1024,10000,(const struct sockaddr *)&apiAddr,path.c_str(),requestHeaders,body.data(),(unsigned long)body.length(),responseHeaders,responseBody);
+			}
+			if (!strcmp(method,"DELETE"))
+				return Http::DEL(1024 * 1024,10000,(const struct sockaddr *)&apiAddr,path.c_str(),requestHeaders,responseHeaders,responseBody);
+			return Http::GET(1024 * 1024,10000,(const struct sockaddr *)&apiAddr,path.c_str(),requestHeaders,responseHeaders,responseBody);
+		};
+
+		std::cout << "[service] A taken primary port falls back to a nearby one and /status reports both... "; std::cout.flush();
+		if ((portStr.empty())||(authToken.empty())) {
+			std::cout << "FAIL (service did not start)" << std::endl;
+			r = -1;
+		} else if (!busyPort) {
+			std::cout << "PASS (skipped, no port could be taken here)" << std::endl;
+		} else {
+			std::string rb;
+			nlohmann::json st;
+			try {
+				if (api("GET","/status",std::string(),authToken,rb) == 200)
+					st = OSUtils::jsonParse(rb);
+			} catch ( ... ) {}
+			const unsigned int port = Utils::strToUInt(portStr.c_str());
+			if ((port <= busyPort)||(port > (busyPort + 16))) {
+				std::cout << "FAIL (bound " << port << " for taken port " << busyPort << ")" << std::endl;
+				r = -1;
+			} else if ((OSUtils::jsonInt(st["primaryPort"],0ULL) != port)||(OSUtils::jsonInt(st["requestedPrimaryPort"],0ULL) != busyPort)) {
+				std::cout << "FAIL (/status: " << rb << ")" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		std::cout << "[service] GET /peer with limit=0 returns every peer... "; std::cout.flush();
+		if ((portStr.empty())||(authToken.empty())) {
+			std::cout << "FAIL (service did not start)" << std::endl;
+			r = -1;
+		} else {
+			std::string rb;
+			auto peerCount = [&](const char *path) -> long {
+				if (api("GET",path,std::string(),authToken,rb) != 200)
+					return -1;
+				try {
+					return (long)OSUtils::jsonParse(rb).size();
+				} catch ( ... ) {
+					return -1;
+				}
+			};
+			const long all = peerCount("/peer");
+			if (all < 2) {
+				std::cout << "FAIL (expected the planet's roots, got " << all << " peers)" << std::endl;
+				r = -1;
+			} else if (peerCount("/peer?limit=0") != all) {
+				std::cout << "FAIL (limit=0 returned " << peerCount("/peer?limit=0") << " of " << all << " peers)" << std::endl;
+				r = -1;
+			} else if ((peerCount("/peer?limit=1") != 1)||(peerCount("/peer?offset=1&limit=0") != (all - 1))) {
+				std::cout << "FAIL (limit or offset ignored)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		std::cout << "[service] Low-power mode is switched on and off through /status... "; std::cout.flush();
+		if ((portStr.empty())||(authToken.empty())) {
+			std::cout << "FAIL (service did not start)" << std::endl;
+			r = -1;
+		} else {
+			std::string rb;
+			auto lowPower = [&]() -> bool {
+				api("GET","/status",std::string(),authToken,rb);
+				try {
+					return OSUtils::jsonBool(OSUtils::jsonParse(rb)["lowPower"],false);
+				} catch ( ... ) {
+					return false;
+				}
+			};
+			if (lowPower()) {
+				std::cout << "FAIL (on without lowPower in local.conf)" << std::endl;
+				r = -1;
+			} else if ((api("POST","/status","{\"lowPower\":true}",authToken,rb) != 200)||(!lowPower())) {
+				std::cout << "FAIL (not turned on)" << std::endl;
+				r = -1;
+			} else if (api("POST","/peer/89e92ceee5/mtuprobe",std::string(),authToken,rb) != 503) {
+				std::cout << "FAIL (MTU probe not refused)" << std::endl;
+				r = -1;
+			} else if ((api("POST","/status","{\"lowPower\":\"no\"}",authToken,rb) != 400)||(!lowPower())) {
+				std::cout << "FAIL (non-boolean lowPower accepted)" << std::endl;
+				r = -1;
+			} else if ((api("POST","/status","{\"lowPower\":false}",authToken,rb) != 200)||(lowPower())) {
+				std::cout << "FAIL (not turned off)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+#ifdef __UNIX_LIKE__
+		std::cout << "[service] The API Unix socket answers our own user without a token... "; std::cout.flush();
+		if ((portStr.empty())||(authToken.empty())) {
+			std::cout << "FAIL (service did not start)" << std::endl;
+			r = -1;
+		} else {
+			struct sockaddr_un sun;
+			memset(&sun,0,sizeof(sun));
+			sun.sun_family = AF_UNIX;
+			OSUtils::ztsnprintf(sun.sun_path,sizeof(sun.sun_path),"%s" ZT_PATH_SEPARATOR_S "zerotier-one.sock",svcPath.c_str());
+			std::string response;
+			const int us = socket(AF_UNIX,SOCK_STREAM,0);
+			if (connect(us,(const struct sockaddr *)&sun,sizeof(sun)) == 0) {
+				const char *const req = "GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n";
+				if (::write(us,req,strlen(req)) == (ssize_t)strlen(req)) {
+					char buf[4096];
+					ssize_t n;
+					while ((n = ::read(us,buf,sizeof(buf))) > 0)
+						response.append(buf,(size_t)n);
+				}
+			}
+			::close(us);
+			if (response.compare(0,12,"HTTP/1.1 200") != 0) {
+				std::cout << "FAIL (" << response.substr(0,response.find('\r')) << ")" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+#endif
+
+		std::cout << "[service] API changes reach the audit log from its writer thread... "; std::cout.flush();
+		if ((portStr.empty())||(authToken.empty())) {
+			std::cout << "FAIL (service did not start)" << std::endl;
+			r = -1;
+		} else {
+			std::string rb,audit;
+			api("POST","/status","{\"lowPower\":false}",authToken + "x",rb);
+			for(int i=0;((i<100)&&(audit.find(" POST /status 401 ") == std::string::npos));++i) {
+				Thread::sleep(20);
+				audit.clear();
+				OSUtils::readFile((svcPath + ZT_PATH_SEPARATOR_S "api-audit.log").c_str(),audit);
+			}
+			if (audit.find(" POST /status 401 ") == std::string::npos) {
+				std::cout << "FAIL (rejected call not logged)" << std::endl;
+				r = -1;
+			} else if (audit.find(" GET /status 200 ") != std::string::npos) {
+				std::cout << "FAIL (plain reads logged)" << std::endl;
+				r = -1;
+			} else {
+				std::cout << "PASS" << std::endl;
+			}
+		}
+
+		svc->terminate();
+		svcThread.join();
+		delete svc;
+#ifdef __UNIX_LIKE__
+		::close(busy4);
+		if (busy6 >= 0)
+			::close(busy6);
+#endif
+	}
+
+	OSUtils::rmDashRf(homePath.c_str());
+	return r;
+}
 
 #define ZT_TEST_PHY_NUM_UDP_PACKETS 10000
 #define ZT_TEST_PHY_UDP_PACKET_SIZE 1000
@@ -1114,6 +2737,9 @@ int main(int argc,char **argv)
 	r |= testIdentity();
 	r |= testCertificate();
 	r |= testPhy();
+	r |= testPeer();
+	r |= testController();
+	r |= testService();
 	//*/
 
 	if (r)
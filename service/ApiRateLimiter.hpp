@@ -0,0 +1,97 @@
+/*
+ * Copyright (c)2019 ZeroTier, Inc.
+ *
+ * Use of this software is governed by the Business Source License included
+ * in the LICENSE.TXT file in the project's root directory.
+ *
+ * Change Date: 2025-01-01
+ *
+ * On the date above, in accordance with the Business Source License, use
+ * of this software will be governed by version 2.0 of the Apache License.
+ */
+/****/
+
+#ifndef ZT_APIRATELIMITER_HPP
+#define ZT_APIRATELIMITER_HPP
+
+#include <stdint.h>
+
+#include <string>
+#include <map>
+
+#include "../node/Utils.hpp"
+
+namespace ZeroTier {
+
+/**
+ * Counts local API requests per caller in each second (settings.apiRateLimit)
+ *
+ * The time is passed to each call rather than read here so tests can run
+ * the limiter on their own clock. Not thread safe.
+ */
+class ApiRateLimiter
+{
+public:
+	ApiRateLimiter() : _limit(0) {}
+
+	/**
+	 * Get the key a request is counted under
+	 *
+	 * Requests presenting the auth token are counted under the token, so a
+	 * token holder has one count wherever it calls from and clients without
+	 * the token can't use it up. Others are counted by source. Counting them
+	 * by the token they present would let a caller start a new count with
+	 * each made-up token, so a wrong token counts as no token.
+	 *
+	 * @param presentedToken Token sent with the request or empty if none
+	 * @param authToken Service's auth token
+	 * @param source Source IP as a string, or "unix" for the API Unix socket
+	 * @return Key for limited()
+	 */
+	static inline std::string key(const std::string &presentedToken,const std::string &authToken,const std::string &source)
+	{
+		if ((presentedToken.length() > 0)&&(presentedToken.length() == authToken.length())&&(Utils::secureEq(presentedToken.data(),authToken.data(),(unsigned int)presentedToken.length())))
+			return std::string("token");
+		return std::string("source:") + source;
+	}
+
+	/**
+	 * @param limit Requests allowed per caller and second, or 0 for no limit
+	 */
+	inline void setLimit(const unsigned int limit) { _limit = limit; }
+
+	/**
+	 * Count a request and check it against the limit
+	 *
+	 * @param key Caller from key()
+	 * @param now Current time in ms
+	 * @return True if the caller is over the limit in this second
+	 */
+	inline bool limited(const std::string &key,const int64_t now)
+	{
+		if (!_limit)
+			return false;
+		const int64_t sec = now / 1000;
+		if (_counts.size() > 1024) {
+			for(std::map< std::string,std::pair<int64_t,unsigned int> >::iterator c(_counts.begin());c!=_counts.end();) {
+				if (c->second.first != sec)
+					_counts.erase(c++);
+				else ++c;
+			}
+		}
+		std::pair<int64_t,unsigned int> &c = _counts[key];
+		if (c.first != sec) {
+			c.first = sec;
+			c.second = 0;
+		}
+		return (++c.second > _limit);
+	}
+
+private:
+	std::map< std::string,std::pair<int64_t,unsigned int> > _counts; // key -> (second, requests)
+	unsigned int _limit;
+};
+
+} // namespace ZeroTier
+
+#endif
@@ -0,0 +1,160 @@
+/*
+ * Copyright (c)2019 ZeroTier, Inc.
+ *
+ * Use of this software is governed by the Business Source License included
+ * in the LICENSE.TXT file in the project's root directory.
+ *
+ * Change Date: 2025-01-01
+ *
+ * On the date above, in accordance with the Business Source License, use
+ * of this software will be governed by version 2.0 of the Apache License.
+ */
+/****/
+
+#ifndef ZT_MANAGEMENTAUTH_HPP
+#define ZT_MANAGEMENTAUTH_HPP
+
+#include <stdint.h>
+#include <stdlib.h>
+
+#include <string>
+#include <vector>
+#include <map>
+
+#include "../node/Identity.hpp"
+#include "../node/InetAddress.hpp"
+#include "../node/Utils.hpp"
+#include "../osdep/OSUtils.hpp"
+
+// Signatures more than this far from our clock are rejected (ms)
+#define ZT_MANAGEMENT_AUTH_MAX_CLOCK_SKEW 30000
+
+namespace ZeroTier {
+
+/**
+ * Client authentication for the management API on management networks
+ *
+ * Requests that reach us on one of our addresses in a management network
+ * (settings.managementNetworks) must come from within that network and be
+ * signed by one of the identities pinned in settings.managementIdentities,
+ * in addition to carrying the auth token. The signature is sent as:
+ *
+ *   X-ZT1-Signature: <address>:<timestamp in ms>:<hex signature>
+ *
+ * It covers message() for the request. Each identity's timestamps must
+ * increase, so a captured request can't be replayed. Not thread safe.
+ */
+class ManagementAuth
+{
+public:
+	/**
+	 * Check if a connection to one of our addresses came from within the same network
+	 *
+	 * @param assigned Our assigned addresses in a management network, with netmask bits
+	 * @param assignedCount Number of assigned addresses
+	 * @param localAddr Address the connection was accepted on, or nil if not on a management listener
+	 * @param remoteAddr Address the connection came from
+	 * @return True if the connection may use the management listener
+	 */
+	static inline bool fromNetwork(const InetAddress *assigned,const unsigned int assignedCount,const InetAddress &localAddr,const InetAddress &remoteAddr)
+	{
+		if (!localAddr)
+			return false;
+		for(unsigned int i=0;i<assignedCount;++i) {
+			if ((assigned[i].ipsEqual(localAddr))&&(assigned[i].containsAddress(remoteAddr)))
+				return true;
+		}
+		return false;
+	}
+
+	/**
+	 * @return Data a client signs for a request
+	 */
+	static inline std::string message(const char *method,const std::string &url,const int64_t timestamp,const std::string &body)
+	{
+		char tmp[64];
+		OSUtils::ztsnprintf(tmp,sizeof(tmp)," %lld\n",(long long)timestamp);
+		return (std::string(method) + " " + url + tmp + body);
+	}
+
+	/**
+	 * Make a signature header value for a request
+	 *
+	 * @param id Client identity with its private key
+	 * @return Value for X-ZT1-Signature
+	 */
+	static inline std::string sign(const Identity &id,const char *method,const std::string &url,const int64_t timestamp,const std::string &body)
+	{
+		const std::string m(message(method,url,timestamp,body));
+		const C25519::Signature sig(id.sign(m.data(),(unsigned int)m.length()));
+		char addr[16],hexbuf[(ZT_C25519_SIGNATURE_LEN * 2) + 1],tmp[256];
+		OSUtils::ztsnprintf(tmp,sizeof(tmp),"%s:%lld:%s",id.address().toString(addr),(long long)timestamp,Utils::hex(sig.data,ZT_C25519_SIGNATURE_LEN,hexbuf));
+		return std::string(tmp);
+	}
+
+	/**
+	 * @param pinned Identities whose signatures are accepted
+	 */
+	inline void setPinned(const std::vector<Identity> &pinned)
+	{
+		_pinned = pinned;
+		for(std::map<uint64_t,int64_t>::iterator l(_lastTimestamp.begin());l!=_lastTimestamp.end();) {
+			bool keep = false;
+			for(std::vector<Identity>::const_iterator id(_pinned.begin());id!=_pinned.end();++id) {
+				if (id->address().toInt() == l->first) {
+					keep = true;
+					break;
+				}
+			}
+			if (keep)
+				++l;
+			else _lastTimestamp.erase(l++);
+		}
+	}
+
+	inline bool hasPinned() const { return (!_pinned.empty()); }
+
+	/**
+	 * Check a request's signature header
+	 *
+	 * @param header Value of X-ZT1-Signature
+	 * @param now Current time in ms
+	 * @return True if signed by a pinned identity within the allowed clock skew and not replayed
+	 */
+	inline bool verify(const std::string &header,const char *method,const std::string &url,const std::string &body,const int64_t now)
+	{
+		const std::size_t c1 = header.find(':');
+		const std::size_t c2 = (c1 == std::string::npos) ? std::string::npos : header.find(':',c1 + 1);
+		if ((c1 != ZT_ADDRESS_LENGTH_HEX)||(c2 == std::string::npos))
+			return false;
+		const uint64_t addr = Utils::hexStrToU64(header.substr(0,c1).c_str());
+		const int64_t timestamp = (int64_t)strtoll(header.substr(c1 + 1,c2 - (c1 + 1)).c_str(),(char **)0,10);
+		if ((timestamp <= 0)||(timestamp > (now + ZT_MANAGEMENT_AUTH_MAX_CLOCK_SKEW))||(timestamp < (now - ZT_MANAGEMENT_AUTH_MAX_CLOCK_SKEW)))
+			return false;
+
+		for(std::vector<Identity>::const_iterator id(_pinned.begin());id!=_pinned.end();++id) {
+			if (id->address().toInt() == addr) {
+				std::map<uint64_t,int64_t>::const_iterator l(_lastTimestamp.find(addr));
+				if ((l != _lastTimestamp.end())&&(timestamp <= l->second))
+					return false;
+				char sig[ZT_C25519_SIGNATURE_LEN + 1];
+				if (Utils::unhex(header.c_str() + c2 + 1,sig,sizeof(sig)) != ZT_C25519_SIGNATURE_LEN)
+					return false;
+				const std::string m(message(method,url,timestamp,body));
+				if (!id->verify(m.data(),(unsigned int)m.length(),sig,ZT_C25519_SIGNATURE_LEN))
+					return false;
+				_lastTimestamp[addr] = timestamp;
+				return true;
+			}
+		}
+		return false;
+	}
+
+private:
+	std::vector<Identity> _pinned;
+	std::map<uint64_t,int64_t> _lastTimestamp; // address -> last accepted signature timestamp
+};
+
+} // namespace ZeroTier
+
+#endif
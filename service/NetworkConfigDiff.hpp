@@ -0,0 +1,62 @@
+/*
+ * Copyright (c)2019 ZeroTier, Inc.
+ *
+ * Use of this software is governed by the Business Source License included
+ * in the LICENSE.TXT file in the project's root directory.
+ *
+ * Change Date: 2025-01-01
+ *
+ * On the date above, in accordance with the Business Source License, use
+ * of this software will be governed by version 2.0 of the Apache License.
+ */
+/****/
+
+#ifndef ZT_NETWORKCONFIGDIFF_HPP
+#define ZT_NETWORKCONFIGDIFF_HPP
+
+#include <string.h>
+
+#include "../include/ZeroTierOne.h"
+#include "../node/InetAddress.hpp"
+
+namespace ZeroTier {
+
+/**
+ * Determine which parts of a network's managed OS state differ between two configs
+ *
+ * Only what changed is reapplied on a config update, so a new netconf
+ * revision doesn't tear down unchanged IPs, routes, or DNS.
+ *
+ * @param oldc Config currently applied
+ * @param newc New config
+ * @param ipsChanged Set true if assigned addresses differ
+ * @param routesChanged Set true if managed routes differ
+ * @param dnsChanged Set true if DNS settings differ
+ */
+static inline void diffNetworkConfig(const ZT_VirtualNetworkConfig &oldc,const ZT_VirtualNetworkConfig &newc,bool &ipsChanged,bool &routesChanged,bool &dnsChanged)
+{
+	ipsChanged = (oldc.assignedAddressCount != newc.assignedAddressCount);
+	for(unsigned int i=0;((!ipsChanged)&&(i<newc.assignedAddressCount));++i) {
+		if (*reinterpret_cast<const InetAddress *>(&(oldc.assignedAddresses[i])) != *reinterpret_cast<const InetAddress *>(&(newc.assignedAddresses[i])))
+			ipsChanged = true;
+	}
+
+	routesChanged = (oldc.routeCount != newc.routeCount);
+	for(unsigned int i=0;((!routesChanged)&&(i<newc.routeCount));++i) {
+		if ( (*reinterpret_cast<const InetAddress *>(&(oldc.routes[i].target)) != *reinterpret_cast<const InetAddress *>(&(newc.routes[i].target))) ||
+		     (*reinterpret_cast<const InetAddress *>(&(oldc.routes[i].via)) != *reinterpret_cast<const InetAddress *>(&(newc.routes[i].via))) ||
+		     (oldc.routes[i].flags != newc.routes[i].flags) ||
+		     (oldc.routes[i].metric != newc.routes[i].metric) )
+			routesChanged = true;
+	}
+
+	dnsChanged = (strncmp(oldc.dns.domain,newc.dns.domain,sizeof(newc.dns.domain)) != 0);
+	for(int j=0;((!dnsChanged)&&(j<ZT_MAX_DNS_SERVERS));++j) {
+		if (*reinterpret_cast<const InetAddress *>(&(oldc.dns.server_addr[j])) != *reinterpret_cast<const InetAddress *>(&(newc.dns.server_addr[j])))
+			dnsChanged = true;
+	}
+}
+
+} // namespace ZeroTier
+
+#endif
@@ -23,7 +23,9 @@
 #include <list>
 #include <thread>
 #include <mutex>
+#include <atomic>
 #include <condition_variable>
+#include <memory>
 
 #include "../version.h"
 #include "../include/ZeroTierOne.h"
@@ -41,6 +43,8 @@
 #include "../node/SHA512.hpp"
 #include "../node/Bond.hpp"
 #include "../node/Peer.hpp"
+#include "../node/NetworkConfig.hpp"
+#include "../node/AtomicCounter.hpp"
 
 #include "../osdep/Phy.hpp"
 #include "../osdep/OSUtils.hpp"
@@ -53,6 +57,11 @@
 
 #include "OneService.hpp"
 #include "SoftwareUpdater.hpp"
+#include "ApiRateLimiter.hpp"
+#include "ManagementAuth.hpp"
+#include "NetworkConfigDiff.hpp"
+#include "TrafficAlert.hpp"
+#include "StateChecksum.hpp"
 
 #ifdef __WINDOWS__
 #include <WinSock2.h>
@@ -68,9 +77,16 @@
 #include <sys/stat.h>
 #include <sys/wait.h>
 #include <unistd.h>
+#include <fcntl.h>
+#include <poll.h>
+#include <signal.h>
 #include <ifaddrs.h>
 #endif
 
+#ifdef __LINUX__
+#include <sys/syscall.h>
+#endif
+
 #ifdef __APPLE__
 #include "../osdep/MacDNSHelper.hpp"
 #elif defined(__WINDOWS__)
@@ -136,6 +152,76 @@ using json = nlohmann::json;
 // TCP activity timeout
 #define ZT_TCP_ACTIVITY_TIMEOUT 60000
 
+// Default size at which api-audit.log is rotated (settings.apiAuditLogMaxSize)
+#define ZT_API_AUDIT_LOG_DEFAULT_MAX_SIZE 1048576
+
+// Default and maximum number of rotated audit logs kept (settings.apiAuditLogKeep)
+#define ZT_API_AUDIT_LOG_DEFAULT_KEEP 1
+#define ZT_API_AUDIT_LOG_MAX_KEEP 100
+
+// Audit lines waiting to be written before new ones are dropped
+#define ZT_API_AUDIT_LOG_MAX_QUEUED 4096
+
+// Default time in ms after which unused peers.d entries are deleted (settings.peerCacheRetention)
+#define ZT_PEER_CACHE_RETENTION_DEFAULT 2592000000LL
+
+// Default time in ms after which a core callback is reported as slow (settings.slowCallbackWarning)
+#define ZT_SLOW_CALLBACK_DEFAULT 500
+
+// Minimum delay between slow callback warnings of the same kind
+#define ZT_SLOW_CALLBACK_WARNING_INTERVAL 60000
+
+// Unix domain socket for the JSON API in the home path (settings.apiUnixSocket)
+#define ZT_API_UNIX_SOCKET_NAME "zerotier-one.sock"
+
+// Number of ports after the configured primary port to try if it is in use (settings.portFallback)
+#define ZT_PRIMARY_PORT_FALLBACK_RANGE 16
+
+// Default range for automatically chosen secondary and tertiary ports (settings.secondaryPortRange)
+#define ZT_SECONDARY_PORT_RANGE_FIRST 20000
+#define ZT_SECONDARY_PORT_RANGE_LAST 65535
+
+// Number of recent trace events kept for GET /trace
+#define ZT_TRACE_BUFFER_SIZE 1024
+
+// How often to sample per-network traffic counters, and the window over which rates are computed
+#define ZT_NETWORK_STATS_SAMPLE_INTERVAL 5000
+#define ZT_NETWORK_STATS_WINDOW 30000
+
+// Number of recent traffic alerts listed in GET /status
+#define ZT_TRAFFIC_ALERT_LOG_SIZE 64
+
+// Only one in this many wire packet send and frame delivery callbacks is timed (must be a power of two)
+#define ZT_SLOW_CALLBACK_SAMPLE_RATE 256
+
+// How long metadata from settings.pathMetadataCommand is cached for an address
+#define ZT_PATH_METADATA_CACHE_TTL 3600000
+
+// How long to wait before asking the command again about an address it could not describe
+#define ZT_PATH_METADATA_RETRY_DELAY 300000
+
+// Time the metadata command may run for one address before it is killed
+#define ZT_PATH_METADATA_COMMAND_TIMEOUT 5000
+
+// Maximum size of the metadata command's output
+#define ZT_PATH_METADATA_MAX_OUTPUT 65536
+
+// Maximum number of addresses with cached (or pending) command metadata
+#define ZT_PATH_METADATA_CACHE_MAX 4096
+
+// Core callbacks timed for the slow callback watchdog (per-packet callbacks are sampled)
+enum ZT_ServiceCallback
+{
+	ZT_SERVICE_CALLBACK_NETWORK_CONFIG = 0,
+	ZT_SERVICE_CALLBACK_EVENT = 1,
+	ZT_SERVICE_CALLBACK_STATE_PUT = 2,
+	ZT_SERVICE_CALLBACK_STATE_GET = 3,
+	ZT_SERVICE_CALLBACK_WIRE_PACKET_SEND = 4,
+	ZT_SERVICE_CALLBACK_VIRTUAL_NETWORK_FRAME = 5,
+	ZT_SERVICE_CALLBACK__COUNT = 6
+};
+static const char *const ZT_SERVICE_CALLBACK_NAMES[ZT_SERVICE_CALLBACK__COUNT] = { "networkConfig","event","statePut","stateGet","wirePacketSend","virtualNetworkFrame" };
+
 #if ZT_VAULT_SUPPORT
 size_t curlResponseWrite(void *ptr, size_t size, size_t nmemb, std::string *data)
 {
@@ -172,6 +258,244 @@ static std::string _trimString(const std::string &s)
 	return s.substr(start,end - start);
 }
 
+// "ipv4", "ipv6", or "any" (or absent) from local.conf to AF_INET, AF_INET6, or 0; -1 if invalid
+static int _preferFamilyFromJson(const nlohmann::json &jv)
+{
+	const std::string f(OSUtils::jsonString(jv,"any"));
+	if (f == "ipv4")
+		return AF_INET;
+	if (f == "ipv6")
+		return AF_INET6;
+	if (f == "any")
+		return 0;
+	return -1;
+}
+
+// Parse local.conf, an empty file is an empty configuration
+static bool _parseLocalConf(const std::string &buf,json &lc,std::string &err)
+{
+	if (buf.find_first_not_of(" \t\r\n") == std::string::npos) {
+		lc = json::object();
+		return true;
+	}
+	try {
+		lc = OSUtils::jsonParse(buf);
+	} catch (json::parse_error &e) {
+		unsigned long line = 1,col = 1;
+		for(std::size_t i=0;((i+1)<e.byte)&&(i<buf.length());++i) {
+			if (buf[i] == '\n') {
+				++line;
+				col = 1;
+			} else ++col;
+		}
+		err = "line " + std::to_string(line) + ", column " + std::to_string(col) + ": invalid JSON: " + e.what();
+		return false;
+	} catch ( ... ) {
+		err = "invalid JSON";
+		return false;
+	}
+	if (!lc.is_object()) {
+		err = "root element is not a JSON object";
+		return false;
+	}
+	return true;
+}
+
+// Check parsed local.conf for values the service rejects or ignores, as (where, what)
+static void _checkLocalConf(json lc,std::vector< std::pair<std::string,std::string> > &errors,std::vector< std::pair<std::string,std::string> > &warnings)
+{
+	auto error = [&errors](const std::string &where,const char *what) {
+		errors.push_back(std::pair<std::string,std::string>(where,what));
+	};
+	auto warning = [&warnings](const std::string &where,const char *what) {
+		warnings.push_back(std::pair<std::string,std::string>(where,what));
+	};
+	auto isNetwork = [](const std::string &s) -> bool {
+		const InetAddress a(s.c_str());
+		return (((a.ss_family == AF_INET)||(a.ss_family == AF_INET6))&&(a.netmaskBitsValid()));
+	};
+	auto isPreferFamily = [](const json &j) -> bool {
+		return ((j == "ipv4")||(j == "ipv6")||(j == "any"));
+	};
+	auto isHexId = [](const std::string &s,unsigned int len) -> bool {
+		return ((s.length() == len)&&(s.find_first_not_of("0123456789abcdefABCDEF") == std::string::npos)&&(Utils::hexStrToU64(s.c_str()) != 0));
+	};
+	auto checkArray = [&](json &j,const std::string &where,bool (*valid)(const json &),const char *what) {
+		if (j.is_null())
+			return;
+		if (!j.is_array()) {
+			error(where,"must be an array");
+			return;
+		}
+		for(unsigned long i=0;i<j.size();++i) {
+			if (!valid(j[i]))
+				error(where + "[" + std::to_string(i) + "]",what);
+		}
+	};
+
+	for(json::iterator k(lc.begin());k!=lc.end();++k) {
+		if ((k.key() != "physical")&&(k.key() != "virtual")&&(k.key() != "settings"))
+			warning(k.key(),"unknown section (ignored)");
+	}
+
+	json &physical = lc["physical"];
+	if (physical.is_object()) {
+		for(json::iterator p(physical.begin());p!=physical.end();++p) {
+			const std::string where("physical." + p.key());
+			if (!isNetwork(p.key()))
+				error(where,"key must be a network in IP/bits form");
+			if (!p.value().is_object()) {
+				error(where,"must be an object");
+				continue;
+			}
+			if ((p.value().count("blacklist"))&&(!p.value()["blacklist"].is_boolean()))
+				error(where + ".blacklist","must be true or false");
+			if ((p.value().count("trustedPathId"))&&(!p.value()["trustedPathId"].is_number_unsigned()))
+				error(where + ".trustedPathId","must be a non-negative integer");
+			if ((p.value().count("mtu"))&&(!p.value()["mtu"].is_number_unsigned()))
+				error(where + ".mtu","must be a non-negative integer");
+			if ((p.value().count("metadata"))&&(!p.value()["metadata"].is_object()))
+				error(where + ".metadata","must be an object");
+		}
+	} else if (!physical.is_null()) error("physical","must be an object");
+
+	json &virt = lc["virtual"];
+	if (virt.is_object()) {
+		for(json::iterator v(virt.begin());v!=virt.end();++v) {
+			const std::string where("virtual." + v.key());
+			if (!isHexId(v.key(),ZT_ADDRESS_LENGTH_HEX))
+				error(where,"key must be a 10-digit ZeroTier address");
+			if (!v.value().is_object()) {
+				error(where,"must be an object");
+				continue;
+			}
+			checkArray(v.value()["try"],where + ".try",[](const json &j) -> bool { return ((j.is_string())&&(InetAddress(j.get<std::string>().c_str()).port() != 0)); },"must be IP/port");
+			checkArray(v.value()["pin"],where + ".pin",[](const json &j) -> bool { return ((j.is_string())&&(InetAddress(j.get<std::string>().c_str()).port() != 0)); },"must be IP/port");
+			checkArray(v.value()["blacklist"],where + ".blacklist",[](const json &j) -> bool { return ((j.is_string())&&(InetAddress(j.get<std::string>().c_str()).netmaskBitsValid())); },"must be a network in IP/bits form");
+			if ((v.value().count("name"))&&(!v.value()["name"].is_string()))
+				error(where + ".name","must be a string");
+			if ((v.value().count("keepalive"))&&((OSUtils::jsonDuration(v.value()["keepalive"],-1) < 1000)||(OSUtils::jsonDuration(v.value()["keepalive"],-1) > 14000)))
+				error(where + ".keepalive","must be a duration from 1000 to 14000 ms");
+			if ((v.value().count("noRelay"))&&(!v.value()["noRelay"].is_boolean()))
+				error(where + ".noRelay","must be true or false");
+			if ((v.value().count("relayAlert"))&&(OSUtils::jsonDuration(v.value()["relayAlert"],-1) < 0))
+				error(where + ".relayAlert","must be milliseconds or a duration such as \"10m\" (0 to disable)");
+			if ((v.value().count("preferFamily"))&&(!isPreferFamily(v.value()["preferFamily"])))
+				error(where + ".preferFamily","must be \"ipv4\", \"ipv6\", or \"any\"");
+			if ((v.value().count("dscp"))&&((!v.value()["dscp"].is_number_unsigned())||(OSUtils::jsonInt(v.value()["dscp"],0ULL) > 63)))
+				error(where + ".dscp","must be a DSCP value from 0 to 63");
+		}
+	} else if (!virt.is_null()) error("virtual","must be an object");
+
+	json &settings = lc["settings"];
+	if (settings.is_object()) {
+		for(json::iterator s(settings.begin());s!=settings.end();++s) {
+			bool known = false;
+			for(unsigned int i=0;OneService::knownSettings[i];++i) {
+				if (s.key() == OneService::knownSettings[i]) {
+					known = true;
+					break;
+				}
+			}
+			if (!known)
+				warning("settings." + s.key(),"unknown setting (ignored)");
+		}
+
+		static const char *const ports[] = { "primaryPort","secondaryPort","tertiaryPort",(const char *)0 };
+		for(unsigned int i=0;ports[i];++i) {
+			if ((settings.count(ports[i]))&&((!settings[ports[i]].is_number_unsigned())||(settings[ports[i]] > 65535)))
+				error(std::string("settings.") + ports[i],"must be a port number from 0 to 65535");
+		}
+		static const char *const bools[] = { "portFallback","allowSecondaryPort","portMappingEnabled","allowTcpFallbackRelay","ipv6Only","noRelay","lowPower","softwareUpdateDist","apiAuditLog","apiUnixSocket","stateChecksums",(const char *)0 };
+		for(unsigned int i=0;bools[i];++i) {
+			if ((settings.count(bools[i]))&&(!settings[bools[i]].is_boolean()))
+				error(std::string("settings.") + bools[i],"must be true or false");
+		}
+		if (settings.count("trafficAlert")) {
+			json &ta = settings["trafficAlert"];
+			if (ta.is_object()) {
+				static const char *const taKeys[] = { "spikeFactor","minPacketsPerSecond","multicastPacketsPerSecond","unknownEtherTypePacketsPerSecond",(const char *)0 };
+				for(json::iterator k(ta.begin());k!=ta.end();++k) {
+					bool known = false;
+					for(unsigned int i=0;taKeys[i];++i) {
+						if (k.key() == taKeys[i])
+							known = true;
+					}
+					if (!known)
+						warning("settings.trafficAlert." + k.key(),"unknown setting (ignored)");
+					else if (!k.value().is_number_unsigned())
+						error("settings.trafficAlert." + k.key(),"must be a non-negative number");
+					else if (((k.key() == "spikeFactor")||(k.key() == "minPacketsPerSecond"))&&(k.value().get<uint64_t>() == 0))
+						error("settings.trafficAlert." + k.key(),"must be at least 1");
+				}
+			} else error("settings.trafficAlert","must be an object");
+		}
+		if (settings.count("secondaryPortMode")) {
+			const std::string spm(OSUtils::jsonString(settings["secondaryPortMode"],""));
+			if ((spm != "fixed")&&(spm != "random")&&(spm != "disabled"))
+				error("settings.secondaryPortMode","must be \"fixed\", \"random\", or \"disabled\"");
+		}
+		if (settings.count("secondaryPortRange")) {
+			json &spr = settings["secondaryPortRange"];
+			if ((!spr.is_array())||(spr.size() != 2)||(!spr[0].is_number_unsigned())||(!spr[1].is_number_unsigned())||(spr[0] < 1)||(spr[0] > spr[1])||(spr[1] > 65535))
+				error("settings.secondaryPortRange","must be [ first,last ] with 1 <= first <= last <= 65535");
+		}
+		if (settings.count("slowCallbackWarning")) {
+			const int64_t scw = OSUtils::jsonDuration(settings["slowCallbackWarning"],-1);
+			if (scw < 0)
+				error("settings.slowCallbackWarning","must be milliseconds or a duration such as \"2s\" (0 to disable)");
+			else if (scw > 0xffffffffLL)
+				error("settings.slowCallbackWarning","must be under 49 days");
+		}
+		if (settings.count("peerIdleTimeout")) {
+			const int64_t pit = OSUtils::jsonDuration(settings["peerIdleTimeout"],-1);
+			if ((pit != 0)&&((pit < ZT_PEER_IDLE_TIMEOUT_MIN)||(pit > ZT_PEER_IDLE_TIMEOUT_MAX)))
+				error("settings.peerIdleTimeout","must be a duration from 30s to 7d (0 for the default)");
+		}
+		if ((settings.count("peerCacheRetention"))&&(OSUtils::jsonDuration(settings["peerCacheRetention"],-1) <= 0))
+			error("settings.peerCacheRetention","must be a duration such as \"30d\"");
+		if ((settings.count("dscp"))&&((!settings["dscp"].is_number_unsigned())||(OSUtils::jsonInt(settings["dscp"],0ULL) > 63)))
+			error("settings.dscp","must be a DSCP value from 0 to 63");
+		if ((settings.count("rootDscp"))&&((!settings["rootDscp"].is_number_unsigned())||(OSUtils::jsonInt(settings["rootDscp"],0ULL) > 63)))
+			error("settings.rootDscp","must be a DSCP value from 0 to 63");
+		if (settings.count("aqmMaxEnqueuedPackets")) {
+			const uint64_t aqm = OSUtils::jsonInt(settings["aqmMaxEnqueuedPackets"],0ULL);
+			if ((!settings["aqmMaxEnqueuedPackets"].is_number_unsigned())||((aqm)&&((aqm < ZT_AQM_MAX_ENQUEUED_PACKETS_MIN)||(aqm > ZT_AQM_MAX_ENQUEUED_PACKETS_MAX))))
+				error("settings.aqmMaxEnqueuedPackets","must be from 16 to 1048576 (0 for the default)");
+		}
+		static const char *const queueSizes[] = { "rxQueueSize","txQueueSize",(const char *)0 };
+		for(unsigned int q=0;queueSizes[q];++q) {
+			if (settings.count(queueSizes[q])) {
+				const uint64_t qs = OSUtils::jsonInt(settings[queueSizes[q]],0ULL);
+				if ((!settings[queueSizes[q]].is_number_unsigned())||((qs)&&((qs < ZT_QUEUE_SIZE_MIN)||(qs > ZT_QUEUE_SIZE_MAX))))
+					error(std::string("settings.") + queueSizes[q],"must be from 8 to 65536 (0 for the default)");
+			}
+		}
+		if ((settings.count("pathMetadataCommand"))&&(OSUtils::jsonString(settings["pathMetadataCommand"],"").c_str()[0] != '/'))
+			error("settings.pathMetadataCommand","must be the absolute path of an executable");
+		if ((settings.count("apiRateLimit"))&&(!settings["apiRateLimit"].is_number_unsigned()))
+			error("settings.apiRateLimit","must be a number of requests per second");
+		if ((settings.count("apiAuditLogMaxSize"))&&(!settings["apiAuditLogMaxSize"].is_number_unsigned()))
+			error("settings.apiAuditLogMaxSize","must be a size in bytes");
+		if ((settings.count("apiAuditLogKeep"))&&((!settings["apiAuditLogKeep"].is_number_unsigned())||(OSUtils::jsonInt(settings["apiAuditLogKeep"],0ULL) > 100)))
+			error("settings.apiAuditLogKeep","must be a number of rotated logs from 0 to 100");
+		if (settings.count("softwareUpdate")) {
+			const std::string up(OSUtils::jsonString(settings["softwareUpdate"],""));
+			if ((up != "apply")&&(up != "download")&&(up != "disable"))
+				error("settings.softwareUpdate","must be \"apply\", \"download\", or \"disable\"");
+		}
+		checkArray(settings["interfacePrefixBlacklist"],"settings.interfacePrefixBlacklist",[](const json &j) -> bool { return j.is_string(); },"must be a string");
+		checkArray(settings["interfacePrefixWhitelist"],"settings.interfacePrefixWhitelist",[](const json &j) -> bool { return j.is_string(); },"must be a string");
+		checkArray(settings["allowManagementFrom"],"settings.allowManagementFrom",[](const json &j) -> bool { return ((j.is_string())&&(InetAddress(j.get<std::string>().c_str()).netmaskBitsValid())); },"must be a network in IP/bits form");
+		checkArray(settings["bind"],"settings.bind",[](const json &j) -> bool { const InetAddress a((j.is_string()) ? j.get<std::string>().c_str() : ""); return ((a.ss_family == AF_INET)||(a.ss_family == AF_INET6)); },"must be an IP address");
+		checkArray(settings["managementNetworks"],"settings.managementNetworks",[](const json &j) -> bool { return ((j.is_string())&&(j.get<std::string>().length() == 16)&&(j.get<std::string>().find_first_not_of("0123456789abcdefABCDEF") == std::string::npos)); },"must be a 16-digit network ID");
+		checkArray(settings["managementIdentities"],"settings.managementIdentities",[](const json &j) -> bool { Identity id; return ((j.is_string())&&(id.fromString(j.get<std::string>().c_str()))&&(id.locallyValidate())); },"must be a valid public identity");
+		if ((settings.count("preferFamily"))&&(!isPreferFamily(settings["preferFamily"])))
+			error("settings.preferFamily","must be \"ipv4\", \"ipv6\", or \"any\"");
+	} else if (!settings.is_null()) error("settings","must be an object");
+}
+
+
 static void _networkToJson(nlohmann::json &nj,const ZT_VirtualNetworkConfig *nc,const std::string &portDeviceName,const OneService::NetworkSettings &localSettings)
 {
 	char tmp[256];
@@ -209,6 +533,9 @@ static void _networkToJson(nlohmann::json &nj,const ZT_VirtualNetworkConfig *nc,
 	nj["allowGlobal"] = localSettings.allowGlobal;
 	nj["allowDefault"] = localSettings.allowDefault;
 	nj["allowDNS"] = localSettings.allowDNS;
+	nj["paused"] = localSettings.paused;
+	nj["nickname"] = localSettings.nickname;
+	nj["keepalive"] = localSettings.keepalive;
 
 	nlohmann::json aa = nlohmann::json::array();
 	for(unsigned int i=0;i<nc->assignedAddressCount;++i) {
@@ -250,7 +577,6 @@ static void _networkToJson(nlohmann::json &nj,const ZT_VirtualNetworkConfig *nc,
 		}
 	}
 	nj["dns"] = m;
-	
 }
 
 static void _peerToJson(nlohmann::json &pj,const ZT_Peer *peer)
@@ -278,6 +604,9 @@ static void _peerToJson(nlohmann::json &pj,const ZT_Peer *peer)
 	pj["isHealthy"] = peer->isHealthy;
 	pj["numAliveLinks"] = peer->numAliveLinks;
 	pj["numTotalLinks"] = peer->numTotalLinks;
+	pj["macFailures"] = peer->macFailures;
+	pj["relayed"] = (peer->relayed != 0);
+	pj["cipher"] = (peer->aesGmacSiv) ? "AES-GMAC-SIV" : "SALSA2012-POLY1305";
 
 	nlohmann::json pa = nlohmann::json::array();
 	for(unsigned int i=0;i<peer->pathCount;++i) {
@@ -291,6 +620,13 @@ static void _peerToJson(nlohmann::json &pj,const ZT_Peer *peer)
 		j["active"] = (bool)(peer->paths[i].expired == 0);
 		j["expired"] = (bool)(peer->paths[i].expired != 0);
 		j["preferred"] = (bool)(peer->paths[i].preferred != 0);
+		j["keepalive"] = peer->paths[i].keepalive;
+		if (peer->paths[i].lastMtuProbe > 0) {
+			j["mtuProbeMaxSize"] = peer->paths[i].mtuProbeMaxSize;
+			j["mtuProbeBlackhole"] = (bool)(peer->paths[i].mtuProbeBlackhole != 0);
+			j["mtuProbeDone"] = (bool)(peer->paths[i].mtuProbeDone != 0);
+			j["lastMtuProbe"] = peer->paths[i].lastMtuProbe;
+		}
 		pa.push_back(j);
 	}
 	pj["paths"] = pa;
@@ -365,6 +701,181 @@ static void _moonToJson(nlohmann::json &mj,const World &world)
 	mj["waiting"] = false;
 }
 
+static void _rootHealthToJson(nlohmann::json &ra,const World &world,const char *role,const ZT_PeerList *pl,const int64_t now)
+{
+	char tmp[256];
+	for(std::vector<World::Root>::const_iterator r(world.roots().begin());r!=world.roots().end();++r) {
+		const ZT_Peer *peer = (const ZT_Peer *)0;
+		for(unsigned long i=0;i<pl->peerCount;++i) {
+			if (pl->peers[i].address == r->identity.address().toInt()) {
+				peer = &(pl->peers[i]);
+				break;
+			}
+		}
+
+		nlohmann::json rj;
+		rj["address"] = r->identity.address().toString(tmp);
+		OSUtils::ztsnprintf(tmp,sizeof(tmp),"%.16llx",world.id());
+		rj["worldId"] = tmp;
+		rj["role"] = role;
+		nlohmann::json eps = nlohmann::json::array();
+		for(std::vector<InetAddress>::const_iterator a(r->stableEndpoints.begin());a!=r->stableEndpoints.end();++a) {
+			nlohmann::json ej;
+			ej["address"] = a->toString(tmp);
+			uint64_t lastReceive = 0;
+			bool preferred = false;
+			if (peer) {
+				for(unsigned int p=0;p<peer->pathCount;++p) {
+					if (InetAddress(peer->paths[p].address) == *a) {
+						lastReceive = peer->paths[p].lastReceive;
+						preferred = (peer->paths[p].preferred != 0);
+						break;
+					}
+				}
+			}
+			ej["reachable"] = ((lastReceive > 0)&&((now - (int64_t)lastReceive) < (ZT_PATH_HEARTBEAT_PERIOD + 5000)));
+			ej["lastReceive"] = lastReceive;
+			ej["preferred"] = preferred;
+			// Peer latency is measured on the preferred path only
+			ej["latency"] = ((preferred)&&(peer)) ? peer->latency : -1;
+			eps.push_back(ej);
+		}
+		rj["endpoints"] = eps;
+		ra.push_back(rj);
+	}
+}
+
+static bool _isHex(const std::string &s)
+{
+	for(std::string::const_iterator c(s.begin());c!=s.end();++c) {
+		if (!(((*c >= '0')&&(*c <= '9'))||((*c >= 'a')&&(*c <= 'f'))||((*c >= 'A')&&(*c <= 'F'))))
+			return false;
+	}
+	return true;
+}
+
+// Directory index is into the list in _repairState(): home, moons.d, networks.d, peers.d
+static bool _isStateFileName(const int dir,const std::string &name)
+{
+	switch(dir) {
+		case 0:
+			return ((name == "identity.public")||(name == "identity.secret")||(name == "planet"));
+		case 1:
+			return ((name.length() == 21)&&(name.substr(16) == ".moon")&&(_isHex(name.substr(0,16))));
+		case 2:
+			return ((name.length() == 21)&&(name.substr(16) == ".conf")&&(_isHex(name.substr(0,16))));
+		case 3:
+			return ((name.length() == 15)&&(name.substr(10) == ".peer")&&(_isHex(name.substr(0,10))));
+	}
+	return false;
+}
+
+// Move a corrupt state object aside, optionally leaving replacement content in its place
+static void _quarantineStateFile(const std::string &p,const char *replacement)
+{
+	char qp[1100];
+	OSUtils::ztsnprintf(qp,sizeof(qp),"%s.corrupt-%lld",p.c_str(),(long long)OSUtils::now());
+	if (!OSUtils::rename(p.c_str(),qp))
+		return;
+	fprintf(stderr,"WARNING: %s is corrupt and was moved to %s" ZT_EOL_S,p.c_str(),qp);
+	if (replacement)
+		OSUtils::writeFile(p.c_str(),replacement,(unsigned int)strlen(replacement));
+}
+
+// Called by the service at startup before the node is created. A <name>.tmp is only
+// ever written by nodeStatePutFunction() and renamed over <name> once synced, so if
+// <name> exists the .tmp is an unfinished write and is dropped. If <name> is missing the .tmp is the only
+// copy and is promoted. Peer and network objects that no longer parse, and any object that does not
+// match its checksum trailer, are then moved to <name>.corrupt-<ms> so the core never reads them and
+// they can be inspected later.
+static void _repairState(const std::string &homePath)
+{
+	const char *stateDirs[4] = { "", "moons.d", "networks.d", "peers.d" };
+	for(int d=0;d<4;++d) {
+		const std::string dp((stateDirs[d][0]) ? (homePath + ZT_PATH_SEPARATOR_S + stateDirs[d]) : homePath);
+		std::vector<std::string> files(OSUtils::listDirectory(dp.c_str()));
+		for(std::vector<std::string>::iterator f(files.begin());f!=files.end();++f) {
+			if ((f->length() <= 4)||(f->substr(f->length() - 4) != ".tmp"))
+				continue;
+			const std::string name(f->substr(0,f->length() - 4));
+			if (!_isStateFileName(d,name))
+				continue;
+			const std::string tp(dp + ZT_PATH_SEPARATOR_S + *f);
+			const std::string p(dp + ZT_PATH_SEPARATOR_S + name);
+			if (OSUtils::fileExists(p.c_str(),false)) {
+				fprintf(stderr,"WARNING: removing incomplete state file %s" ZT_EOL_S,tp.c_str());
+				OSUtils::rm(tp.c_str());
+			} else if (OSUtils::rename(tp.c_str(),p.c_str())) {
+				fprintf(stderr,"WARNING: %s was missing, restored it from %s" ZT_EOL_S,p.c_str(),tp.c_str());
+			}
+		}
+	}
+
+	std::vector<std::string> worlds;
+	worlds.push_back(homePath + ZT_PATH_SEPARATOR_S "planet");
+	const std::string moonsPath(homePath + ZT_PATH_SEPARATOR_S "moons.d");
+	std::vector<std::string> moons(OSUtils::listDirectory(moonsPath.c_str()));
+	for(std::vector<std::string>::iterator f(moons.begin());f!=moons.end();++f) {
+		if (_isStateFileName(1,*f))
+			worlds.push_back(moonsPath + ZT_PATH_SEPARATOR_S + *f);
+	}
+	for(std::vector<std::string>::iterator p(worlds.begin());p!=worlds.end();++p) {
+		std::string buf;
+		if ((OSUtils::readFile(p->c_str(),buf))&&(stripStateChecksum(buf) < 0))
+			_quarantineStateFile(*p,(const char *)0);
+	}
+
+	const std::string peersPath(homePath + ZT_PATH_SEPARATOR_S "peers.d");
+	std::vector<std::string> peers(OSUtils::listDirectory(peersPath.c_str()));
+	for(std::vector<std::string>::iterator f(peers.begin());f!=peers.end();++f) {
+		if (!_isStateFileName(3,*f))
+			continue;
+		const std::string p(peersPath + ZT_PATH_SEPARATOR_S + *f);
+		std::string buf;
+		bool ok = false;
+		if ((OSUtils::readFile(p.c_str(),buf))&&(stripStateChecksum(buf) >= 0)&&(!buf.empty())&&(buf.length() <= ZT_PEER_MAX_SERIALIZED_STATE_SIZE)) {
+			try {
+				Buffer<ZT_PEER_MAX_SERIALIZED_STATE_SIZE> b(buf.data(),(unsigned int)buf.length());
+				if (b[0] == 1) {
+					Identity id;
+					unsigned int ptr = 1 + id.deserialize(b,1);
+					b.at<uint16_t>(ptr + 8); // version fields and path count must be present
+					ok = ((id)&&(id.address().toInt() == Utils::hexStrToU64(f->substr(0,10).c_str())));
+				}
+			} catch ( ... ) {}
+		}
+		if (!ok)
+			_quarantineStateFile(p,(const char *)0);
+	}
+
+	const std::string networksPath(homePath + ZT_PATH_SEPARATOR_S "networks.d");
+	std::vector<std::string> networks(OSUtils::listDirectory(networksPath.c_str()));
+	for(std::vector<std::string>::iterator f(networks.begin());f!=networks.end();++f) {
+		if (!_isStateFileName(2,*f))
+			continue;
+		const std::string p(networksPath + ZT_PATH_SEPARATOR_S + *f);
+		std::string buf;
+		if (!OSUtils::readFile(p.c_str(),buf))
+			continue;
+		const bool checksumOk = (stripStateChecksum(buf) >= 0);
+		if ((checksumOk)&&(buf == "\n")) // placeholder written by the core for a network with no config yet
+			continue;
+		bool ok = false;
+		if ((checksumOk)&&(buf.length() > 1)&&(buf.length() < ZT_NETWORKCONFIG_DICT_CAPACITY)) {
+			Dictionary<ZT_NETWORKCONFIG_DICT_CAPACITY> *dict = new Dictionary<ZT_NETWORKCONFIG_DICT_CAPACITY>(buf.data(),(unsigned int)buf.length());
+			NetworkConfig *nconf = new NetworkConfig();
+			try {
+				ok = ((nconf->fromDictionary(*dict))&&(nconf->networkId == Utils::hexStrToU64(f->substr(0,16).c_str())));
+			} catch ( ... ) {}
+			delete nconf;
+			delete dict;
+		}
+		// Keep a placeholder so the network is still joined and its config is fetched again
+		if (!ok)
+			_quarantineStateFile(p,"\n");
+	}
+}
+
 class OneServiceImpl;
 
 static int SnodeVirtualNetworkConfigFunction(ZT_Node *node,void *uptr,void *tptr,uint64_t nwid,void **nuptr,enum ZT_VirtualNetworkConfigOperation op,const ZT_VirtualNetworkConfig *nwconf);
@@ -428,6 +939,8 @@ struct TcpConnection
 	OneServiceImpl *parent;
 	PhySocket *sock;
 	InetAddress remoteAddr;
+	InetAddress managementLocalAddr; // our address if accepted on a management network listener, else nil
+	bool unixPeer; // HTTP over the API Unix socket from root or our own user
 	uint64_t lastReceive;
 
 	// Used for inbound HTTP connections
@@ -470,13 +983,37 @@ public:
 	SoftwareUpdater *_updater;
 	PhySocket *_localControlSocket4;
 	PhySocket *_localControlSocket6;
+	PhySocket *_localControlSocketUnix;
+	bool _apiUnixSocket;
+	std::map< InetAddress,PhySocket * > _managementNetworkSockets; // only touched by main I/O loop
+	ApiRateLimiter _apiRateLimiter; // main I/O loop only
 	bool _updateAutoApply;
 	bool _allowTcpFallbackRelay;
 	bool _allowSecondaryPort;
+	bool _ipv6Only;
+	bool _apiAuditLog;
+	std::atomic<uint64_t> _apiAuditLogMaxSize;
+	std::atomic<unsigned int> _apiAuditLogKeep;
+	BlockingQueue<std::string> _apiAuditQueue; // lines for the audit log writer, so the I/O loop never waits on the disk
+	std::thread _apiAuditThread;
+	std::atomic<int64_t> _peerCacheRetention;
+	std::atomic<bool> _stateChecksums; // append a checksum trailer to state objects only the core reads
+
+	// Slow core callback watchdog
+	std::atomic<unsigned int> _slowCallbackThreshold;
+	AtomicCounter _slowCallbacks[ZT_SERVICE_CALLBACK__COUNT];
+	AtomicCounter _sampledCallbackCalls[ZT_SERVICE_CALLBACK__COUNT];
+	std::atomic<int64_t> _slowCallbackMax[ZT_SERVICE_CALLBACK__COUNT];
+	std::atomic<int64_t> _slowCallbackLastWarning[ZT_SERVICE_CALLBACK__COUNT];
 
 	unsigned int _primaryPort;
+	unsigned int _requestedPrimaryPort; // configured primary port if we had to fall back from it, otherwise 0
+	bool _portFallback;
 	unsigned int _secondaryPort;
 	unsigned int _tertiaryPort;
+	bool _randomSecondaryPort; // pick a new secondary port each start instead of one derived from our address
+	unsigned int _secondaryPortRangeFirst;
+	unsigned int _secondaryPortRangeLast;
 	volatile unsigned int _udpPortPickerCounter;
 
 	// Local configuration and memo-ized information from it
@@ -485,12 +1022,54 @@ public:
 	Hashtable< uint64_t,std::vector<InetAddress> > _v6Hints;
 	Hashtable< uint64_t,std::vector<InetAddress> > _v4Blacklists;
 	Hashtable< uint64_t,std::vector<InetAddress> > _v6Blacklists;
+	Hashtable< uint64_t,std::vector<InetAddress> > _pinnedPaths;
+	Hashtable< uint64_t,std::string > _peerNames;
+	std::vector< std::pair<InetAddress,json> > _physicalMetadata; // physical.<net>.metadata, most specific first
+	std::vector<uint64_t> _peerKeepalives;
+	std::vector<uint64_t> _peerNoRelays;
+	Hashtable< uint64_t,int64_t > _peerRelayAlerts;
+	bool _trafficAlert; // settings.trafficAlert present
+	unsigned int _trafficAlertSpikeFactor;
+	unsigned int _trafficAlertMinPacketsPerSecond;
+	unsigned int _trafficAlertMulticastPacketsPerSecond;
+	unsigned int _trafficAlertUnknownEtherTypePacketsPerSecond;
+	Hashtable< uint64_t,std::pair<int64_t,bool> > _relayedSince; // when relaying started, whether alerted
+	std::vector<uint64_t> _peerPreferredFamilies;
 	std::vector< InetAddress > _globalV4Blacklist;
 	std::vector< InetAddress > _globalV6Blacklist;
 	std::vector< InetAddress > _allowManagementFrom;
+	std::vector< uint64_t > _managementNetworks;
+	ManagementAuth _managementAuth; // identities pinned for management networks, guarded by _localConfig_m
 	std::vector< std::string > _interfacePrefixBlacklist;
+	std::vector< std::string > _interfacePrefixWhitelist;
 	Mutex _localConfig_m;
 
+	// DSCP overrides (as TOS bytes) by destination for wire packets, checked on each send while any are set
+	struct WireTos
+	{
+		Hashtable< uint64_t,unsigned int > peerTos; // "virtual".<address>.dscp
+		Hashtable< uint64_t,bool > rootAddresses; // roots and moons, refreshed in the background while rootTos is set
+		int rootTos; // settings.rootDscp, or -1 if not set
+	};
+	std::shared_ptr<const WireTos> _wireTos; // replaced whole with std::atomic_store() so sends don't lock, null if there are no overrides
+	std::atomic<bool> _wireTosInUse; // false if there are no overrides, checked before loading _wireTos
+	Mutex _wireTos_m; // held while building a replacement for _wireTos
+
+	// Metadata for path addresses from settings.pathMetadataCommand, looked up in the background
+	struct PathMetadata
+	{
+		PathMetadata() : ts(0),pending(true) {}
+		int64_t ts; // time of last lookup
+		bool pending; // queued for (re)lookup
+		json metadata; // object, or null if the command could not describe the address
+	};
+	std::string _pathMetadataCommand;
+	unsigned long _pathMetadataCommandChanges; // lets the worker drop answers from a replaced command
+	std::map< InetAddress,PathMetadata > _pathMetadata;
+	Mutex _pathMetadata_m;
+	BlockingQueue<InetAddress> _pathMetadataQueue;
+	std::thread _pathMetadataThread;
+
 	std::vector<InetAddress> explicitBind;
 
 	/*
@@ -523,13 +1102,18 @@ public:
 	struct NetworkState
 	{
 		NetworkState() :
-			tap((EthernetTap *)0)
+			tap((EthernetTap *)0),
+			trafficAlerts(0),
+			lastTrafficAlert(0),
+			lastTrafficAlertReason("")
 		{
 			// Real defaults are in network 'up' code in network event handler
 			settings.allowManaged = true;
 			settings.allowGlobal = false;
 			settings.allowDefault = false;
 			settings.allowDNS = false;
+			settings.paused = false;
+			settings.keepalive = 0;
 			memset(&config, 0, sizeof(ZT_VirtualNetworkConfig));
 		}
 
@@ -538,10 +1122,38 @@ public:
 		std::vector<InetAddress> managedIps;
 		std::map< InetAddress, SharedPtr<ManagedRoute> > managedRoutes;
 		NetworkSettings settings;
+		std::vector<NetworkStatsSample> statsSamples; // oldest first, covering ZT_NETWORK_STATS_WINDOW
+		uint64_t trafficAlerts;
+		int64_t lastTrafficAlert;
+		const char *lastTrafficAlertReason;
 	};
 	std::map<uint64_t,NetworkState> _nets;
 	Mutex _nets_m;
 
+	// Recent traffic alerts for GET /status, oldest first, guarded by _nets_m
+	struct TrafficAlertEntry
+	{
+		uint64_t nwid;
+		int64_t ts;
+		const char *reason;
+		uint64_t rate;
+		uint64_t avgRate;
+	};
+	std::list<TrafficAlertEntry> _trafficAlertLog;
+
+	// Recent trace events for GET /trace, oldest first (the core only emits these in ZT_TRACE builds)
+	struct TraceEntry
+	{
+		uint64_t seq;
+		int64_t ts;
+		std::string message;
+	};
+	std::list<TraceEntry> _traceBuffer;
+	uint64_t _traceSeq; // sequence number of last event added
+	uint64_t _traceBoot; // random per service start, since _traceSeq restarts at zero
+	uint64_t _traceDropped; // events pushed out of the buffer
+	Mutex _traceBuffer_m;
+
 	// Active TCP/IP connections
 	std::vector< TcpConnection * > _tcpConnections;
 	Mutex _tcpConnections_m;
@@ -585,15 +1197,39 @@ public:
 		,_updater((SoftwareUpdater *)0)
 		,_localControlSocket4((PhySocket *)0)
 		,_localControlSocket6((PhySocket *)0)
+		,_localControlSocketUnix((PhySocket *)0)
+		,_apiUnixSocket(false)
 		,_updateAutoApply(false)
+		,_ipv6Only(false)
+		,_apiAuditLog(false)
+		,_apiAuditLogMaxSize(ZT_API_AUDIT_LOG_DEFAULT_MAX_SIZE)
+		,_apiAuditLogKeep(ZT_API_AUDIT_LOG_DEFAULT_KEEP)
+		,_peerCacheRetention(ZT_PEER_CACHE_RETENTION_DEFAULT)
+		,_stateChecksums(false)
+		,_slowCallbackThreshold(ZT_SLOW_CALLBACK_DEFAULT)
 		,_primaryPort(port)
+		,_requestedPrimaryPort(0)
+		,_portFallback(true)
+		,_randomSecondaryPort(false)
+		,_secondaryPortRangeFirst(ZT_SECONDARY_PORT_RANGE_FIRST)
+		,_secondaryPortRangeLast(ZT_SECONDARY_PORT_RANGE_LAST)
 		,_udpPortPickerCounter(0)
+		,_trafficAlert(false)
+		,_trafficAlertSpikeFactor(0)
+		,_trafficAlertMinPacketsPerSecond(0)
+		,_trafficAlertMulticastPacketsPerSecond(0)
+		,_trafficAlertUnknownEtherTypePacketsPerSecond(0)
+		,_wireTosInUse(false)
+		,_pathMetadataCommandChanges(0)
 		,_lastDirectReceiveFromGlobal(0)
 #ifdef ZT_TCP_FALLBACK_RELAY
 		,_lastSendToGlobalV4(0)
 #endif
 		,_lastRestart(0)
 		,_nextBackgroundTaskDeadline(0)
+		,_traceSeq(0)
+		,_traceBoot(0)
+		,_traceDropped(0)
 		,_tcpFallbackTunnel((TcpConnection *)0)
 		,_termReason(ONE_STILL_RUNNING)
 		,_portMappingEnabled(true)
@@ -612,6 +1248,11 @@ public:
 		_ports[0] = 0;
 		_ports[1] = 0;
 		_ports[2] = 0;
+		for(unsigned int i=0;i<ZT_SERVICE_CALLBACK__COUNT;++i) {
+			_slowCallbackMax[i] = 0;
+			_slowCallbackLastWarning[i] = 0;
+		}
+		Utils::getSecureRandom(&_traceBoot,sizeof(_traceBoot));
 
 #if ZT_VAULT_SUPPORT
 		curl_global_init(CURL_GLOBAL_DEFAULT);
@@ -623,6 +1264,10 @@ public:
 		_binder.closeAll(_phy);
 		_phy.close(_localControlSocket4);
 		_phy.close(_localControlSocket6);
+		if (_localControlSocketUnix) {
+			_phy.close(_localControlSocketUnix);
+			OSUtils::rm((_homePath + ZT_PATH_SEPARATOR_S ZT_API_UNIX_SOCKET_NAME).c_str());
+		}
 
 #if ZT_VAULT_SUPPORT
 		curl_global_cleanup();
@@ -631,6 +1276,12 @@ public:
 #ifdef ZT_USE_MINIUPNPC
 		delete _portMapper;
 #endif
+		_pathMetadataQueue.stop();
+		if (_pathMetadataThread.joinable())
+			_pathMetadataThread.join();
+		_apiAuditQueue.stop();
+		if (_apiAuditThread.joinable())
+			_apiAuditThread.join();
 		delete _controller;
 		delete _rc;
 	}
@@ -658,6 +1309,9 @@ public:
 				_authToken = _trimString(_authToken);
 			}
 
+			// Finish or discard state writes interrupted by a crash and set aside corrupt objects
+			_repairState(_homePath);
+
 			{
 				struct ZT_Node_Callbacks cb;
 				cb.version = 0;
@@ -677,6 +1331,7 @@ public:
 			applyLocalConfig();
 
 			// Make sure we can use the primary port, and hunt for one if configured to do so
+			const unsigned int configuredPort = _primaryPort;
 			const int portTrials = (_primaryPort == 0) ? 256 : 1; // if port is 0, pick random
 			for(int k=0;k<portTrials;++k) {
 				if (_primaryPort == 0) {
@@ -690,6 +1345,16 @@ public:
 					_primaryPort = 0;
 				}
 			}
+			if ((_ports[0] == 0)&&(configuredPort != 0)&&(_portFallback)) {
+				for(unsigned int fp=configuredPort+1;((fp<=(configuredPort + ZT_PRIMARY_PORT_FALLBACK_RANGE))&&(fp<=65535));++fp) {
+					if (_trialBind(fp)) {
+						_ports[0] = _primaryPort = fp;
+						_requestedPrimaryPort = configuredPort;
+						fprintf(stderr,"WARNING: primary port %u is in use, using %u instead" ZT_EOL_S,configuredPort,fp);
+						break;
+					}
+				}
+			}
 			if (_ports[0] == 0) {
 				Mutex::Lock _l(_termReason_m);
 				_termReason = ONE_UNRECOVERABLE_ERROR;
@@ -713,6 +1378,17 @@ public:
 				_localControlSocket6 = _phy.tcpListen((const struct sockaddr *)&lo6);
 			}
 
+#ifdef __UNIX_LIKE__
+			// Optionally serve the same API on a Unix socket, authorized by the caller's user ID
+			if (_apiUnixSocket) {
+				const std::string sp(_homePath + ZT_PATH_SEPARATOR_S ZT_API_UNIX_SOCKET_NAME);
+				_localControlSocketUnix = _phy.unixListen(sp.c_str());
+				if (_localControlSocketUnix)
+					OSUtils::lockDownFile(sp.c_str(),false);
+				else fprintf(stderr,"WARNING: unable to listen on %s" ZT_EOL_S,sp.c_str());
+			}
+#endif
+
 			// Save primary port to a file so CLIs and GUIs can learn it easily
 			char portstr[64];
 			OSUtils::ztsnprintf(portstr,sizeof(portstr),"%u",_ports[0]);
@@ -726,17 +1402,12 @@ public:
 				if (_secondaryPort) {
 					_ports[1] = _secondaryPort;
 				} else {
-					_ports[1] = 20000 + ((unsigned int)_node->address() % 45500);
-					for(int i=0;;++i) {
-						if (i > 1000) {
-							_ports[1] = 0;
-							break;
-						} else if (++_ports[1] >= 65536) {
-							_ports[1] = 20000;
-						}
-						if (_trialBind(_ports[1]))
-							break;
-					}
+					unsigned int start = (unsigned int)_node->address();
+					if (_randomSecondaryPort)
+						Utils::getSecureRandom(&start,sizeof(start));
+					// Modulus of at most 45500 keeps the port earlier versions picked for the default range
+					const unsigned int span = (_secondaryPortRangeLast - _secondaryPortRangeFirst) + 1;
+					_ports[1] = _findSecondaryPort(_secondaryPortRangeFirst + (start % std::min(span,45500U)));
 				}
 			}
 #ifdef ZT_USE_MINIUPNPC
@@ -748,17 +1419,7 @@ public:
 					if (_tertiaryPort) {
 						_ports[2] = _tertiaryPort;
 					} else {
-						_ports[2] = _ports[1];
-						for(int i=0;;++i) {
-							if (i > 1000) {
-								_ports[2] = 0;
-								break;
-							} else if (++_ports[2] >= 65536) {
-								_ports[2] = 20000;
-							}
-							if (_trialBind(_ports[2]))
-								break;
-						}
+						_ports[2] = _findSecondaryPort(_ports[1]);
 						if (_ports[2]) {
 							char uniqueName[64];
 							OSUtils::ztsnprintf(uniqueName,sizeof(uniqueName),"ZeroTier/%.10llx@%u",_node->address(),_ports[2]);
@@ -781,8 +1442,23 @@ public:
 				std::vector<std::string> networksDotD(OSUtils::listDirectory((_homePath + ZT_PATH_SEPARATOR_S "networks.d").c_str()));
 				for(std::vector<std::string>::iterator f(networksDotD.begin());f!=networksDotD.end();++f) {
 					std::size_t dot = f->find_last_of('.');
-					if ((dot == 16)&&(f->substr(16) == ".conf"))
-						_node->join(Utils::hexStrToU64(f->substr(0,dot).c_str()),(void *)0,(void *)0);
+					if ((dot == 16)&&(f->substr(16) == ".conf")) {
+						const uint64_t nwid = Utils::hexStrToU64(f->substr(0,dot).c_str());
+						_node->join(nwid,(void *)0,(void *)0);
+
+						// Keep presenting a saved join token until the controller authorizes us
+						std::string nlcbuf;
+						if (OSUtils::readFile((_networksPath + ZT_PATH_SEPARATOR_S + f->substr(0,dot) + ".local.conf").c_str(),nlcbuf)) {
+							Dictionary<4096> nc;
+							nc.load(nlcbuf.c_str());
+							char joinToken[ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH + 1];
+							if (nc.get("joinToken",joinToken,sizeof(joinToken)) > 0)
+								_node->setNetworkJoinToken(nwid,joinToken);
+							char keepalive[16];
+							if (nc.get("keepalive",keepalive,sizeof(keepalive)) > 0)
+								_node->setNetworkKeepalive(nwid,Utils::strToUInt(keepalive));
+						}
+					}
 				}
 			}
 
@@ -801,9 +1477,12 @@ public:
 			int64_t clockShouldBe = OSUtils::now();
 			_lastRestart = clockShouldBe;
 			int64_t lastTapMulticastGroupCheck = 0;
+			int64_t lastNetworkStatsSample = 0;
 			int64_t lastBindRefresh = 0;
 			int64_t lastUpdateCheck = clockShouldBe;
 			int64_t lastCleanedPeersDb = 0;
+			int64_t lastRelayCheck = 0;
+			int64_t lastRootAddressCheck = 0;
 			int64_t lastLocalInterfaceAddressCheck = (clockShouldBe - ZT_LOCAL_INTERFACE_CHECK_INTERVAL) + 15000; // do this in 15s to give portmapper time to configure and other things time to settle
 			int64_t lastLocalConfFileCheck = OSUtils::now();
 			for(;;) {
@@ -864,6 +1543,7 @@ public:
 								syncManagedStuff(n->second,false,true,false);
 						}
 					}
+					syncManagementNetworkSockets();
 				}
 
 				// Run background task processor in core if it's time to do so
@@ -899,6 +1579,35 @@ public:
 					}
 				}
 
+				// Sample per-network traffic counters for rate calculation
+				if ((now - lastNetworkStatsSample) >= ZT_NETWORK_STATS_SAMPLE_INTERVAL) {
+					lastNetworkStatsSample = now;
+					ZT_VirtualNetworkList *nws = _node->networks();
+					if (nws) {
+						// Read counters first so _nets_m is not held while calling into the node
+						std::vector< std::pair<uint64_t,ZT_VirtualNetworkStats> > samples;
+						for(unsigned long i=0;i<nws->networkCount;++i) {
+							ZT_VirtualNetworkStats nst;
+							if (_node->networkStats(nws->networks[i].nwid,&nst) == ZT_RESULT_OK)
+								samples.push_back(std::pair<uint64_t,ZT_VirtualNetworkStats>(nws->networks[i].nwid,nst));
+						}
+						_node->freeQueryResult((void *)nws);
+
+						Mutex::Lock _l(_nets_m);
+						for(std::vector< std::pair<uint64_t,ZT_VirtualNetworkStats> >::const_iterator smp(samples.begin());smp!=samples.end();++smp) {
+							std::map<uint64_t,NetworkState>::iterator n(_nets.find(smp->first));
+							if (n != _nets.end()) {
+								std::vector<NetworkStatsSample> &ss = n->second.statsSamples;
+								ss.push_back(NetworkStatsSample(now,smp->second));
+								while ((ss.size() > 1)&&((now - ss.front().first) > ZT_NETWORK_STATS_WINDOW))
+									ss.erase(ss.begin());
+								if (_trafficAlert)
+									checkTrafficAlert(n->first,n->second,now);
+							}
+						}
+					}
+				}
+
 				// Sync information about physical network interfaces
 				if ((now - lastLocalInterfaceAddressCheck) >= (_node->bondController()->inUse() ? ZT_LOCAL_INTERFACE_CHECK_INTERVAL / 8 : ZT_LOCAL_INTERFACE_CHECK_INTERVAL)) {
 					lastLocalInterfaceAddressCheck = now;
@@ -919,10 +1628,24 @@ public:
 					}
 				}
 
+				// Warn about peers in local.conf with "relayAlert" that have been relayed too long (relaying is expected in low-power mode)
+				if ((_peerRelayAlerts.size() > 0)&&((now - lastRelayCheck) >= ZT_PING_CHECK_INVERVAL)) {
+					lastRelayCheck = now;
+					if (_node->lowPower())
+						_relayedSince.clear();
+					else checkRelayAlerts(now);
+				}
+
+				// Keep the set of roots and moons for settings.rootDscp current
+				if ((now - lastRootAddressCheck) >= ZT_PING_CHECK_INVERVAL) {
+					lastRootAddressCheck = now;
+					refreshRootAddresses();
+				}
+
 				// Clean peers.d periodically
 				if ((now - lastCleanedPeersDb) >= 3600000) {
 					lastCleanedPeersDb = now;
-					OSUtils::cleanDirectory((_homePath + ZT_PATH_SEPARATOR_S "peers.d").c_str(),now - 2592000000LL); // delete older than 30 days
+					OSUtils::cleanDirectory((_homePath + ZT_PATH_SEPARATOR_S "peers.d").c_str(),now - _peerCacheRetention);
 				}
 
 				const unsigned long delay = (dl > now) ? (unsigned long)(dl - now) : 500;
@@ -993,17 +1716,10 @@ public:
 		Mutex::Lock _l2(_localConfig_m);
 		std::string lcbuf;
 		if (OSUtils::readFile((_homePath + ZT_PATH_SEPARATOR_S "local.conf").c_str(),lcbuf)) {
-			if (lcbuf.length() > 0) {
-				try {
-					_localConfig = OSUtils::jsonParse(lcbuf);
-					if (!_localConfig.is_object()) {
-						fprintf(stderr,"ERROR: unable to parse local.conf (root element is not a JSON object)" ZT_EOL_S);
-						exit(1);
-					}
-				} catch ( ... ) {
-					fprintf(stderr,"ERROR: unable to parse local.conf (invalid JSON)" ZT_EOL_S);
-					exit(1);
-				}
+			std::string err;
+			if (!_parseLocalConf(lcbuf,_localConfig,err)) {
+				fprintf(stderr,"ERROR: unable to parse local.conf (%s)" ZT_EOL_S,err.c_str());
+				exit(1);
 			}
 		}
 
@@ -1128,6 +1844,50 @@ public:
 		return true;
 	}
 
+	// Add traffic counters, rates over the sample window, and traffic alert history to a network's JSON
+	void _networkStatsToJson(nlohmann::json &nj,const uint64_t nwid) const
+	{
+		ZT_VirtualNetworkStats cur;
+		if (_node->networkStats(nwid,&cur) != ZT_RESULT_OK)
+			return;
+
+		nlohmann::json &st = nj["stats"];
+		st["rxPackets"] = cur.rxPackets;
+		st["rxBytes"] = cur.rxBytes;
+		st["rxDropped"] = cur.rxDropped;
+		st["txPackets"] = cur.txPackets;
+		st["txBytes"] = cur.txBytes;
+		st["txDropped"] = cur.txDropped;
+		st["rxMulticast"] = cur.rxMulticast;
+		st["txMulticast"] = cur.txMulticast;
+		st["rxUnknownEtherType"] = cur.rxUnknownEtherType;
+		st["txUnknownEtherType"] = cur.txUnknownEtherType;
+
+		uint64_t rxPps = 0,rxBps = 0,txPps = 0,txBps = 0;
+		Mutex::Lock _l(_nets_m);
+		std::map<uint64_t,NetworkState>::const_iterator n(_nets.find(nwid));
+		if ((n != _nets.end())&&(!n->second.statsSamples.empty())) {
+			const int64_t dt = OSUtils::now() - n->second.statsSamples.front().first;
+			const ZT_VirtualNetworkStats &s0 = n->second.statsSamples.front().second;
+			if ((dt > 0)&&(cur.rxBytes >= s0.rxBytes)&&(cur.txBytes >= s0.txBytes)) {
+				rxPps = ((cur.rxPackets - s0.rxPackets) * 1000ULL) / (uint64_t)dt;
+				rxBps = ((cur.rxBytes - s0.rxBytes) * 1000ULL) / (uint64_t)dt;
+				txPps = ((cur.txPackets - s0.txPackets) * 1000ULL) / (uint64_t)dt;
+				txBps = ((cur.txBytes - s0.txBytes) * 1000ULL) / (uint64_t)dt;
+			}
+		}
+		st["rxPacketsPerSecond"] = rxPps;
+		st["rxBytesPerSecond"] = rxBps;
+		st["txPacketsPerSecond"] = txPps;
+		st["txBytesPerSecond"] = txBps;
+
+		if (n != _nets.end()) {
+			st["trafficAlerts"] = n->second.trafficAlerts;
+			st["lastTrafficAlert"] = n->second.lastTrafficAlert;
+			st["lastTrafficAlertReason"] = n->second.lastTrafficAlertReason;
+		}
+	}
+
 	virtual bool setNetworkSettings(const uint64_t nwid,const NetworkSettings &settings)
 	{
 		Mutex::Lock _l(_nets_m);
@@ -1139,17 +1899,26 @@ public:
 
 		char nlcpath[4096];
 		OSUtils::ztsnprintf(nlcpath,sizeof(nlcpath),"%s" ZT_PATH_SEPARATOR_S "%.16llx.local.conf",_networksPath.c_str(),nwid);
-		FILE *out = fopen(nlcpath,"w");
+		FILE *out = (n->second.settings.joinToken.empty()) ? fopen(nlcpath,"w") : OSUtils::fopenPrivate(nlcpath);
 		if (out) {
 			fprintf(out,"allowManaged=%d\n",(int)n->second.settings.allowManaged);
 			fprintf(out,"allowGlobal=%d\n",(int)n->second.settings.allowGlobal);
 			fprintf(out,"allowDefault=%d\n",(int)n->second.settings.allowDefault);
 			fprintf(out,"allowDNS=%d\n",(int)n->second.settings.allowDNS);
+			fprintf(out,"paused=%d\n",(int)n->second.settings.paused);
+			if (!n->second.settings.nickname.empty())
+				fprintf(out,"nickname=%s\n",n->second.settings.nickname.c_str());
+			if (!n->second.settings.joinToken.empty())
+				fprintf(out,"joinToken=%s\n",n->second.settings.joinToken.c_str());
+			if (n->second.settings.keepalive)
+				fprintf(out,"keepalive=%u\n",n->second.settings.keepalive);
 			fclose(out);
 		}
 
-		if (n->second.tap)
+		if (n->second.tap) {
+			n->second.tap->setEnabled(!n->second.settings.paused);
 			syncManagedStuff(n->second,true,true,true);
+		}
 
 		return true;
 	}
@@ -1160,6 +1929,7 @@ public:
 
 	inline unsigned int handleControlPlaneHttpRequest(
 		const InetAddress &fromAddress,
+		const bool preAuthorized,
 		unsigned int httpMethod,
 		const std::string &path,
 		const std::map<std::string,std::string> &headers,
@@ -1194,8 +1964,8 @@ public:
 			return 404;
 		}
 
-		bool isAuth = false;
-		{
+		bool isAuth = preAuthorized; // e.g. Unix socket caller checked by user ID
+		if (!isAuth) {
 			std::map<std::string,std::string>::const_iterator ah(headers.find("x-zt1-auth"));
 			if ((ah != headers.end())&&(_authToken == ah->second)) {
 				isAuth = true;
@@ -1276,6 +2046,42 @@ public:
 					res["publicIdentity"] = status.publicIdentity;
 					res["online"] = (bool)(status.online != 0);
 					res["tcpFallbackActive"] = (_tcpFallbackTunnel != (TcpConnection *)0);
+					res["primaryPort"] = _ports[0];
+					if (_requestedPrimaryPort)
+						res["requestedPrimaryPort"] = _requestedPrimaryPort;
+					res["secondaryPort"] = _ports[1];
+					res["tertiaryPort"] = _ports[2];
+					res["secondaryPortMode"] = (!_allowSecondaryPort) ? "disabled" : ((_secondaryPort) ? "manual" : ((_randomSecondaryPort) ? "random" : "fixed"));
+					res["secondaryPortRange"] = json::array({ _secondaryPortRangeFirst,_secondaryPortRangeLast });
+					res["aesHardware"] = AES::accelerated();
+					res["lowPower"] = _node->lowPower();
+					{
+						json sc = json::object();
+						for(unsigned int i=0;i<ZT_SERVICE_CALLBACK__COUNT;++i) {
+							const int count = _slowCallbacks[i].load();
+							if (count > 0) {
+								json &c = sc[ZT_SERVICE_CALLBACK_NAMES[i]];
+								c["count"] = count;
+								c["maxMs"] = _slowCallbackMax[i].load();
+							}
+						}
+						res["slowCallbacks"] = sc;
+					}
+					{
+						json ta = json::array();
+						Mutex::Lock _l(_nets_m);
+						for(std::list<TrafficAlertEntry>::const_iterator a(_trafficAlertLog.begin());a!=_trafficAlertLog.end();++a) {
+							json e;
+							OSUtils::ztsnprintf(tmp,sizeof(tmp),"%.16llx",(unsigned long long)a->nwid);
+							e["nwid"] = tmp;
+							e["time"] = a->ts;
+							e["reason"] = a->reason;
+							e["rate"] = a->rate;
+							e["averageRate"] = a->avgRate;
+							ta.push_back(e);
+						}
+						res["trafficAlerts"] = ta;
+					}
 					res["versionMajor"] = ZEROTIER_ONE_VERSION_MAJOR;
 					res["versionMinor"] = ZEROTIER_ONE_VERSION_MINOR;
 					res["versionRev"] = ZEROTIER_ONE_VERSION_REVISION;
@@ -1349,6 +2155,19 @@ public:
 						}
 
 					}
+				} else if (ps[0] == "root") {
+					// Health of each planet and moon root's stable endpoints
+					ZT_PeerList *pl = _node->peers();
+					if (pl) {
+						const int64_t now = OSUtils::now();
+						res = json::array();
+						_rootHealthToJson(res,_node->planet(),"PLANET",pl,now);
+						std::vector<World> moons(_node->moons());
+						for(std::vector<World>::const_iterator m(moons.begin());m!=moons.end();++m)
+							_rootHealthToJson(res,*m,"MOON",pl,now);
+						_node->freeQueryResult((void *)pl);
+						scode = 200;
+					}
 				} else if (ps[0] == "network") {
 					ZT_VirtualNetworkList *nws = _node->networks();
 					if (nws) {
@@ -1361,6 +2180,7 @@ public:
 								getNetworkSettings(nws->networks[i].nwid,localSettings);
 								nlohmann::json nj;
 								_networkToJson(nj,&(nws->networks[i]),portDeviceName(nws->networks[i].nwid),localSettings);
+								_networkStatsToJson(nj,nws->networks[i].nwid);
 								res.push_back(nj);
 							}
 
@@ -1374,6 +2194,7 @@ public:
 									OneService::NetworkSettings localSettings;
 									getNetworkSettings(nws->networks[i].nwid,localSettings);
 									_networkToJson(res,&(nws->networks[i]),portDeviceName(nws->networks[i].nwid),localSettings);
+									_networkStatsToJson(res,nws->networks[i].nwid);
 									scode = 200;
 									break;
 								}
@@ -1383,18 +2204,70 @@ public:
 						_node->freeQueryResult((void *)nws);
 					} else scode = 500;
 				} else if (ps[0] == "peer") {
-					ZT_PeerList *pl = _node->peers();
+					// GET /peer takes filters and paging, which the core applies so only the peers returned are copied:
+					//   role=LEAF|MOON|PLANET, activeSince=<ms>, offset=<n>, limit=<n>, fields=<a,b,...>
+					int role = -1;
+					int64_t activeSince = 0;
+					unsigned long offset = 0,limit = 0; // a limit of 0 or none means no limit
+					std::vector<std::string> fields;
+					if (ps.size() == 1) {
+						std::map<std::string,std::string>::const_iterator ua;
+						if ((ua = urlArgs.find("role")) != urlArgs.end()) {
+							if (ua->second == "LEAF") role = ZT_PEER_ROLE_LEAF;
+							else if (ua->second == "MOON") role = ZT_PEER_ROLE_MOON;
+							else if (ua->second == "PLANET") role = ZT_PEER_ROLE_PLANET;
+							else role = -2; // matches nothing
+						}
+						if ((ua = urlArgs.find("activeSince")) != urlArgs.end())
+							activeSince = (int64_t)Utils::strToU64(ua->second.c_str());
+						if ((ua = urlArgs.find("offset")) != urlArgs.end())
+							offset = Utils::strToULong(ua->second.c_str());
+						if ((ua = urlArgs.find("limit")) != urlArgs.end())
+							limit = Utils::strToULong(ua->second.c_str());
+						if ((ua = urlArgs.find("fields")) != urlArgs.end())
+							fields = OSUtils::split(ua->second.c_str(),",","","");
+					}
+					ZT_PeerList *pl = _node->peers(role,activeSince,offset,limit);
 					if (pl) {
 						if (ps.size() == 1) {
-							// Return [array] of all peers
-
+							// Return [array] of the peers that match
 							res = nlohmann::json::array();
 							for(unsigned long i=0;i<pl->peerCount;++i) {
+								const ZT_Peer &p = pl->peers[i];
 								nlohmann::json pj;
-								_peerToJson(pj,&(pl->peers[i]));
+								_peerToJson(pj,&p);
+								pj["name"] = peerName(p.address);
+								_pathMetadataToJson(pj);
+								pj["keepalive"] = _node->peerKeepalive(Address(p.address));
+								pj["noRelay"] = _node->peerNoRelay(Address(p.address));
+								if (!fields.empty()) {
+									nlohmann::json fpj = nlohmann::json::object();
+									for(std::vector<std::string>::const_iterator f(fields.begin());f!=fields.end();++f) {
+										if (pj.count(*f))
+											fpj[*f] = pj[*f];
+									}
+									pj = fpj;
+								}
 								res.push_back(pj);
 							}
 
+							scode = 200;
+						} else if ((ps.size() == 2)&&(ps[1] == "versions")) {
+							// Return { "version": count } for all peers, or those with role=LEAF|MOON|PLANET
+
+							std::map<std::string,std::string>::const_iterator ua(urlArgs.find("role"));
+							res = nlohmann::json::object();
+							char ver[64];
+							for(unsigned long i=0;i<pl->peerCount;++i) {
+								const ZT_Peer &p = pl->peers[i];
+								if ((ua != urlArgs.end())&&(ua->second != ((p.role == ZT_PEER_ROLE_LEAF) ? "LEAF" : ((p.role == ZT_PEER_ROLE_MOON) ? "MOON" : "PLANET"))))
+									continue;
+								if (p.versionMajor < 0)
+									OSUtils::ztsnprintf(ver,sizeof(ver),"unknown");
+								else OSUtils::ztsnprintf(ver,sizeof(ver),"%d.%d.%d",p.versionMajor,p.versionMinor,p.versionRev);
+								res[ver] = OSUtils::jsonInt(res[ver],0ULL) + 1ULL;
+							}
+
 							scode = 200;
 						} else if (ps.size() == 2) {
 							// Return a single peer by ID or 404 if not found
@@ -1403,6 +2276,10 @@ public:
 							for(unsigned long i=0;i<pl->peerCount;++i) {
 								if (pl->peers[i].address == wantp) {
 									_peerToJson(res,&(pl->peers[i]));
+									res["name"] = peerName(wantp);
+									_pathMetadataToJson(res);
+									res["keepalive"] = _node->peerKeepalive(Address(wantp));
+									res["noRelay"] = _node->peerNoRelay(Address(wantp));
 									scode = 200;
 									break;
 								}
@@ -1411,6 +2288,39 @@ public:
 						} else scode = 404;
 						_node->freeQueryResult((void *)pl);
 					} else scode = 500;
+				} else if (ps[0] == "trace") {
+#ifdef ZT_TRACE
+					// Trace events after the sequence number in ?after=, so callers can poll without missing any
+					std::map<std::string,std::string>::const_iterator ua(urlArgs.find("after"));
+					uint64_t after = (ua != urlArgs.end()) ? Utils::strToU64(ua->second.c_str()) : 0;
+					char bootStr[24];
+					OSUtils::ztsnprintf(bootStr,sizeof(bootStr),"%.16llx",(unsigned long long)_traceBoot);
+					json ev = json::array();
+					Mutex::Lock _l(_traceBuffer_m);
+					// A sequence number from before a restart means nothing now, so start over
+					if ((after > _traceSeq)||(((ua = urlArgs.find("boot")) != urlArgs.end())&&(ua->second != bootStr)))
+						after = 0;
+					for(std::list<TraceEntry>::const_iterator t(_traceBuffer.begin());t!=_traceBuffer.end();++t) {
+						if (t->seq > after) {
+							json e;
+							e["seq"] = t->seq;
+							e["time"] = t->ts;
+							e["message"] = t->message;
+							ev.push_back(e);
+						}
+					}
+					// Events after 'after' that were pushed out of the buffer before this call
+					const uint64_t oldest = (_traceBuffer.empty()) ? (_traceSeq + 1) : _traceBuffer.front().seq;
+					res["events"] = ev;
+					res["boot"] = bootStr;
+					res["last"] = _traceSeq;
+					res["missed"] = ((after < _traceSeq)&&((after + 1) < oldest)) ? (oldest - (after + 1)) : 0;
+					res["dropped"] = _traceDropped;
+					res["bufferSize"] = ZT_TRACE_BUFFER_SIZE;
+					scode = 200;
+#else
+					scode = 501; // the core only emits trace events when built with ZT_TRACE
+#endif
 				} else if (ps[0] == "bonds") {
 					ZT_PeerList *pl = _node->peers();
 					if (pl) {
@@ -1511,6 +2421,44 @@ public:
 						}
 
 					} else scode = 404;
+				} else if (ps[0] == "status") {
+					try {
+						json j(OSUtils::jsonParse(body));
+						if ((j.is_object())&&(j["lowPower"].is_boolean())) {
+							_node->setLowPower((bool)j["lowPower"]);
+							res["lowPower"] = _node->lowPower();
+							scode = 200;
+						} else scode = 400;
+					} catch ( ... ) {
+						scode = 400;
+					}
+				} else if (ps[0] == "panic") {
+					if (ps.size() == 1) {
+						res = panic();
+						scode = 200;
+					} else if ((ps.size() == 2)&&(ps[1] == "rearm")) {
+						std::string token;
+						try {
+							json j(OSUtils::jsonParse(body));
+							if (j.is_object())
+								token = OSUtils::jsonString(j["token"],"");
+						} catch ( ... ) {
+							// discard invalid JSON
+						}
+						scode = rearm(token);
+						if (scode == 200)
+							res["result"] = true;
+					} else scode = 404;
+				} else if (ps[0] == "peer") {
+					if ((ps.size() == 3)&&(ps[2] == "mtuprobe")) {
+						const ZT_ResultCode rc = _node->probePathMtu((void *)0,Utils::hexStrToU64(ps[1].c_str()));
+						if (rc == ZT_RESULT_OK) {
+							res["result"] = true;
+							scode = 200;
+						} else if (rc == ZT_RESULT_ERROR_UNSUPPORTED_OPERATION) {
+							scode = 503; // low-power mode
+						} // else 404, unknown peer or no direct paths
+					} else scode = 404;
 				} else if (ps[0] == "network") {
 					if (ps.size() == 2) {
 
@@ -1522,6 +2470,7 @@ public:
 								if (nws->networks[i].nwid == wantnw) {
 									OneService::NetworkSettings localSettings;
 									getNetworkSettings(nws->networks[i].nwid,localSettings);
+									bool badKeepalive = false;
 
 									try {
 										json j(OSUtils::jsonParse(body));
@@ -1534,13 +2483,49 @@ public:
 											if (allowDefault.is_boolean()) localSettings.allowDefault = (bool)allowDefault;
 											json &allowDNS = j["allowDNS"];
 											if (allowDNS.is_boolean()) localSettings.allowDNS = (bool)allowDNS;
+											json &paused = j["paused"];
+											if (paused.is_boolean()) localSettings.paused = (bool)paused;
+											json &nickname = j["nickname"];
+											if (nickname.is_string()) {
+												// Keep to a single line our networks.d local.conf format can store unescaped
+												const std::string nn(nickname.get<std::string>());
+												localSettings.nickname.clear();
+												for(std::string::const_iterator c(nn.begin());(c!=nn.end())&&(localSettings.nickname.length()<ZT_MAX_NETWORK_SHORT_NAME_LENGTH);++c) {
+													if (((unsigned char)*c >= 32)&&(*c != '\\'))
+														localSettings.nickname.push_back(*c);
+												}
+											}
+											json &joinToken = j["joinToken"];
+											if (joinToken.is_string()) {
+												const std::string jt(joinToken.get<std::string>());
+												localSettings.joinToken.clear();
+												for(std::string::const_iterator c(jt.begin());(c!=jt.end())&&(localSettings.joinToken.length()<ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH);++c) {
+													if (((unsigned char)*c > 32)&&((unsigned char)*c < 127)&&(*c != '\\'))
+														localSettings.joinToken.push_back(*c);
+												}
+											}
+											json &keepalive = j["keepalive"];
+											if (!keepalive.is_null()) {
+												const int64_t ka = OSUtils::jsonDuration(keepalive,-1);
+												if ((ka == 0)||((ka >= ZT_PEER_KEEPALIVE_MIN_INTERVAL)&&(ka <= ZT_PATH_HEARTBEAT_PERIOD)))
+													localSettings.keepalive = (unsigned int)ka;
+												else badKeepalive = true;
+											}
 										}
 									} catch ( ... ) {
 										// discard invalid JSON
 									}
 
+									if (badKeepalive) {
+										scode = 400; // keepalive must be 0 or 1000 to 14000 ms
+										break;
+									}
+
 									setNetworkSettings(nws->networks[i].nwid,localSettings);
+									_node->setNetworkJoinToken(nws->networks[i].nwid,localSettings.joinToken.c_str());
+									_node->setNetworkKeepalive(nws->networks[i].nwid,localSettings.keepalive);
 									_networkToJson(res,&(nws->networks[i]),portDeviceName(nws->networks[i].nwid),localSettings);
+									_networkStatsToJson(res,nws->networks[i].nwid);
 
 									scode = 200;
 									break;
@@ -1566,6 +2551,43 @@ public:
 						res["result"] = true;
 						scode = 200;
 					} // else 404
+				} else if (ps[0] == "peer") {
+					if (ps.size() == 2) {
+						bool validAddress = (ps[1].length() == ZT_ADDRESS_LENGTH_HEX);
+						for(std::string::const_iterator c(ps[1].begin());(validAddress)&&(c!=ps[1].end());++c)
+							validAddress = (((*c >= '0')&&(*c <= '9'))||((*c >= 'a')&&(*c <= 'f'))||((*c >= 'A')&&(*c <= 'F')));
+						const Address zta((validAddress) ? Utils::hexStrToU64(ps[1].c_str()) : 0ULL);
+						if ((validAddress)&&(!zta.isReserved())) {
+							// A peer is known if it is in memory or has cached state in peers.d
+							bool known = false;
+							ZT_PeerList *pl = _node->peers();
+							if (pl) {
+								for(unsigned long i=0;i<pl->peerCount;++i) {
+									if (pl->peers[i].address == zta.toInt()) {
+										known = true;
+										break;
+									}
+								}
+								_node->freeQueryResult((void *)pl);
+							}
+							if (!known) {
+								char p[1024];
+								OSUtils::ztsnprintf(p,sizeof(p),"%s" ZT_PATH_SEPARATOR_S "peers.d" ZT_PATH_SEPARATOR_S "%.10llx.peer",_homePath.c_str(),(unsigned long long)zta.toInt());
+								known = OSUtils::fileExists(p);
+							}
+							if (known) {
+								if (_node->forgetPeer((void *)0,zta.toInt()) == ZT_RESULT_OK) {
+									res["result"] = true;
+									scode = 200;
+								} else scode = 403; // upstreams can't be forgotten
+							} // else 404
+						} else scode = 400;
+					} else if ((ps.size() == 3)&&(ps[2] == "paths")) {
+						if (_node->clearPaths(Utils::hexStrToU64(ps[1].c_str())) == ZT_RESULT_OK) {
+							res["result"] = true;
+							scode = 200;
+						} // else 404, peer not in memory
+					} // else 404
 				} else if (ps[0] == "network") {
 					ZT_VirtualNetworkList *nws = _node->networks();
 					if (nws) {
@@ -1613,16 +2635,141 @@ public:
 		return scode;
 	}
 
-	// Must be called after _localConfig is read or modified
-	void applyLocalConfig()
+	// Warn about sudden traffic spikes, multicast/broadcast storms, and unknown ethertypes on a network, assumes _nets_m is locked
+	void checkTrafficAlert(const uint64_t nwid,NetworkState &n,const int64_t now)
+	{
+		uint64_t rate = 0,avgRate = 0;
+		const char *const reason = trafficAlert(n.statsSamples,_trafficAlertSpikeFactor,_trafficAlertMinPacketsPerSecond,_trafficAlertMulticastPacketsPerSecond,_trafficAlertUnknownEtherTypePacketsPerSecond,n.lastTrafficAlert,now,rate,avgRate);
+		if (!reason)
+			return;
+		if (reason[0] == 'm')
+			fprintf(stderr,"WARNING: network %.16llx: %llu multicast/broadcast frames/s, possible bridge loop or broadcast storm" ZT_EOL_S,(unsigned long long)nwid,(unsigned long long)rate);
+		else if (reason[0] == 'e')
+			fprintf(stderr,"WARNING: network %.16llx: %llu frames/s with ethertypes other than IPv4, ARP, or IPv6" ZT_EOL_S,(unsigned long long)nwid,(unsigned long long)rate);
+		else fprintf(stderr,"WARNING: network %.16llx: traffic spike of %llu frames/s (average %llu frames/s)" ZT_EOL_S,(unsigned long long)nwid,(unsigned long long)rate,(unsigned long long)avgRate);
+		++n.trafficAlerts;
+		n.lastTrafficAlert = now;
+		n.lastTrafficAlertReason = reason;
+		TrafficAlertEntry e;
+		e.nwid = nwid;
+		e.ts = now;
+		e.reason = reason;
+		e.rate = rate;
+		e.avgRate = avgRate;
+		_trafficAlertLog.push_back(e);
+		while (_trafficAlertLog.size() > ZT_TRAFFIC_ALERT_LOG_SIZE)
+			_trafficAlertLog.pop_front();
+	}
+
+	void checkRelayAlerts(const int64_t now)
+	{
+		ZT_PeerList *pl = _node->peers();
+		if (!pl)
+			return;
+		for(unsigned long i=0;i<pl->peerCount;++i) {
+			const uint64_t a = pl->peers[i].address;
+			const int64_t *const alertAfter = _peerRelayAlerts.get(a);
+			if (!alertAfter)
+				continue;
+			if (pl->peers[i].relayed) {
+				std::pair<int64_t,bool> &since = _relayedSince[a];
+				if (!since.first) {
+					since.first = now;
+				} else if ((!since.second)&&((now - since.first) >= *alertAfter)) {
+					fprintf(stderr,"WARNING: traffic with %.10llx has been relayed for more than %lld ms" ZT_EOL_S,(unsigned long long)a,(long long)*alertAfter);
+					since.second = true;
+				}
+			} else {
+				const std::pair<int64_t,bool> *const since = _relayedSince.get(a);
+				if (since) {
+					if (since->second)
+						fprintf(stderr,"direct path to %.10llx restored after relaying" ZT_EOL_S,(unsigned long long)a);
+					_relayedSince.erase(a);
+				}
+			}
+		}
+		_node->freeQueryResult((void *)pl);
+	}
+
+	void refreshRootAddresses()
+	{
+		{
+			const std::shared_ptr<const WireTos> wt(std::atomic_load(&_wireTos));
+			if ((!wt)||(wt->rootTos < 0))
+				return;
+		}
+		Hashtable< uint64_t,bool > roots;
+		ZT_PeerList *pl = _node->peers();
+		if (pl) {
+			for(unsigned long i=0;i<pl->peerCount;++i) {
+				if (pl->peers[i].role != ZT_PEER_ROLE_LEAF)
+					roots[pl->peers[i].address] = true;
+			}
+			_node->freeQueryResult((void *)pl);
+		}
+		Mutex::Lock _l(_wireTos_m);
+		const std::shared_ptr<const WireTos> wt(std::atomic_load(&_wireTos));
+		if ((!wt)||(wt->rootTos < 0)) // changed while we listed peers
+			return;
+		std::shared_ptr<WireTos> nwt(new WireTos(*wt));
+		nwt->rootAddresses = roots;
+		std::atomic_store(&_wireTos,std::shared_ptr<const WireTos>(nwt));
+	}
+
+	// TOS byte for a wire packet from the destination address in its header, or -1 for the socket default
+	inline int wirePacketTos(const void *data,unsigned int len)
+	{
+		if ((!_wireTosInUse)||(len <= (ZT_PACKET_IDX_DEST + ZT_ADDRESS_LENGTH))) // same offset in fragments
+			return -1;
+		const std::shared_ptr<const WireTos> wt(std::atomic_load(&_wireTos));
+		if (!wt)
+			return -1;
+		const uint64_t dest = Address(reinterpret_cast<const uint8_t *>(data) + ZT_PACKET_IDX_DEST,ZT_ADDRESS_LENGTH).toInt();
+		const unsigned int *const tos = wt->peerTos.get(dest);
+		if (tos)
+			return (int)*tos;
+		if ((wt->rootTos >= 0)&&(wt->rootAddresses.contains(dest)))
+			return wt->rootTos;
+		return -1;
+	}
+
+	// Must be called after _localConfig is read or modified
+	void applyLocalConfig()
+	{
+		unsigned int tos;
+		{
+			Mutex::Lock _l(_localConfig_m);
+			tos = _applyLocalConfig();
+		}
+		// Binder::refresh() locks _localConfig_m (via shouldBindInterface) while holding its own lock, so don't nest them the other way
+		_binder.setTos(_phy,tos);
+		_binder.setTosOverrides(_phy,_wireTosInUse);
+		refreshRootAddresses();
+	}
+
+	// Apply _localConfig and return the IP TOS for wire packets, assumes _localConfig_m is locked
+	unsigned int _applyLocalConfig()
 	{
-		Mutex::Lock _l(_localConfig_m);
 		json lc(_localConfig);
 
 		_v4Hints.clear();
 		_v6Hints.clear();
 		_v4Blacklists.clear();
 		_v6Blacklists.clear();
+		_pinnedPaths.clear();
+		_peerNames.clear();
+		for(std::vector<uint64_t>::const_iterator a(_peerKeepalives.begin());a!=_peerKeepalives.end();++a)
+			_node->setPeerKeepalive(*a,0);
+		_peerKeepalives.clear();
+		for(std::vector<uint64_t>::const_iterator a(_peerNoRelays.begin());a!=_peerNoRelays.end();++a)
+			_node->setPeerNoRelay(*a,false);
+		_peerNoRelays.clear();
+		_peerRelayAlerts.clear();
+		_relayedSince.clear();
+		for(std::vector<uint64_t>::const_iterator a(_peerPreferredFamilies.begin());a!=_peerPreferredFamilies.end();++a)
+			_node->setPeerPreferredFamily(*a,0);
+		_peerPreferredFamilies.clear();
+		Hashtable< uint64_t,unsigned int > peerTos;
 		json &virt = lc["virtual"];
 		if (virt.is_object()) {
 			for(json::iterator v(virt.begin());v!=virt.end();++v) {
@@ -1635,6 +2782,32 @@ public:
 						std::vector<InetAddress> &v6h = _v6Hints[ztaddr2];
 						std::vector<InetAddress> &v4b = _v4Blacklists[ztaddr2];
 						std::vector<InetAddress> &v6b = _v6Blacklists[ztaddr2];
+						std::vector<InetAddress> &pins = _pinnedPaths[ztaddr2];
+
+						const std::string name(OSUtils::jsonString(v.value()["name"],""));
+						if (!name.empty())
+							_peerNames[ztaddr2] = name;
+
+						const int64_t keepalive = OSUtils::jsonDuration(v.value()["keepalive"],0);
+						if (keepalive) {
+							if ((keepalive <= 0xffffffffLL)&&(_node->setPeerKeepalive(ztaddr2,(unsigned int)keepalive) == ZT_RESULT_OK))
+								_peerKeepalives.push_back(ztaddr2);
+						}
+
+						if (OSUtils::jsonBool(v.value()["noRelay"],false)) {
+							_node->setPeerNoRelay(ztaddr2,true);
+							_peerNoRelays.push_back(ztaddr2);
+						}
+						const int preferFamily = _preferFamilyFromJson(v.value()["preferFamily"]);
+						if (preferFamily > 0) {
+							_node->setPeerPreferredFamily(ztaddr2,preferFamily);
+							_peerPreferredFamilies.push_back(ztaddr2);
+						}
+						const int64_t relayAlert = OSUtils::jsonDuration(v.value()["relayAlert"],0);
+						if (relayAlert > 0)
+							_peerRelayAlerts[ztaddr2] = relayAlert;
+						if (v.value().count("dscp"))
+							peerTos[ztaddr2] = ((unsigned int)OSUtils::jsonInt(v.value()["dscp"],0ULL) & 0x3fU) << 2;
 
 						json &tryAddrs = v.value()["try"];
 						if (tryAddrs.is_array()) {
@@ -1656,11 +2829,24 @@ public:
 									v6b.push_back(ip);
 							}
 						}
+						json &pinAddrs = v.value()["pin"];
+						if (pinAddrs.is_array()) {
+							for(unsigned long i=0;i<pinAddrs.size();++i) {
+								const InetAddress ip(OSUtils::jsonString(pinAddrs[i],"").c_str());
+								if (ip.ss_family == AF_INET)
+									v4h.push_back(ip);
+								else if (ip.ss_family == AF_INET6)
+									v6h.push_back(ip);
+								else continue;
+								pins.push_back(ip);
+							}
+						}
 
 						if (v4h.empty()) _v4Hints.erase(ztaddr2);
 						if (v6h.empty()) _v6Hints.erase(ztaddr2);
 						if (v4b.empty()) _v4Blacklists.erase(ztaddr2);
 						if (v6b.empty()) _v6Blacklists.erase(ztaddr2);
+						if (pins.empty()) _pinnedPaths.erase(ztaddr2);
 					}
 				}
 			}
@@ -1668,6 +2854,7 @@ public:
 
 		_globalV4Blacklist.clear();
 		_globalV6Blacklist.clear();
+		_physicalMetadata.clear();
 		json &physical = lc["physical"];
 		if (physical.is_object()) {
 			for(json::iterator phy(physical.begin());phy!=physical.end();++phy) {
@@ -1680,15 +2867,53 @@ public:
 							else if (net.ss_family == AF_INET6)
 								_globalV6Blacklist.push_back(net);
 						}
+						json &md = phy.value()["metadata"];
+						if (md.is_object())
+							_physicalMetadata.push_back(std::pair<InetAddress,json>(net,md));
 					}
 				}
 			}
 		}
+		std::stable_sort(_physicalMetadata.begin(),_physicalMetadata.end(),_morePhysicalMetadataSpecific);
+
+		// External command that describes path addresses not covered by physical metadata
+		{
+			std::string pathMetadataCommand(OSUtils::jsonString(lc["settings"]["pathMetadataCommand"],""));
+			if ((!pathMetadataCommand.empty())&&(pathMetadataCommand[0] != '/'))
+				pathMetadataCommand.clear();
+#ifndef __UNIX_LIKE__
+			if (!pathMetadataCommand.empty()) {
+				fprintf(stderr,"WARNING: ignoring pathMetadataCommand: not supported on this platform" ZT_EOL_S);
+				pathMetadataCommand.clear();
+			}
+#endif
+			Mutex::Lock _l(_pathMetadata_m);
+			if (pathMetadataCommand != _pathMetadataCommand) {
+				_pathMetadataCommand = pathMetadataCommand;
+				++_pathMetadataCommandChanges;
+				for(std::map< InetAddress,PathMetadata >::iterator m(_pathMetadata.begin());m!=_pathMetadata.end();) {
+					if (m->second.pending) // still queued, the worker will finish it
+						++m;
+					else _pathMetadata.erase(m++);
+				}
+			}
+			if ((!_pathMetadataCommand.empty())&&(!_pathMetadataThread.joinable()))
+				_pathMetadataThread = std::thread([this]() { _pathMetadataMain(); });
+		}
 
 		_allowManagementFrom.clear();
+		_managementNetworks.clear();
 		_interfacePrefixBlacklist.clear();
+		_interfacePrefixWhitelist.clear();
 
 		json &settings = lc["settings"];
+		{
+			std::vector< std::pair<std::string,std::string> > errors,warnings;
+			_checkLocalConf(lc,errors,warnings);
+			errors.insert(errors.end(),warnings.begin(),warnings.end());
+			for(std::vector< std::pair<std::string,std::string> >::const_iterator e(errors.begin());e!=errors.end();++e)
+				fprintf(stderr,"WARNING: local.conf: %s: %s" ZT_EOL_S,e->first.c_str(),e->second.c_str());
+		}
 
 		if (!_node->bondController()->inUse()) {
 			// defaultBondingPolicy
@@ -1827,10 +3052,109 @@ public:
 			}
 		}
 
-		// bondingPolicy cannot be used with allowTcpFallbackRelay
-		_allowTcpFallbackRelay = OSUtils::jsonBool(settings["allowTcpFallbackRelay"],true) && !(_node->bondController()->inUse());
+		// Bind and use only IPv6 physical paths (for hosts without IPv4 egress)
+		_ipv6Only = OSUtils::jsonBool(settings["ipv6Only"],false);
+
+		// Management API abuse limits and audit trail
+		_apiRateLimiter.setLimit((unsigned int)OSUtils::jsonInt(settings["apiRateLimit"],0ULL));
+		_apiAuditLog = OSUtils::jsonBool(settings["apiAuditLog"],false);
+		_apiAuditLogMaxSize = std::max(OSUtils::jsonInt(settings["apiAuditLogMaxSize"],(uint64_t)ZT_API_AUDIT_LOG_DEFAULT_MAX_SIZE),(uint64_t)4096);
+		_apiAuditLogKeep = (unsigned int)std::min(OSUtils::jsonInt(settings["apiAuditLogKeep"],(uint64_t)ZT_API_AUDIT_LOG_DEFAULT_KEEP),(uint64_t)ZT_API_AUDIT_LOG_MAX_KEEP);
+		if ((_apiAuditLog)&&(!_apiAuditThread.joinable()))
+			_apiAuditThread = std::thread([this]() { _apiAuditMain(); });
+		_apiUnixSocket = OSUtils::jsonBool(settings["apiUnixSocket"],false);
+
+		// Warn when a core callback (state write, sampled frame delivery, etc.) stalls the core this long
+		const int64_t slowCallbackWarning = OSUtils::jsonDuration(settings["slowCallbackWarning"],ZT_SLOW_CALLBACK_DEFAULT);
+		if (slowCallbackWarning > 0xffffffffLL) {
+			fprintf(stderr,"WARNING: slowCallbackWarning must be under 49 days, using %u ms" ZT_EOL_S,(unsigned int)ZT_SLOW_CALLBACK_DEFAULT);
+			_slowCallbackThreshold = ZT_SLOW_CALLBACK_DEFAULT;
+		} else _slowCallbackThreshold = (unsigned int)slowCallbackWarning;
+
+		// DSCP for outgoing UDP wire packets (0 leaves the OS default), set by applyLocalConfig()
+		const unsigned int tos = ((unsigned int)OSUtils::jsonInt(settings["dscp"],0ULL) & 0x3fU) << 2;
+		const int rootTos = (settings.count("rootDscp")) ? (int)(((unsigned int)OSUtils::jsonInt(settings["rootDscp"],0ULL) & 0x3fU) << 2) : -1;
+		{
+			Mutex::Lock _l(_wireTos_m);
+			if ((peerTos.size() > 0)||(rootTos >= 0)) {
+				const std::shared_ptr<const WireTos> owt(std::atomic_load(&_wireTos));
+				std::shared_ptr<WireTos> wt(new WireTos());
+				wt->peerTos = peerTos;
+				if ((owt)&&(rootTos >= 0))
+					wt->rootAddresses = owt->rootAddresses;
+				wt->rootTos = rootTos;
+				std::atomic_store(&_wireTos,std::shared_ptr<const WireTos>(wt));
+				_wireTosInUse = true;
+			} else {
+				_wireTosInUse = false;
+				std::atomic_store(&_wireTos,std::shared_ptr<const WireTos>());
+			}
+		}
+
+		// Forbid relaying of network traffic with all peers (fail closed)
+		_node->setPeerNoRelay(0,OSUtils::jsonBool(settings["noRelay"],false));
+
+		// Can also be toggled with POST /status until local.conf changes again
+		_node->setLowPower(OSUtils::jsonBool(settings["lowPower"],false));
+
+		// Default IPv4/IPv6 preference for direct paths (per peer in "virtual" overrides it)
+		const int preferFamily = _preferFamilyFromJson(settings["preferFamily"]);
+		_node->setPeerPreferredFamily(0,(preferFamily > 0) ? preferFamily : 0);
+
+		// How long idle peers stay in memory and unused peers.d entries are kept
+		if (_node->setPeerIdleTimeout((uint64_t)OSUtils::jsonDuration(settings["peerIdleTimeout"],0)) != ZT_RESULT_OK)
+			_node->setPeerIdleTimeout(0);
+		const int64_t peerCacheRetention = OSUtils::jsonDuration(settings["peerCacheRetention"],ZT_PEER_CACHE_RETENTION_DEFAULT);
+		if (peerCacheRetention <= 0) {
+			fprintf(stderr,"WARNING: peerCacheRetention must be a positive duration, using 30 days" ZT_EOL_S);
+			_peerCacheRetention = ZT_PEER_CACHE_RETENTION_DEFAULT;
+		} else _peerCacheRetention = peerCacheRetention;
+
+		_stateChecksums = OSUtils::jsonBool(settings["stateChecksums"],false);
+
+		// Per-network AQM send queue limit (absent or 0 keeps the default)
+		const uint64_t aqmMaxEnqueuedPackets = OSUtils::jsonInt(settings["aqmMaxEnqueuedPackets"],0ULL);
+		if ((aqmMaxEnqueuedPackets > 0xffffffffULL)||(_node->setAqmMaxEnqueuedPackets((unsigned int)aqmMaxEnqueuedPackets) != ZT_RESULT_OK)) {
+			fprintf(stderr,"WARNING: aqmMaxEnqueuedPackets must be from %u to %u, using %u" ZT_EOL_S,(unsigned int)ZT_AQM_MAX_ENQUEUED_PACKETS_MIN,(unsigned int)ZT_AQM_MAX_ENQUEUED_PACKETS_MAX,(unsigned int)ZT_AQM_MAX_ENQUEUED_PACKETS);
+			_node->setAqmMaxEnqueuedPackets(0);
+		}
+
+		// RX/TX queue sizes (absent or 0 keeps the default), the RX queue can only be resized at startup
+		const uint64_t rxQueueSize = OSUtils::jsonInt(settings["rxQueueSize"],0ULL);
+		const uint64_t txQueueSize = OSUtils::jsonInt(settings["txQueueSize"],0ULL);
+		const ZT_ResultCode queueSizesResult = ((rxQueueSize > 0xffffffffULL)||(txQueueSize > 0xffffffffULL)) ? ZT_RESULT_ERROR_BAD_PARAMETER : _node->setQueueSizes((unsigned int)rxQueueSize,(unsigned int)txQueueSize);
+		if (queueSizesResult == ZT_RESULT_ERROR_BAD_PARAMETER) {
+			fprintf(stderr,"WARNING: rxQueueSize and txQueueSize must be from %u to %u, using %u" ZT_EOL_S,(unsigned int)ZT_QUEUE_SIZE_MIN,(unsigned int)ZT_QUEUE_SIZE_MAX,(unsigned int)ZT_RX_QUEUE_SIZE);
+			_node->setQueueSizes(0,0);
+		} else if (queueSizesResult == ZT_RESULT_ERROR_UNSUPPORTED_OPERATION) {
+			fprintf(stderr,"WARNING: rxQueueSize changes take effect when the service restarts" ZT_EOL_S);
+		}
+
+		// bondingPolicy cannot be used with allowTcpFallbackRelay, and the relay is IPv4 only
+		_allowTcpFallbackRelay = OSUtils::jsonBool(settings["allowTcpFallbackRelay"],true) && !(_node->bondController()->inUse()) && !_ipv6Only;
 		_primaryPort = (unsigned int)OSUtils::jsonInt(settings["primaryPort"],(uint64_t)_primaryPort) & 0xffff;
+		_portFallback = OSUtils::jsonBool(settings["portFallback"],true);
 		_allowSecondaryPort = OSUtils::jsonBool(settings["allowSecondaryPort"],true);
+		const std::string spMode(OSUtils::jsonString(settings["secondaryPortMode"],"fixed"));
+		if (spMode == "disabled") {
+			_allowSecondaryPort = false;
+		} else if ((spMode != "fixed")&&(spMode != "random")) {
+			fprintf(stderr,"WARNING: unknown secondaryPortMode \"%s\", using \"fixed\"" ZT_EOL_S,spMode.c_str());
+		}
+		_randomSecondaryPort = (spMode == "random");
+		_secondaryPortRangeFirst = ZT_SECONDARY_PORT_RANGE_FIRST;
+		_secondaryPortRangeLast = ZT_SECONDARY_PORT_RANGE_LAST;
+		json &spRange = settings["secondaryPortRange"];
+		if (spRange.is_array()) {
+			const unsigned int first = (spRange.size() == 2) ? (unsigned int)OSUtils::jsonInt(spRange[0],0) : 0;
+			const unsigned int last = (spRange.size() == 2) ? (unsigned int)OSUtils::jsonInt(spRange[1],0) : 0;
+			if ((first > 0)&&(first <= last)&&(last <= 65535)) {
+				_secondaryPortRangeFirst = first;
+				_secondaryPortRangeLast = last;
+			} else {
+				fprintf(stderr,"WARNING: invalid secondaryPortRange, using %u-%u" ZT_EOL_S,(unsigned int)ZT_SECONDARY_PORT_RANGE_FIRST,(unsigned int)ZT_SECONDARY_PORT_RANGE_LAST);
+			}
+		}
 		_secondaryPort = (unsigned int)OSUtils::jsonInt(settings["secondaryPort"],0);
 		_tertiaryPort = (unsigned int)OSUtils::jsonInt(settings["tertiaryPort"],0);
 		if (_secondaryPort != 0 || _tertiaryPort != 0) {
@@ -1838,6 +3162,23 @@ public:
 		}
 		_portMappingEnabled = OSUtils::jsonBool(settings["portMappingEnabled"],true);
 
+		json ta(settings["trafficAlert"]);
+		_trafficAlert = ta.is_object();
+		if (!_trafficAlert)
+			ta = json::object();
+		_trafficAlertSpikeFactor = (unsigned int)OSUtils::jsonInt(ta["spikeFactor"],10ULL);
+		if (!_trafficAlertSpikeFactor) {
+			fprintf(stderr,"WARNING: invalid trafficAlert.spikeFactor 0, using 10" ZT_EOL_S);
+			_trafficAlertSpikeFactor = 10;
+		}
+		_trafficAlertMinPacketsPerSecond = (unsigned int)OSUtils::jsonInt(ta["minPacketsPerSecond"],1000ULL);
+		if (!_trafficAlertMinPacketsPerSecond) {
+			fprintf(stderr,"WARNING: invalid trafficAlert.minPacketsPerSecond 0, using 1000" ZT_EOL_S);
+			_trafficAlertMinPacketsPerSecond = 1000;
+		}
+		_trafficAlertMulticastPacketsPerSecond = (unsigned int)OSUtils::jsonInt(ta["multicastPacketsPerSecond"],1000ULL);
+		_trafficAlertUnknownEtherTypePacketsPerSecond = (unsigned int)OSUtils::jsonInt(ta["unknownEtherTypePacketsPerSecond"],100ULL);
+
 #ifndef ZT_SDK
 		const std::string up(OSUtils::jsonString(settings["softwareUpdate"],ZT_SOFTWARE_UPDATE_DEFAULT));
 		const bool udist = OSUtils::jsonBool(settings["softwareUpdateDist"],false);
@@ -1862,6 +3203,14 @@ public:
 					_interfacePrefixBlacklist.push_back(tmp);
 			}
 		}
+		json &onlyIfs = settings["interfacePrefixWhitelist"];
+		if (onlyIfs.is_array()) {
+			for(unsigned long i=0;i<onlyIfs.size();++i) {
+				const std::string tmp(OSUtils::jsonString(onlyIfs[i],""));
+				if (tmp.length() > 0)
+					_interfacePrefixWhitelist.push_back(tmp);
+			}
+		}
 
 		json &amf = settings["allowManagementFrom"];
 		if (amf.is_array()) {
@@ -1871,6 +3220,32 @@ public:
 					_allowManagementFrom.push_back(nw);
 			}
 		}
+
+		// Networks whose members may reach the management API at our address on that network
+		json &mgmtNets = settings["managementNetworks"];
+		if (mgmtNets.is_array()) {
+			for(unsigned long i=0;i<mgmtNets.size();++i) {
+				const std::string nwids(OSUtils::jsonString(mgmtNets[i],""));
+				if (nwids.length() == 16)
+					_managementNetworks.push_back(Utils::hexStrToU64(nwids.c_str()));
+			}
+		}
+
+		// Client identities that may sign requests made over management networks
+		std::vector<Identity> mgmtIds;
+		json &mgmtIdentities = settings["managementIdentities"];
+		if (mgmtIdentities.is_array()) {
+			for(unsigned long i=0;i<mgmtIdentities.size();++i) {
+				Identity id;
+				if ((id.fromString(OSUtils::jsonString(mgmtIdentities[i],"").c_str()))&&(id.locallyValidate()))
+					mgmtIds.push_back(id);
+			}
+		}
+		_managementAuth.setPinned(mgmtIds);
+		if ((!_managementNetworks.empty())&&(mgmtIds.empty()))
+			fprintf(stderr,"WARNING: managementNetworks is set but managementIdentities is empty, so no management network request will be accepted" ZT_EOL_S);
+
+		return tos;
 	}
 
 #if ZT_VAULT_SUPPORT
@@ -1914,6 +3289,409 @@ public:
 		}
 #endif
 
+	// Listen for management API requests on our managed IPs in any configured management networks
+	void syncManagementNetworkSockets()
+	{
+		std::vector<uint64_t> mgmtNets;
+		{
+			Mutex::Lock _l(_localConfig_m);
+			mgmtNets = _managementNetworks;
+		}
+
+		std::set<InetAddress> want;
+		{
+			Mutex::Lock _l(_nets_m);
+			for(std::vector<uint64_t>::const_iterator nwid(mgmtNets.begin());nwid!=mgmtNets.end();++nwid) {
+				std::map<uint64_t,NetworkState>::const_iterator n(_nets.find(*nwid));
+				if ((n != _nets.end())&&(n->second.tap)) {
+					for(std::vector<InetAddress>::const_iterator ip(n->second.managedIps.begin());ip!=n->second.managedIps.end();++ip) {
+						InetAddress la(*ip);
+						la.setPort(_ports[0]);
+						want.insert(la);
+					}
+				}
+			}
+		}
+
+		for(std::map< InetAddress,PhySocket * >::iterator s(_managementNetworkSockets.begin());s!=_managementNetworkSockets.end();) {
+			if (want.find(s->first) == want.end()) {
+				_phy.close(s->second,false);
+				_managementNetworkSockets.erase(s++);
+			} else ++s;
+		}
+		for(std::set<InetAddress>::const_iterator la(want.begin());la!=want.end();++la) {
+			if (_managementNetworkSockets.find(*la) == _managementNetworkSockets.end()) {
+				PhySocket *const ls = _phy.tcpListen(reinterpret_cast<const struct sockaddr *>(&(*la)));
+				if (ls) // if the IP isn't up yet (e.g. IPv6 DAD) we'll try again on next refresh
+					_managementNetworkSockets[*la] = ls;
+			}
+		}
+	}
+
+	// Local label for a peer from local.conf, or empty string if none
+	std::string peerName(const uint64_t address)
+	{
+		Mutex::Lock _l(_localConfig_m);
+		const std::string *const n = _peerNames.get(address);
+		return (n) ? *n : std::string();
+	}
+
+	static bool _morePhysicalMetadataSpecific(const std::pair<InetAddress,json> &a,const std::pair<InetAddress,json> &b)
+	{
+		return (a.first.netmaskBits() > b.first.netmaskBits());
+	}
+
+	// Attach metadata (e.g. site, ASN or location) to each of a peer's paths. Entries in
+	// local.conf "physical" win, otherwise the cached result of pathMetadataCommand is used.
+	void _pathMetadataToJson(nlohmann::json &pj)
+	{
+		nlohmann::json &pa = pj["paths"];
+		for(nlohmann::json::iterator p(pa.begin());p!=pa.end();++p) {
+			const InetAddress addr(OSUtils::jsonString((*p)["address"],"").c_str());
+			bool found = false;
+			{
+				Mutex::Lock _l(_localConfig_m);
+				for(std::vector< std::pair<InetAddress,json> >::const_iterator m(_physicalMetadata.begin());m!=_physicalMetadata.end();++m) {
+					if (m->first.containsAddress(addr)) {
+						(*p)["metadata"] = m->second;
+						found = true;
+						break;
+					}
+				}
+			}
+			if (!found) {
+				json md;
+				if (_cachedPathMetadata(addr.ipOnly(),md))
+					(*p)["metadata"] = md;
+			}
+		}
+	}
+
+	// Get command metadata for an address without blocking, queueing a lookup if it is missing or stale.
+	// A stale result is still returned while it is being refreshed.
+	bool _cachedPathMetadata(const InetAddress &ip,json &md)
+	{
+		if (!ip)
+			return false;
+		const int64_t now = OSUtils::now();
+		Mutex::Lock _l(_pathMetadata_m);
+		if (_pathMetadataCommand.empty())
+			return false;
+		std::map< InetAddress,PathMetadata >::iterator m(_pathMetadata.find(ip));
+		if (m == _pathMetadata.end()) {
+			if (_pathMetadata.size() >= ZT_PATH_METADATA_CACHE_MAX) {
+				for(std::map< InetAddress,PathMetadata >::iterator old(_pathMetadata.begin());old!=_pathMetadata.end();) {
+					if ((!old->second.pending)&&((now - old->second.ts) >= ZT_PATH_METADATA_RETRY_DELAY))
+						_pathMetadata.erase(old++);
+					else ++old;
+				}
+				if (_pathMetadata.size() >= ZT_PATH_METADATA_CACHE_MAX)
+					return false;
+			}
+			_pathMetadata[ip];
+			_pathMetadataQueue.post(ip);
+			return false;
+		}
+		if (m->second.pending)
+			return false;
+		const bool have = m->second.metadata.is_object();
+		if ((now - m->second.ts) >= (have ? ZT_PATH_METADATA_CACHE_TTL : ZT_PATH_METADATA_RETRY_DELAY)) {
+			m->second.pending = true;
+			_pathMetadataQueue.post(ip);
+		}
+		if (have)
+			md = m->second.metadata;
+		return have;
+	}
+
+	// Background thread that runs pathMetadataCommand for queued addresses
+	void _pathMetadataMain()
+	{
+		InetAddress ip;
+		while (_pathMetadataQueue.get(ip)) {
+			std::string command;
+			unsigned long changes;
+			{
+				Mutex::Lock _l(_pathMetadata_m);
+				command = _pathMetadataCommand;
+				changes = _pathMetadataCommandChanges;
+			}
+			const json md((command.empty()) ? json() : _runPathMetadataCommand(command,ip));
+			Mutex::Lock _l(_pathMetadata_m);
+			PathMetadata &m = _pathMetadata[ip];
+			if (changes == _pathMetadataCommandChanges) {
+				m.metadata = md;
+				m.ts = OSUtils::now();
+			} else {
+				m.metadata = json(); // from the old command, so look it up again on next use
+				m.ts = 0;
+			}
+			m.pending = false;
+		}
+	}
+
+	// Run "command <ip>" and parse its standard output as a JSON object. A non-zero exit,
+	// a timeout, or output that is not an object gives null.
+	static json _runPathMetadataCommand(const std::string &command,const InetAddress &ip)
+	{
+#ifdef __UNIX_LIKE__
+		char ipstr[64];
+		ip.toIpString(ipstr);
+		const char *const path = command.c_str();
+		long maxFd = ::sysconf(_SC_OPEN_MAX); // not async-signal-safe, so not in the child
+		if (maxFd <= 0)
+			maxFd = 65536;
+		// Close-on-exec so the read end doesn't leak into anything another thread starts meanwhile
+		int out[2];
+#if defined(__LINUX__) || defined(__FreeBSD__) || defined(__OpenBSD__) || defined(__NetBSD__) || defined(__DragonFly__)
+		if (::pipe2(out,O_CLOEXEC))
+			return json();
+#else
+		if (::pipe(out)) // no pipe2() on macOS, so there is still a short window here
+			return json();
+		::fcntl(out[0],F_SETFD,FD_CLOEXEC);
+		::fcntl(out[1],F_SETFD,FD_CLOEXEC);
+#endif
+		const pid_t pid = ::fork();
+		if (pid < 0) {
+			::close(out[0]);
+			::close(out[1]);
+			return json();
+		}
+		if (pid == 0) {
+			::setpgid(0,0); // so a timeout also kills anything the command started
+			::dup2(out[1],STDOUT_FILENO); // the copy is not close-on-exec
+
+			// Don't leak sockets or the tap device to the command
+			bool closed = false;
+#if defined(__FreeBSD__) || defined(__OpenBSD__) || defined(__NetBSD__) || defined(__DragonFly__)
+			::closefrom(3);
+			closed = true;
+#elif defined(__LINUX__) && defined(SYS_close_range)
+			closed = (::syscall(SYS_close_range,3U,~0U,0U) == 0); // Linux 5.9 and newer
+#endif
+			if (!closed) {
+				for(long fd=3;fd<maxFd;++fd)
+					::close((int)fd);
+			}
+
+			::execl(path,path,ipstr,(char *)0);
+			::_exit(127);
+		}
+		::setpgid(pid,pid);
+		::close(out[1]);
+
+		const int64_t deadline = OSUtils::now() + ZT_PATH_METADATA_COMMAND_TIMEOUT;
+		std::string buf;
+		for(;;) {
+			const int64_t left = deadline - OSUtils::now();
+			if (left <= 0)
+				break;
+			struct pollfd pfd;
+			pfd.fd = out[0];
+			pfd.events = POLLIN;
+			pfd.revents = 0;
+			const int pr = ::poll(&pfd,1,(int)left);
+			if (pr < 0) {
+				if (errno == EINTR)
+					continue;
+				break;
+			}
+			if (pr == 0)
+				break;
+			char tmp[4096];
+			const ssize_t n = ::read(out[0],tmp,sizeof(tmp));
+			if (n <= 0)
+				break;
+			buf.append(tmp,(size_t)n);
+			if (buf.size() > ZT_PATH_METADATA_MAX_OUTPUT)
+				break;
+		}
+		::close(out[0]);
+
+		int status = 0;
+		bool exited = false;
+		for(;;) {
+			if (::waitpid(pid,&status,WNOHANG) == pid) {
+				exited = true;
+				break;
+			}
+			if (OSUtils::now() >= deadline)
+				break;
+			std::this_thread::sleep_for(std::chrono::milliseconds(10));
+		}
+		if (!exited) {
+			::kill(-pid,SIGKILL);
+			::waitpid(pid,&status,0);
+			return json();
+		}
+		if ((!WIFEXITED(status))||(WEXITSTATUS(status) != 0)||(buf.size() > ZT_PATH_METADATA_MAX_OUTPUT))
+			return json();
+		try {
+			const json md(OSUtils::jsonParse(buf));
+			if (md.is_object())
+				return md;
+		} catch ( ... ) {}
+#endif
+		return json();
+	}
+
+	// Emergency disconnect: leave every network and forget every peer except upstreams.
+	// The networks left and their local settings are saved in panic.json so they can
+	// be rejoined by rearm() with the returned token. Repeated calls keep the token.
+	json panic()
+	{
+		const std::string panicPath(_homePath + ZT_PATH_SEPARATOR_S "panic.json");
+		json p;
+		std::string buf;
+		if (OSUtils::readFile(panicPath.c_str(),buf)) {
+			try {
+				p = OSUtils::jsonParse(buf);
+			} catch ( ... ) {}
+		}
+		if (!p.is_object())
+			p = json::object();
+		if (!p["token"].is_string()) {
+			uint8_t rtok[16];
+			char token[48];
+			Utils::getSecureRandom(rtok,sizeof(rtok));
+			p["token"] = Utils::hex(rtok,sizeof(rtok),token);
+		}
+		json &networks = p["networks"];
+		if (!networks.is_array())
+			networks = json::array();
+
+		std::vector<uint64_t> left;
+		ZT_VirtualNetworkList *nws = _node->networks();
+		if (nws) {
+			for(unsigned long i=0;i<nws->networkCount;++i) {
+				NetworkSettings ns;
+				if (!getNetworkSettings(nws->networks[i].nwid,ns))
+					continue;
+				char tmp[32];
+				OSUtils::ztsnprintf(tmp,sizeof(tmp),"%.16llx",(unsigned long long)nws->networks[i].nwid);
+				json n;
+				n["id"] = tmp;
+				n["allowManaged"] = ns.allowManaged;
+				n["allowGlobal"] = ns.allowGlobal;
+				n["allowDefault"] = ns.allowDefault;
+				n["allowDNS"] = ns.allowDNS;
+				n["paused"] = ns.paused;
+				n["nickname"] = ns.nickname;
+				n["joinToken"] = ns.joinToken;
+				n["keepalive"] = ns.keepalive;
+				// A network joined again since an earlier panic replaces its old entry
+				unsigned long k = 0;
+				while ((k < networks.size())&&(OSUtils::jsonString(networks[k]["id"],"") != tmp))
+					++k;
+				if (k < networks.size())
+					networks[k] = n;
+				else networks.push_back(n);
+				left.push_back(nws->networks[i].nwid);
+			}
+			_node->freeQueryResult((void *)nws);
+		}
+
+		// Save before leaving, since leaving deletes each network's files in networks.d
+		const std::string pj(OSUtils::jsonDump(p));
+		FILE *pf = OSUtils::fopenPrivate(panicPath.c_str()); // contains join tokens
+		bool saved = false;
+		if (pf) {
+			saved = (fwrite(pj.data(),1,pj.length(),pf) == pj.length());
+			saved &= (fclose(pf) == 0);
+		}
+		if (!saved)
+			fprintf(stderr,"WARNING: unable to write %s, networks will have to be rejoined by hand" ZT_EOL_S,panicPath.c_str());
+
+		for(std::vector<uint64_t>::const_iterator nwid(left.begin());nwid!=left.end();++nwid)
+			_node->leave(*nwid,(void **)0,(void *)0);
+
+		const unsigned long forgotten = _node->forgetLeafPeers((void *)0);
+
+		json res;
+		res["token"] = p["token"];
+		res["networks"] = json::array();
+		for(unsigned long i=0;i<networks.size();++i)
+			res["networks"].push_back(networks[i]["id"]);
+		res["peersForgotten"] = forgotten;
+		return res;
+	}
+
+	// Rejoin the networks saved by panic() if the token matches; returns an HTTP status
+	unsigned int rearm(const std::string &token)
+	{
+		const std::string panicPath(_homePath + ZT_PATH_SEPARATOR_S "panic.json");
+		json p;
+		std::string buf;
+		if (!OSUtils::readFile(panicPath.c_str(),buf))
+			return 404;
+		try {
+			p = OSUtils::jsonParse(buf);
+		} catch ( ... ) {
+			return 500;
+		}
+		const std::string expected(OSUtils::jsonString(p["token"],""));
+		if ((expected.empty())||(token.length() != expected.length())||(!Utils::secureEq(token.data(),expected.data(),(unsigned int)token.length())))
+			return 403;
+
+		json &networks = p["networks"];
+		if (networks.is_array()) {
+			for(unsigned long i=0;i<networks.size();++i) {
+				json &n = networks[i];
+				const uint64_t nwid = Utils::hexStrToU64(OSUtils::jsonString(n["id"],"0").c_str());
+				if (!nwid)
+					continue;
+				_node->join(nwid,(void *)0,(void *)0);
+				NetworkSettings ns;
+				if (getNetworkSettings(nwid,ns)) {
+					ns.allowManaged = OSUtils::jsonBool(n["allowManaged"],true);
+					ns.allowGlobal = OSUtils::jsonBool(n["allowGlobal"],false);
+					ns.allowDefault = OSUtils::jsonBool(n["allowDefault"],false);
+					ns.allowDNS = OSUtils::jsonBool(n["allowDNS"],false);
+					ns.paused = OSUtils::jsonBool(n["paused"],false);
+					ns.nickname = OSUtils::jsonString(n["nickname"],"");
+					ns.joinToken = OSUtils::jsonString(n["joinToken"],"");
+					ns.keepalive = (unsigned int)OSUtils::jsonInt(n["keepalive"],0);
+					setNetworkSettings(nwid,ns);
+					_node->setNetworkJoinToken(nwid,ns.joinToken.c_str());
+					_node->setNetworkKeepalive(nwid,ns.keepalive);
+				}
+			}
+		}
+
+		OSUtils::rm(panicPath.c_str());
+		return 200;
+	}
+
+	// Checks a management network request's signature against the pinned identities
+	bool managementRequestSigned(TcpConnection *tc)
+	{
+		std::map<std::string,std::string>::const_iterator sh(tc->headers.find("x-zt1-signature"));
+		if (sh == tc->headers.end())
+			return false;
+		Mutex::Lock _l(_localConfig_m);
+		return _managementAuth.verify(sh->second,http_method_str((enum http_method)tc->parser.method),tc->url,tc->readq,OSUtils::now());
+	}
+
+	// Checks if a connection to our address on a management network came from within that network
+	bool isFromManagementNetwork(const InetAddress &localAddr,const InetAddress &remoteAddr) const
+	{
+		if (!localAddr)
+			return false;
+		std::vector<uint64_t> mgmtNets;
+		{
+			Mutex::Lock _l(_localConfig_m);
+			mgmtNets = _managementNetworks;
+		}
+		Mutex::Lock _l(_nets_m);
+		for(std::vector<uint64_t>::const_iterator nwid(mgmtNets.begin());nwid!=mgmtNets.end();++nwid) {
+			std::map<uint64_t,NetworkState>::const_iterator n(_nets.find(*nwid));
+			if ((n != _nets.end())&&(ManagementAuth::fromNetwork(reinterpret_cast<const InetAddress *>(n->second.config.assignedAddresses),n->second.config.assignedAddressCount,localAddr,remoteAddr)))
+				return true;
+		}
+		return false;
+	}
+
 	// Checks if a managed IP or route target is allowed
 	bool checkIfManagedIsAllowed(const NetworkState &n,const InetAddress &target)
 	{
@@ -2163,6 +3941,13 @@ public:
 			tc->parent = this;
 			tc->sock = sockN;
 			tc->remoteAddr = from;
+			for(std::map< InetAddress,PhySocket * >::const_iterator s(_managementNetworkSockets.begin());s!=_managementNetworkSockets.end();++s) {
+				if (s->second == sockL) {
+					tc->managementLocalAddr = s->first;
+					break;
+				}
+			}
+			tc->unixPeer = false;
 			tc->lastReceive = OSUtils::now();
 			http_parser_init(&(tc->parser),HTTP_REQUEST);
 			tc->parser.data = (void *)tc;
@@ -2219,6 +4004,8 @@ public:
 									}
 								}
 							}
+							if (!allow)
+								allow = isFromManagementNetwork(tc->managementLocalAddr,tc->remoteAddr);
 							if (allow) {
 								tc->type = TcpConnection::TCP_HTTP_INCOMING;
 								phyOnTcpData(sock,uptr,data,len);
@@ -2350,9 +4137,40 @@ public:
 
 	inline void phyOnFileDescriptorActivity(PhySocket *sock,void **uptr,bool readable,bool writable) {}
 	inline void phyOnUnixAccept(PhySocket *sockL,PhySocket *sockN,void **uptrL,void **uptrN) {}
-	inline void phyOnUnixClose(PhySocket *sock,void **uptr) {}
-	inline void phyOnUnixData(PhySocket *sock,void **uptr,void *data,unsigned long len) {}
-	inline void phyOnUnixWritable(PhySocket *sock,void **uptr) {}
+
+	// Unix sockets are only accepted for the API, which then runs as HTTP like phyOnTcpData()
+	inline void phyOnUnixClose(PhySocket *sock,void **uptr) { phyOnTcpClose(sock,uptr); }
+	inline void phyOnUnixData(PhySocket *sock,void **uptr,void *data,unsigned long len)
+	{
+#ifdef __UNIX_LIKE__
+		if (!*uptr) {
+			if (!OSUtils::unixPeerIsRootOrSelf((int)Phy<OneServiceImpl *>::getDescriptor(sock))) {
+				_phy.close(sock);
+				return;
+			}
+
+			TcpConnection *tc = new TcpConnection();
+			{
+				Mutex::Lock _l(_tcpConnections_m);
+				_tcpConnections.push_back(tc);
+			}
+
+			tc->type = TcpConnection::TCP_HTTP_INCOMING;
+			tc->parent = this;
+			tc->sock = sock;
+			tc->remoteAddr = InetAddress::LO4;
+			tc->unixPeer = true;
+			tc->lastReceive = OSUtils::now();
+			http_parser_init(&(tc->parser),HTTP_REQUEST);
+			tc->parser.data = (void *)tc;
+			tc->messageSize = 0;
+
+			*uptr = (void *)tc;
+		}
+		phyOnTcpData(sock,uptr,data,len);
+#endif
+	}
+	inline void phyOnUnixWritable(PhySocket *sock,void **uptr) { phyOnTcpWritable(sock,uptr); }
 
 	inline int nodeVirtualNetworkConfigFunction(uint64_t nwid,void **nuptr,enum ZT_VirtualNetworkConfigOperation op,const ZT_VirtualNetworkConfig *nwc)
 	{
@@ -2412,6 +4230,17 @@ public:
 							n.settings.allowGlobal = nc.getB("allowGlobal", false);
 							n.settings.allowDefault = nc.getB("allowDefault", false);
 							n.settings.allowDNS = nc.getB("allowDNS", false);
+							n.settings.paused = nc.getB("paused", false);
+							n.tap->setEnabled(!n.settings.paused);
+							char nickname[ZT_MAX_NETWORK_SHORT_NAME_LENGTH + 1];
+							if (nc.get("nickname",nickname,sizeof(nickname)) > 0)
+								n.settings.nickname = nickname;
+							char joinToken[ZT_MAX_NETWORK_JOIN_TOKEN_LENGTH + 1];
+							if (nc.get("joinToken",joinToken,sizeof(joinToken)) > 0)
+								n.settings.joinToken = joinToken;
+							char keepalive[16];
+							if (nc.get("keepalive",keepalive,sizeof(keepalive)) > 0)
+								n.settings.keepalive = Utils::strToUInt(keepalive);
 						}
 					} catch (std::exception &exc) {
 #ifdef __WINDOWS__
@@ -2431,7 +4260,11 @@ public:
 				}
 				// After setting up tap, fall through to CONFIG_UPDATE since we also want to do this...
 
-			case ZT_VIRTUAL_NETWORK_CONFIG_OPERATION_CONFIG_UPDATE:
+			case ZT_VIRTUAL_NETWORK_CONFIG_OPERATION_CONFIG_UPDATE: {
+				// Only touch IPs, routes, and DNS that actually changed so that a new
+				// netconf revision doesn't tear down and re-create unchanged OS state.
+				bool ipsChanged,routesChanged,dnsChanged;
+				diffNetworkConfig(n.config,*nwc,ipsChanged,routesChanged,dnsChanged);
 				memcpy(&(n.config),nwc,sizeof(ZT_VirtualNetworkConfig));
 				if (n.tap) { // sanity check
 #if defined(__WINDOWS__) && !defined(ZT_SDK)
@@ -2444,19 +4277,28 @@ public:
 						Sleep(10);
 					}
 #endif
-					syncManagedStuff(n,true,true,true);
+					// Route source selection depends on assigned IPs, so resync routes if those moved.
+					syncManagedStuff(n,ipsChanged,(routesChanged||ipsChanged),dnsChanged);
 					n.tap->setMtu(nwc->mtu);
 				} else {
 					_nets.erase(nwid);
 					return -999; // tap init failed
 				}
-				break;
+			}	break;
 
 			case ZT_VIRTUAL_NETWORK_CONFIG_OPERATION_DOWN:
 			case ZT_VIRTUAL_NETWORK_CONFIG_OPERATION_DESTROY:
 				if (n.tap) { // sanity check
 #if defined(__WINDOWS__) && !defined(ZT_SDK)
 					std::string winInstanceId(((WindowsEthernetTap *)(n.tap.get()))->instanceId());
+#endif
+					// Remove routes and DNS while the device still exists. If the tap goes
+					// first, route removal can fail and leave routes that blackhole traffic.
+					n.managedRoutes.clear();
+#ifdef __APPLE__
+					MacDNSHelper::removeDNS(nwid);
+#elif defined(__WINDOWS__)
+					WinDNSHelper::removeDNS(nwid);
 #endif
 					*nuptr = (void *)0;
 					n.tap.reset();
@@ -2493,6 +4335,16 @@ public:
 				if (metaData) {
 					::fprintf(stderr,"%s" ZT_EOL_S,(const char *)metaData);
 					::fflush(stderr);
+
+					Mutex::Lock _l(_traceBuffer_m);
+					_traceBuffer.push_back(TraceEntry());
+					_traceBuffer.back().seq = ++_traceSeq;
+					_traceBuffer.back().ts = OSUtils::now();
+					_traceBuffer.back().message = (const char *)metaData;
+					if (_traceBuffer.size() > ZT_TRACE_BUFFER_SIZE) {
+						_traceBuffer.pop_front();
+						++_traceDropped;
+					}
 				}
 			}	break;
 
@@ -2631,16 +4483,20 @@ public:
 		}
 
 		if ((len >= 0)&&(data)) {
+			std::string obj(reinterpret_cast<const char *>(data),(std::size_t)len);
+			if ((_stateChecksums)&&(type != ZT_STATE_OBJECT_IDENTITY_PUBLIC)&&(type != ZT_STATE_OBJECT_IDENTITY_SECRET))
+				appendStateChecksum(obj);
+
 			// Check to see if we've already written this first. This reduces
 			// redundant writes and I/O overhead on most platforms and has
 			// little effect on others.
 			f = fopen(p,"rb");
 			if (f) {
-				char *const buf = (char *)malloc(len*4);
+				char *const buf = (char *)malloc(obj.length()*4);
 				if (buf) {
-					long l = (long)fread(buf,1,(size_t)(len*4),f);
+					long l = (long)fread(buf,1,obj.length()*4,f);
 					fclose(f);
-					if ((l == (long)len)&&(memcmp(data,buf,l) == 0)) {
+					if ((l == (long)obj.length())&&(memcmp(obj.data(),buf,l) == 0)) {
 						free(buf);
 						return;
 					}
@@ -2648,17 +4504,42 @@ public:
 				}
 			}
 
-			f = fopen(p,"wb");
+			// Write to a temporary file and rename it into place so that a crash or
+			// power loss never leaves a truncated identity, planet, or config behind.
+			// Cached peers are not synced: the core writes one every time a peer's
+			// paths change, so syncing them would put a disk flush on the hot path for
+			// a cache that is relearned from the network. A peer lost to a crash is
+			// dropped or set aside by _repairState() at the next start.
+			char tp[1040];
+			OSUtils::ztsnprintf(tp,sizeof(tp),"%s.tmp",p);
+			f = fopen(tp,"wb");
 			if ((!f)&&(dirname[0])) { // create subdirectory if it does not exist
 				OSUtils::mkdir(dirname);
-				f = fopen(p,"wb");
+				f = fopen(tp,"wb");
 			}
 			if (f) {
-				if (fwrite(data,len,1,f) != 1)
-					fprintf(stderr,"WARNING: unable to write to file: %s (I/O error)" ZT_EOL_S,p);
-				fclose(f);
 				if (secure)
-					OSUtils::lockDownFile(p,false);
+					OSUtils::lockDownFile(tp,false);
+				const bool ok = (((obj.empty())||(fwrite(obj.data(),obj.length(),1,f) == 1))&&((type == ZT_STATE_OBJECT_PEER) ? (fflush(f) == 0) : OSUtils::syncFile(f)));
+				fclose(f);
+				if (!ok) {
+					fprintf(stderr,"WARNING: unable to write to file: %s (I/O error)" ZT_EOL_S,p);
+					OSUtils::rm(tp);
+					return;
+				}
+
+				// The core only replaces identity.secret if it could not be read, so keep the old one
+				if ((type == ZT_STATE_OBJECT_IDENTITY_SECRET)&&(OSUtils::fileExists(p,false))) {
+					char qp[1100];
+					OSUtils::ztsnprintf(qp,sizeof(qp),"%s.corrupt-%lld",p,(long long)OSUtils::now());
+					if (OSUtils::rename(p,qp))
+						fprintf(stderr,"WARNING: identity.secret was unreadable and a new identity was generated; the old file was moved to %s" ZT_EOL_S,qp);
+				}
+
+				if (!OSUtils::rename(tp,p)) {
+					fprintf(stderr,"WARNING: unable to write to file: %s (unable to rename)" ZT_EOL_S,p);
+					OSUtils::rm(tp);
+				}
 			} else {
 				fprintf(stderr,"WARNING: unable to write to file: %s (unable to open)" ZT_EOL_S,p);
 			}
@@ -2779,8 +4660,18 @@ public:
 		}
 		FILE *f = fopen(p,"rb");
 		if (f) {
-			int n = (int)fread(data,1,maxlen,f);
+			// Room for a checksum trailer on an object of maxlen bytes
+			std::string obj((std::size_t)maxlen + ZT_STATE_CHECKSUM_TRAILER_SIZE,(char)0);
+			obj.resize(fread(const_cast<char *>(obj.data()),1,obj.length(),f));
 			fclose(f);
+			if (stripStateChecksum(obj) < 0) {
+				fprintf(stderr,"WARNING: %s does not match its checksum, ignoring it" ZT_EOL_S,p);
+				return -1;
+			}
+			if (obj.length() > maxlen)
+				obj.resize(maxlen);
+			memcpy(data,obj.data(),obj.length());
+			int n = (int)obj.length();
 #if ZT_VAULT_SUPPORT
 			if (_vaultEnabled && (type == ZT_STATE_OBJECT_IDENTITY_SECRET || type == ZT_STATE_OBJECT_IDENTITY_PUBLIC)) {
 				// If we've gotten here while Vault is enabled, Vault does not know the key and it's been
@@ -2844,6 +4735,7 @@ public:
 							}
 							tc->type = TcpConnection::TCP_TUNNEL_OUTGOING;
 							tc->remoteAddr = addr;
+							tc->unixPeer = false;
 							tc->lastReceive = OSUtils::now();
 							tc->parent = this;
 							tc->sock = (PhySocket *)0; // set in connect handler
@@ -2862,13 +4754,12 @@ public:
 		// working we can instantly "fail forward" to it and stop using TCP
 		// proxy fallback, which is slow.
 
+		// A per-peer DSCP marks this packet only, see Binder::udpSend()
+		const int tos = wirePacketTos(data,len);
 		if ((localSocket != -1)&&(localSocket != 0)&&(_binder.isUdpSocketValid((PhySocket *)((uintptr_t)localSocket)))) {
-			if ((ttl)&&(addr->ss_family == AF_INET)) _phy.setIp4UdpTtl((PhySocket *)((uintptr_t)localSocket),ttl);
-			const bool r = _phy.udpSend((PhySocket *)((uintptr_t)localSocket),(const struct sockaddr *)addr,data,len);
-			if ((ttl)&&(addr->ss_family == AF_INET)) _phy.setIp4UdpTtl((PhySocket *)((uintptr_t)localSocket),255);
-			return ((r) ? 0 : -1);
+			return ((_binder.udpSend(_phy,(PhySocket *)((uintptr_t)localSocket),addr,data,len,(addr->ss_family == AF_INET) ? ttl : 0,tos)) ? 0 : -1);
 		} else {
-			return ((_binder.udpSendAll(_phy,addr,data,len,ttl)) ? 0 : -1);
+			return ((_binder.udpSendAll(_phy,addr,data,len,ttl,tos)) ? 0 : -1);
 		}
 	}
 
@@ -2903,6 +4794,22 @@ public:
 		 * path even if its managed routes override this for other traffic. Will
 		 * revisit if we see recursion problems. */
 
+		{
+			Mutex::Lock _l(_localConfig_m);
+			if ((_ipv6Only)&&(remoteAddr->ss_family == AF_INET))
+				return 0;
+
+			// If paths are pinned for this peer, use only those
+			const std::vector<InetAddress> *pins = _pinnedPaths.get(ztaddr);
+			if (pins) {
+				for(std::vector<InetAddress>::const_iterator a(pins->begin());a!=pins->end();++a) {
+					if (*a == *reinterpret_cast<const InetAddress *>(remoteAddr))
+						return 1;
+				}
+				return 0;
+			}
+		}
+
 		// Check blacklists
 		const Hashtable< uint64_t,std::vector<InetAddress> > *blh = (const Hashtable< uint64_t,std::vector<InetAddress> > *)0;
 		const std::vector<InetAddress> *gbl = (const std::vector<InetAddress> *)0;
@@ -2935,6 +4842,16 @@ public:
 	inline int nodePathLookupFunction(uint64_t ztaddr, int family, struct sockaddr_storage* result)
 	{
 		const Hashtable< uint64_t, std::vector<InetAddress> >* lh = (const Hashtable< uint64_t, std::vector<InetAddress> > *)0;
+		bool ipv6Only;
+		{
+			Mutex::Lock _l(_localConfig_m);
+			ipv6Only = _ipv6Only;
+		}
+		if (ipv6Only) {
+			if (family == AF_INET)
+				return 0;
+			family = AF_INET6;
+		}
 		if (family < 0)
 			lh = (_node->prng() & 1) ? &_v4Hints : &_v6Hints;
 		else if (family == AF_INET)
@@ -2965,18 +4882,27 @@ public:
 		// Note that we check allowed IP ranges when HTTP connections are first detected in
 		// phyOnTcpData(). If we made it here the source IP is okay.
 
-		try {
-			scode = handleControlPlaneHttpRequest(tc->remoteAddr, tc->parser.method, tc->url, tc->headers, tc->readq, data, contentType);
-		}
-		catch (std::exception& exc) {
-			fprintf(stderr, "WARNING: unexpected exception processing control HTTP request: %s" ZT_EOL_S, exc.what());
-			scode = 500;
-		}
-		catch (...) {
-			fprintf(stderr, "WARNING: unexpected exception processing control HTTP request: unknown exception" ZT_EOL_S);
-			scode = 500;
+		if (apiRateLimited(tc)) {
+			scode = 429;
+		} else if ((tc->managementLocalAddr)&&(!managementRequestSigned(tc))) {
+			scode = 401;
+		} else {
+			try {
+				scode = handleControlPlaneHttpRequest(tc->remoteAddr, tc->unixPeer, tc->parser.method, tc->url, tc->headers, tc->readq, data, contentType);
+			}
+			catch (std::exception& exc) {
+				fprintf(stderr, "WARNING: unexpected exception processing control HTTP request: %s" ZT_EOL_S, exc.what());
+				scode = 500;
+			}
+			catch (...) {
+				fprintf(stderr, "WARNING: unexpected exception processing control HTTP request: unknown exception" ZT_EOL_S);
+				scode = 500;
+			}
 		}
 
+		if ((_apiAuditLog)&&(((tc->parser.method != HTTP_GET)&&(tc->parser.method != HTTP_HEAD))||(scode == 401)||(scode == 403)||(scode == 429)))
+			auditApiCall(tc,scode);
+
 		const char* scodestr;
 		switch (scode) {
 		case 200: scodestr = "OK"; break;
@@ -2984,6 +4910,7 @@ public:
 		case 401: scodestr = "Unauthorized"; break;
 		case 403: scodestr = "Forbidden"; break;
 		case 404: scodestr = "Not Found"; break;
+		case 429: scodestr = "Too Many Requests"; break;
 		case 500: scodestr = "Internal Server Error"; break;
 		case 501: scodestr = "Not Implemented"; break;
 		case 503: scodestr = "Service Unavailable"; break;
@@ -3005,6 +4932,115 @@ public:
 		_phy.setNotifyWritable(tc->sock, true);
 	}
 
+	// True if this caller has made more than apiRateLimit requests this second
+	inline bool apiRateLimited(TcpConnection *tc)
+	{
+		std::string presented;
+		std::map<std::string,std::string>::const_iterator ah(tc->headers.find("x-zt1-auth"));
+		if (ah != tc->headers.end()) {
+			presented = ah->second;
+		} else {
+			const std::size_t ap = tc->url.find("auth=");
+			if ((ap != std::string::npos)&&(ap > 0)&&((tc->url[ap - 1] == '?')||(tc->url[ap - 1] == '&')))
+				presented = tc->url.substr(ap + 5,tc->url.find('&',ap) - (ap + 5));
+		}
+		char ip[64];
+		return _apiRateLimiter.limited(ApiRateLimiter::key(presented,_authToken,(tc->unixPeer) ? std::string("unix") : std::string(tc->remoteAddr.toIpString(ip))),OSUtils::now());
+	}
+
+	// Queue a line for api-audit.log (query strings are left out since they may contain the auth token)
+	inline void auditApiCall(TcpConnection *tc,unsigned int scode)
+	{
+		char ip[64],line[1024];
+		std::map<std::string,std::string>::const_iterator actor(tc->headers.find("x-zt1-actor"));
+		OSUtils::ztsnprintf(line,sizeof(line),"%lld %s %s %s %u %s" ZT_EOL_S,
+			(long long)OSUtils::now(),
+			(tc->unixPeer) ? "unix" : tc->remoteAddr.toIpString(ip),
+			http_method_str((enum http_method)tc->parser.method),
+			tc->url.substr(0,tc->url.find('?')).c_str(),
+			scode,
+			(actor != tc->headers.end()) ? actor->second.substr(0,64).c_str() : "-");
+		if (!_apiAuditQueue.postIfRoom(std::string(line),ZT_API_AUDIT_LOG_MAX_QUEUED))
+			fprintf(stderr,"WARNING: API audit log writer is behind, dropped: %s",line);
+	}
+
+	// Background thread that appends queued lines to api-audit.log and rotates it
+	void _apiAuditMain()
+	{
+		const std::string logPath(_homePath + ZT_PATH_SEPARATOR_S "api-audit.log");
+		FILE *f = (FILE *)0;
+		std::string line;
+		for(;;) {
+			if (!_apiAuditQueue.get(line)) {
+				// Stopped, write what was queued before shutdown
+				std::vector<std::string> rest(_apiAuditQueue.drain());
+				if (rest.empty())
+					break;
+				line.clear();
+				for(std::vector<std::string>::const_iterator l(rest.begin());l!=rest.end();++l)
+					line.append(*l);
+			}
+			if (!f) {
+				f = OSUtils::fopenPrivate(logPath.c_str(),true);
+				if (!f) {
+					fprintf(stderr,"WARNING: unable to write API audit log %s" ZT_EOL_S,logPath.c_str());
+					continue;
+				}
+			}
+			if ((fwrite(line.data(),1,line.length(),f) != line.length())||(fflush(f) != 0))
+				fprintf(stderr,"WARNING: unable to write API audit log %s" ZT_EOL_S,logPath.c_str());
+			const long size = ftell(f);
+			if ((size > 0)&&((uint64_t)size > _apiAuditLogMaxSize)) {
+				fclose(f);
+				f = (FILE *)0;
+				// api-audit.log.1 is the newest rotated log, all of them keep the owner-only modes
+				const unsigned int keep = _apiAuditLogKeep;
+				char from[4096],to[4096];
+				if (keep) {
+					OSUtils::ztsnprintf(to,sizeof(to),"%s.%u",logPath.c_str(),keep);
+					OSUtils::rm(to);
+					for(unsigned int i=keep;i>0;--i) {
+						if (i > 1)
+							OSUtils::ztsnprintf(from,sizeof(from),"%s.%u",logPath.c_str(),i - 1);
+						else Utils::scopy(from,sizeof(from),logPath.c_str());
+						OSUtils::ztsnprintf(to,sizeof(to),"%s.%u",logPath.c_str(),i);
+						if ((OSUtils::fileExists(from))&&(!OSUtils::rename(from,to)))
+							fprintf(stderr,"WARNING: unable to rotate API audit log %s" ZT_EOL_S,from);
+					}
+				} else {
+					OSUtils::rm(logPath.c_str());
+				}
+			}
+		}
+		if (f)
+			fclose(f);
+	}
+
+	// True if this call of a per-packet callback should be timed
+	inline bool sampleCallback(const unsigned int cb)
+	{
+		return ((_slowCallbackThreshold != 0)&&((++_sampledCallbackCalls[cb] & (ZT_SLOW_CALLBACK_SAMPLE_RATE - 1)) == 0));
+	}
+
+	// Called by the callback trampolines below with the time each callback started
+	inline void callbackDone(const unsigned int cb,const int64_t start)
+	{
+		const unsigned int threshold = _slowCallbackThreshold;
+		if (!threshold)
+			return;
+		const int64_t now = OSUtils::now();
+		const int64_t took = now - start;
+		if (took < (int64_t)threshold)
+			return;
+		++_slowCallbacks[cb];
+		int64_t max = _slowCallbackMax[cb].load();
+		while ((took > max)&&(!_slowCallbackMax[cb].compare_exchange_weak(max,took))) {}
+		int64_t lastWarning = _slowCallbackLastWarning[cb].load();
+		if (((now - lastWarning) >= ZT_SLOW_CALLBACK_WARNING_INTERVAL)&&(_slowCallbackLastWarning[cb].compare_exchange_strong(lastWarning,now))) {
+			fprintf(stderr,"WARNING: %s callback took %lld ms; the core is stalled while callbacks run" ZT_EOL_S,ZT_SERVICE_CALLBACK_NAMES[cb],(long long)took);
+		}
+	}
+
 	inline void onHttpResponseFromClient(TcpConnection* tc)
 	{
 		_phy.close(tc->sock);
@@ -3044,11 +5080,25 @@ public:
 
 		{
 			Mutex::Lock _l(_localConfig_m);
+			if ((_ipv6Only)&&(ifaddr.ss_family == AF_INET))
+				return false;
 			for(std::vector<std::string>::const_iterator p(_interfacePrefixBlacklist.begin());p!=_interfacePrefixBlacklist.end();++p) {
 				if (!strncmp(p->c_str(),ifname,p->length()))
 					return false;
 			}
-			return _node->bondController()->allowedToBind(std::string(ifname));
+			if (!_interfacePrefixWhitelist.empty()) {
+				bool listed = false;
+				for(std::vector<std::string>::const_iterator p(_interfacePrefixWhitelist.begin());p!=_interfacePrefixWhitelist.end();++p) {
+					if (!strncmp(p->c_str(),ifname,p->length())) {
+						listed = true;
+						break;
+					}
+				}
+				if (!listed)
+					return false;
+			}
+			if (!_node->bondController()->allowedToBind(std::string(ifname)))
+				return false;
 		}
 		{
 			// Check global blacklists
@@ -3082,6 +5132,19 @@ public:
 		return true;
 	}
 
+	// Find a bindable port in the secondary port range after 'after', wrapping around, or 0 if none
+	unsigned int _findSecondaryPort(unsigned int after)
+	{
+		unsigned int p = after;
+		for(int i=0;i<=1000;++i) {
+			if ((++p > _secondaryPortRangeLast)||(p < _secondaryPortRangeFirst))
+				p = _secondaryPortRangeFirst;
+			if (_trialBind(p))
+				return p;
+		}
+		return 0;
+	}
+
 	bool _trialBind(unsigned int port)
 	{
 		struct sockaddr_in in4;
@@ -3118,18 +5181,42 @@ public:
 	}
 };
 
+// Times a core callback for the slow callback watchdog
+class _CallbackTimer
+{
+public:
+	_CallbackTimer(void *uptr,const unsigned int cb) : _s(reinterpret_cast<OneServiceImpl *>(uptr)),_cb(cb),_start(OSUtils::now()) {}
+	~_CallbackTimer() { _s->callbackDone(_cb,_start); }
+private:
+	OneServiceImpl *const _s;
+	const unsigned int _cb;
+	const int64_t _start;
+};
+
+// Times one in ZT_SLOW_CALLBACK_SAMPLE_RATE calls of a per-packet callback
+class _SampledCallbackTimer
+{
+public:
+	_SampledCallbackTimer(void *uptr,const unsigned int cb) : _s(reinterpret_cast<OneServiceImpl *>(uptr)),_cb(cb),_start((_s->sampleCallback(cb)) ? OSUtils::now() : 0) {}
+	~_SampledCallbackTimer() { if (_start) _s->callbackDone(_cb,_start); }
+private:
+	OneServiceImpl *const _s;
+	const unsigned int _cb;
+	const int64_t _start;
+};
+
 static int SnodeVirtualNetworkConfigFunction(ZT_Node *node,void *uptr,void *tptr,uint64_t nwid,void **nuptr,enum ZT_VirtualNetworkConfigOperation op,const ZT_VirtualNetworkConfig *nwconf)
-{ return reinterpret_cast<OneServiceImpl *>(uptr)->nodeVirtualNetworkConfigFunction(nwid,nuptr,op,nwconf); }
+{ _CallbackTimer t(uptr,ZT_SERVICE_CALLBACK_NETWORK_CONFIG); return reinterpret_cast<OneServiceImpl *>(uptr)->nodeVirtualNetworkConfigFunction(nwid,nuptr,op,nwconf); }
 static void SnodeEventCallback(ZT_Node *node,void *uptr,void *tptr,enum ZT_Event event,const void *metaData)
-{ reinterpret_cast<OneServiceImpl *>(uptr)->nodeEventCallback(event,metaData); }
+{ _CallbackTimer t(uptr,ZT_SERVICE_CALLBACK_EVENT); reinterpret_cast<OneServiceImpl *>(uptr)->nodeEventCallback(event,metaData); }
 static void SnodeStatePutFunction(ZT_Node *node,void *uptr,void *tptr,enum ZT_StateObjectType type,const uint64_t id[2],const void *data,int len)
-{ reinterpret_cast<OneServiceImpl *>(uptr)->nodeStatePutFunction(type,id,data,len); }
+{ _CallbackTimer t(uptr,ZT_SERVICE_CALLBACK_STATE_PUT); reinterpret_cast<OneServiceImpl *>(uptr)->nodeStatePutFunction(type,id,data,len); }
 static int SnodeStateGetFunction(ZT_Node *node,void *uptr,void *tptr,enum ZT_StateObjectType type,const uint64_t id[2],void *data,unsigned int maxlen)
-{ return reinterpret_cast<OneServiceImpl *>(uptr)->nodeStateGetFunction(type,id,data,maxlen); }
+{ _CallbackTimer t(uptr,ZT_SERVICE_CALLBACK_STATE_GET); return reinterpret_cast<OneServiceImpl *>(uptr)->nodeStateGetFunction(type,id,data,maxlen); }
 static int SnodeWirePacketSendFunction(ZT_Node *node,void *uptr,void *tptr,int64_t localSocket,const struct sockaddr_storage *addr,const void *data,unsigned int len,unsigned int ttl)
-{ return reinterpret_cast<OneServiceImpl *>(uptr)->nodeWirePacketSendFunction(localSocket,addr,data,len,ttl); }
+{ _SampledCallbackTimer t(uptr,ZT_SERVICE_CALLBACK_WIRE_PACKET_SEND); return reinterpret_cast<OneServiceImpl *>(uptr)->nodeWirePacketSendFunction(localSocket,addr,data,len,ttl); }
 static void SnodeVirtualNetworkFrameFunction(ZT_Node *node,void *uptr,void *tptr,uint64_t nwid,void **nuptr,uint64_t sourceMac,uint64_t destMac,unsigned int etherType,unsigned int vlanId,const void *data,unsigned int len)
-{ reinterpret_cast<OneServiceImpl *>(uptr)->nodeVirtualNetworkFrameFunction(nwid,nuptr,sourceMac,destMac,etherType,vlanId,data,len); }
+{ _SampledCallbackTimer t(uptr,ZT_SERVICE_CALLBACK_VIRTUAL_NETWORK_FRAME); reinterpret_cast<OneServiceImpl *>(uptr)->nodeVirtualNetworkFrameFunction(nwid,nuptr,sourceMac,destMac,etherType,vlanId,data,len); }
 static int SnodePathCheckFunction(ZT_Node *node,void *uptr,void *tptr,uint64_t ztaddr,int64_t localSocket,const struct sockaddr_storage *remoteAddr)
 { return reinterpret_cast<OneServiceImpl *>(uptr)->nodePathCheckFunction(ztaddr,localSocket,remoteAddr); }
 static int SnodePathLookupFunction(ZT_Node *node,void *uptr,void *tptr,uint64_t ztaddr,int family,struct sockaddr_storage *result)
@@ -3217,6 +5304,24 @@ static int ShttpOnMessageComplete(http_parser *parser)
 
 } // anonymous namespace
 
+bool OneService::checkLocalConf(const std::string &buf,std::vector< std::pair<std::string,std::string> > &errors,std::vector< std::pair<std::string,std::string> > &warnings)
+{
+	json lc;
+	std::string err;
+	if (!_parseLocalConf(buf,lc,err)) {
+		errors.push_back(std::pair<std::string,std::string>(std::string(),err));
+		return false;
+	}
+	_checkLocalConf(lc,errors,warnings);
+	return errors.empty();
+}
+
+const char *const OneService::knownSettings[] = {
+	"primaryPort","portFallback","secondaryPort","tertiaryPort","allowSecondaryPort","secondaryPortMode","secondaryPortRange","portMappingEnabled","allowTcpFallbackRelay","ipv6Only","noRelay","preferFamily","lowPower",
+	"softwareUpdate","softwareUpdateChannel","softwareUpdateDist","interfacePrefixBlacklist","interfacePrefixWhitelist","allowManagementFrom","managementNetworks","managementIdentities",
+	"bind","apiRateLimit","apiAuditLog","apiAuditLogMaxSize","apiAuditLogKeep","apiUnixSocket","slowCallbackWarning","trafficAlert","dscp","rootDscp","aqmMaxEnqueuedPackets","rxQueueSize","txQueueSize","peerIdleTimeout","peerCacheRetention","stateChecksums","pathMetadataCommand","multipathMode","defaultBondingPolicy","policies","peerSpecificBonds","controllerDbPath","controllerDb","controllerHistory","redis","vault",(const char *)0
+};
+
 std::string OneService::platformDefaultHomePath()
 {
 	return OSUtils::platformDefaultHomePath();
@@ -16,6 +16,9 @@
 
 #include <string>
 #include <vector>
+#include <utility>
+
+#include "../include/ZeroTierOne.h"
 
 namespace ZeroTier {
 
@@ -91,8 +94,49 @@ public:
 		 * Allow configuration of DNS for the network
 		 */
 		bool allowDNS;
+
+		/**
+		 * Stop passing frames between the network and the local interface (membership is kept)
+		 */
+		bool paused;
+
+		/**
+		 * Local nickname for this network (never sent to the controller or peers)
+		 */
+		std::string nickname;
+
+		/**
+		 * Token presented to the controller for automatic authorization (empty for none)
+		 */
+		std::string joinToken;
+
+		/**
+		 * Keepalive interval in ms for this network's controller and members (0 for the default)
+		 */
+		unsigned int keepalive;
 	};
 
+	/**
+	 * Keys that local.conf "settings" may contain, terminated by NULL
+	 *
+	 * Both the service and "zerotier-one -c" warn about keys not listed
+	 * here, so new settings must be added to it.
+	 */
+	static const char *const knownSettings[];
+
+	/**
+	 * Check the contents of a local.conf the way the service reads it
+	 *
+	 * "zerotier-one -c" and -t use this so they reject what the service
+	 * would reject or ignore, and nothing else.
+	 *
+	 * @param buf File contents
+	 * @param errors Filled with (where, what) for the parse error or for values the service ignores
+	 * @param warnings Filled with (where, what) for unknown sections and settings
+	 * @return True if there are no errors
+	 */
+	static bool checkLocalConf(const std::string &buf,std::vector< std::pair<std::string,std::string> > &errors,std::vector< std::pair<std::string,std::string> > &warnings);
+
 	/**
 	 * @return Platform default home path or empty string if this platform doesn't have one
 	 */
@@ -0,0 +1,72 @@
+/*
+ * Copyright (c)2019 ZeroTier, Inc.
+ *
+ * Use of this software is governed by the Business Source License included
+ * in the LICENSE.TXT file in the project's root directory.
+ *
+ * Change Date: 2025-01-01
+ *
+ * On the date above, in accordance with the Business Source License, use
+ * of this software will be governed by version 2.0 of the Apache License.
+ */
+/****/
+
+#ifndef ZT_STATECHECKSUM_HPP
+#define ZT_STATECHECKSUM_HPP
+
+#include <string.h>
+
+#include <string>
+
+#include "../node/SHA512.hpp"
+
+// Trailer on checksummed state objects: magic then the first 8 bytes of SHA-512 of the object
+#define ZT_STATE_CHECKSUM_MAGIC "ZTCK"
+#define ZT_STATE_CHECKSUM_MAGIC_SIZE 4
+#define ZT_STATE_CHECKSUM_SIZE 8
+#define ZT_STATE_CHECKSUM_TRAILER_SIZE (ZT_STATE_CHECKSUM_MAGIC_SIZE + ZT_STATE_CHECKSUM_SIZE)
+
+namespace ZeroTier {
+
+/**
+ * Append a checksum trailer to a state object (settings.stateChecksums)
+ *
+ * Only objects that nothing but the core reads get a trailer: the planet,
+ * moons, network configs, and cached peers. Identities are text that
+ * people and other tools read, and already fail to parse when damaged.
+ *
+ * @param obj Object as written by the core, trailer is appended
+ */
+static inline void appendStateChecksum(std::string &obj)
+{
+	uint8_t h[ZT_SHA512_DIGEST_SIZE];
+	SHA512(h,obj.data(),(unsigned int)obj.length());
+	obj.append(ZT_STATE_CHECKSUM_MAGIC,ZT_STATE_CHECKSUM_MAGIC_SIZE);
+	obj.append(reinterpret_cast<const char *>(h),ZT_STATE_CHECKSUM_SIZE);
+}
+
+/**
+ * Check and remove a state object's checksum trailer
+ *
+ * Objects without a trailer were written with checksums off and are
+ * returned as they are, so the setting can be switched either way.
+ *
+ * @param obj Object as read from disk, trailer is removed if it matches
+ * @return 1 if a matching trailer was removed, 0 if there is none, -1 if the object does not match its trailer
+ */
+static inline int stripStateChecksum(std::string &obj)
+{
+	if ((obj.length() < ZT_STATE_CHECKSUM_TRAILER_SIZE)||(memcmp(obj.data() + (obj.length() - ZT_STATE_CHECKSUM_TRAILER_SIZE),ZT_STATE_CHECKSUM_MAGIC,ZT_STATE_CHECKSUM_MAGIC_SIZE) != 0))
+		return 0;
+	const unsigned int len = (unsigned int)(obj.length() - ZT_STATE_CHECKSUM_TRAILER_SIZE);
+	uint8_t h[ZT_SHA512_DIGEST_SIZE];
+	SHA512(h,obj.data(),len);
+	if (memcmp(h,obj.data() + (len + ZT_STATE_CHECKSUM_MAGIC_SIZE),ZT_STATE_CHECKSUM_SIZE) != 0)
+		return -1;
+	obj.resize(len);
+	return 1;
+}
+
+} // namespace ZeroTier
+
+#endif
@@ -0,0 +1,84 @@
+/*
+ * Copyright (c)2019 ZeroTier, Inc.
+ *
+ * Use of this software is governed by the Business Source License included
+ * in the LICENSE.TXT file in the project's root directory.
+ *
+ * Change Date: 2025-01-01
+ *
+ * On the date above, in accordance with the Business Source License, use
+ * of this software will be governed by version 2.0 of the Apache License.
+ */
+/****/
+
+#ifndef ZT_TRAFFICALERT_HPP
+#define ZT_TRAFFICALERT_HPP
+
+#include <stdint.h>
+
+#include <vector>
+#include <utility>
+
+#include "../include/ZeroTierOne.h"
+
+// Minimum delay between traffic alerts for the same network (settings.trafficAlert)
+#define ZT_TRAFFIC_ALERT_INTERVAL 60000
+
+namespace ZeroTier {
+
+// Time and traffic counters of a network at one point, used to compute rates
+typedef std::pair< int64_t,ZT_VirtualNetworkStats > NetworkStatsSample;
+
+/**
+ * Check a network's recent stats samples for a traffic alert
+ *
+ * The latest interval is compared against the thresholds and against the
+ * average over the earlier samples. A zero multicast or ethertype threshold
+ * disables that check. Alerts are limited to one per ZT_TRAFFIC_ALERT_INTERVAL.
+ *
+ * @param samples Time and cumulative stats, oldest first (at least three needed)
+ * @param spikeFactor Alert if frames/s exceeds this multiple of the average
+ * @param minPacketsPerSecond Minimum frames/s for a spike alert
+ * @param multicastPacketsPerSecond Multicast/broadcast frames/s threshold
+ * @param unknownEtherTypePacketsPerSecond Unknown ethertype frames/s threshold
+ * @param lastAlert Time of this network's last alert or 0 for none
+ * @param now Current time
+ * @param rate Set to the frames/s that triggered the alert
+ * @param avgRate Set to the average frames/s over the earlier samples
+ * @return "multicast", "ethertype", "spike", or NULL if no alert is due
+ */
+static inline const char *trafficAlert(const std::vector<NetworkStatsSample> &samples,unsigned int spikeFactor,unsigned int minPacketsPerSecond,unsigned int multicastPacketsPerSecond,unsigned int unknownEtherTypePacketsPerSecond,int64_t lastAlert,int64_t now,uint64_t &rate,uint64_t &avgRate)
+{
+	if ((samples.size() < 3)||((now - lastAlert) < ZT_TRAFFIC_ALERT_INTERVAL))
+		return (const char *)0;
+	const NetworkStatsSample &first = samples.front();
+	const NetworkStatsSample &prev = samples[samples.size() - 2];
+	const NetworkStatsSample &cur = samples.back();
+	const int64_t dt = cur.first - prev.first;
+	const int64_t windowDt = prev.first - first.first;
+	if ((dt <= 0)||(windowDt <= 0))
+		return (const char *)0;
+
+	const uint64_t pps = (((cur.second.rxPackets + cur.second.txPackets) - (prev.second.rxPackets + prev.second.txPackets)) * 1000ULL) / (uint64_t)dt;
+	const uint64_t mcPps = (((cur.second.rxMulticast + cur.second.txMulticast) - (prev.second.rxMulticast + prev.second.txMulticast)) * 1000ULL) / (uint64_t)dt;
+	const uint64_t uePps = (((cur.second.rxUnknownEtherType + cur.second.txUnknownEtherType) - (prev.second.rxUnknownEtherType + prev.second.txUnknownEtherType)) * 1000ULL) / (uint64_t)dt;
+	avgRate = (((prev.second.rxPackets + prev.second.txPackets) - (first.second.rxPackets + first.second.txPackets)) * 1000ULL) / (uint64_t)windowDt;
+
+	if ((multicastPacketsPerSecond)&&(mcPps >= multicastPacketsPerSecond)) {
+		rate = mcPps;
+		return "multicast";
+	}
+	if ((unknownEtherTypePacketsPerSecond)&&(uePps >= unknownEtherTypePacketsPerSecond)) {
+		rate = uePps;
+		return "ethertype";
+	}
+	if ((pps >= minPacketsPerSecond)&&(pps > (avgRate * spikeFactor))) {
+		rate = pps;
+		return "spike";
+	}
+	return (const char *)0;
+}
+
+} // namespace ZeroTier
+
+#endif
@@ -11,6 +11,24 @@ _get_network_ids ()
     fi
 }
 
+_get_moon_ids ()
+{
+    if [[ "$OSTYPE" == "darwin"* ]]; then
+        COMPREPLY=($(compgen -W "$(ls -1 /Library/Application\ Support/ZeroTier/One/moons.d 2>/dev/null | cut -c 1-16)" -- ${cur}))
+    else
+        COMPREPLY=($(compgen -W "$(ls -1 /var/lib/zerotier-one/moons.d 2>/dev/null | cut -c 1-16)" -- ${cur}))
+    fi
+}
+
+_get_peer_addresses ()
+{
+    if [[ "$OSTYPE" == "darwin"* ]]; then
+        COMPREPLY=($(compgen -W "$(ls -1 /Library/Application\ Support/ZeroTier/One/peers.d 2>/dev/null | cut -c 1-10)" -- ${cur}))
+    else
+        COMPREPLY=($(compgen -W "$(ls -1 /var/lib/zerotier-one/peers.d 2>/dev/null | cut -c 1-10)" -- ${cur}))
+    fi
+}
+
 _get_network_ids_from_history ()
 {
     COMPREPLY=($(compgen -W "$(fc -l -1000 -1 | sed -n 's/.*\([[:xdigit:]]\{16\}\).*/\1/p')" -- ${cur}))
@@ -25,7 +43,7 @@ _zerotier-cli_completions()
 
     case ${COMP_CWORD} in
         1)
-            COMPREPLY=($(compgen -W "info listpeers peers listnetworks join leave set get listmoons orbit deorbit" -- ${cur}))
+            COMPREPLY=($(compgen -W "info listpeers peers listnetworks join leave set get listmoons orbit deorbit mtuprobe panic rearm listbonds bond dump" -- ${cur}))
             ;;
         2)
             case ${prev} in
@@ -41,6 +59,28 @@ _zerotier-cli_completions()
                 get)
                     _get_network_ids
                     ;;
+                deorbit)
+                    _get_moon_ids
+                    ;;
+                mtuprobe)
+                    _get_peer_addresses
+                    ;;
+                bond)
+                    COMPREPLY=($(compgen -W "list" -- ${cur}))
+                    ;;
+                *)
+                    COMPREPLY=()
+                    ;;
+            esac
+            ;;
+        3)
+            case ${COMP_WORDS[1]} in
+                set)
+                    COMPREPLY=($(compgen -W "allowManaged= allowGlobal= allowDefault= allowDNS= paused=" -- ${cur}))
+                    ;;
+                bond)
+                    COMPREPLY=($(compgen -W "show rotate" -- ${cur}))
+                    ;;
                 *)
                     COMPREPLY=()
                     ;;